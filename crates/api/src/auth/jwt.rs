@@ -5,6 +5,16 @@ static KEYS: Lazy<Keys> = Lazy::new(|| {
     Keys::new(secret.as_bytes())
 });
 
+// admin tokens are minted out of band with their own secret, none of the authorize routes
+// can issue one. the admin routes stay closed while it is unset or equals `JWT_SECRET`.
+static ADMIN_KEYS: Lazy<Option<Keys>> = Lazy::new(|| {
+    let secret = std::env::var("ADMIN_JWT_SECRET").ok()?;
+    if secret.is_empty() || std::env::var("JWT_SECRET").is_ok_and(|s| s == secret) {
+        return None;
+    }
+    Some(Keys::new(secret.as_bytes()))
+});
+
 pub async fn protected(claims: Claims) -> Result<String, AuthError> {
     // Send the protected data to the user
     Ok(format!(
@@ -123,6 +133,37 @@ where
     }
 }
 
+/// bearer claims signed with `ADMIN_JWT_SECRET` of an address configured in `api.admins`
+pub struct AdminClaims(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminClaims
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let keys = ADMIN_KEYS.as_ref().ok_or(AuthError::Forbidden)?;
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+        let claims = decode::<Claims>(bearer.token(), &keys.decoding, &Validation::default())
+            .map_err(|_| AuthError::Forbidden)?
+            .claims;
+        let Extension(app_state) = parts
+            .extract::<Extension<Arc<AppState>>>()
+            .await
+            .map_err(|_| AuthError::Forbidden)?;
+        if !app_state.admins.contains(&claims.address.to_lowercase()) {
+            return Err(AuthError::Forbidden);
+        }
+
+        Ok(AdminClaims(claims))
+    }
+}
+
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
@@ -130,6 +171,7 @@ impl IntoResponse for AuthError {
             AuthError::MissingCredentials => (StatusCode::BAD_REQUEST, "Missing credentials"),
             AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Token creation error"),
             AuthError::InvalidToken => (StatusCode::BAD_REQUEST, "Invalid token"),
+            AuthError::Forbidden => (StatusCode::FORBIDDEN, "Admin only"),
         };
         let body = Json(json!({
             "error": error_message,
@@ -177,4 +219,5 @@ pub enum AuthError {
     MissingCredentials,
     TokenCreation,
     InvalidToken,
+    Forbidden,
 }
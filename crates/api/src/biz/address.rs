@@ -1,14 +1,24 @@
 use axum::extract::Query;
+use bigdecimal::BigDecimal;
 use common::consts;
 use entities::{
     address_token_balances::Model as TokenBalanceModel, addresses::Model as AddressModel,
     tokens::Model as TokenModel,
 };
-use repo::dal::{address::Query as AddressQuery, token_balance::Query as TokenBalanceQuery};
+use repo::dal::{
+    address::Query as AddressQuery, current_token_balance::Query as CurrentTokenQuery,
+    token::Query as TokenQuery, token_balance::Query as TokenBalanceQuery,
+    token_instance::Query as TokenInstanceQuery,
+};
 
 use crate::checker::base::check_address;
 
+use super::token_instance::conv_instance_model_to_resp;
+
 use super::*;
+
+const MAX_PAGE_SIZE: u64 = 100;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AddressTokenResp {
     pub token: TokenResp,
@@ -130,6 +140,128 @@ pub async fn get_address_tokens(
     Ok(Json(BaseResponse::success(resp)))
 }
 
+pub async fn get_address_nfts(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(params): Query<TokenBalanceQueryParams>,
+) -> Result<Json<BaseResponse<Vec<AddressTokenResp>>>, AppError> {
+    let conn = get_conn(&state);
+
+    let address = match check_address(id) {
+        Ok(h) => h,
+        Err(e) => return Err(e),
+    };
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(20).clamp(1, MAX_PAGE_SIZE);
+
+    // ERC-721 owners live on the instances, ERC-1155 holders on the per-id current balances
+    let mut holdings = vec![];
+    match params.r#type.as_str() {
+        consts::ERC721 => {
+            let (instances, _) = TokenInstanceQuery::find_by_owner(conn, address, page, page_size)
+                .await
+                .map_err(AppError::from)?;
+            for instance in instances.into_iter() {
+                holdings.push((
+                    instance.token_contract_address_hash.clone(),
+                    instance.token_id.clone(),
+                    Some(BigDecimal::from(1)),
+                    Some(instance),
+                ));
+            }
+        }
+        consts::ERC1155 => {
+            let (balances, _) =
+                CurrentTokenQuery::find_token_ids_by_address(conn, address, page, page_size)
+                    .await
+                    .map_err(AppError::from)?;
+            let token_ids = balances
+                .iter()
+                .filter_map(|b| {
+                    b.token_id
+                        .clone()
+                        .map(|id| (b.token_contract_address_hash.clone(), id))
+                })
+                .collect::<Vec<_>>();
+            let mut instances = TokenInstanceQuery::find_by_token_ids(conn, token_ids)
+                .await
+                .map_err(AppError::from)?
+                .into_iter()
+                .map(|i| {
+                    (
+                        (i.token_contract_address_hash.clone(), i.token_id.clone()),
+                        i,
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            for balance in balances.into_iter() {
+                let Some(token_id) = balance.token_id else {
+                    continue;
+                };
+                let instance = instances.remove(&(
+                    balance.token_contract_address_hash.clone(),
+                    token_id.clone(),
+                ));
+                holdings.push((
+                    balance.token_contract_address_hash,
+                    token_id,
+                    balance.value,
+                    instance,
+                ));
+            }
+        }
+        _ => return Err(AppError::from(CoreError::Param(params.r#type))),
+    }
+
+    let contracts = holdings
+        .iter()
+        .map(|(contract, _, _, _)| contract.clone())
+        .collect::<Vec<_>>();
+    let tokens = TokenQuery::find_by_contract_addresses(conn, contracts)
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .map(|t| (t.contract_address_hash.clone(), t))
+        .collect::<HashMap<_, _>>();
+
+    let mut resp = vec![];
+    for (contract, token_id, value, instance) in holdings.iter() {
+        let Some(token) = tokens.get(contract) else {
+            continue;
+        };
+        let token_instance = match instance {
+            Some(i) => conv_instance_model_to_resp(i),
+            None => TokenInstanceResp {
+                id: Some(token_id.to_string()),
+                ..Default::default()
+            },
+        };
+        resp.push(AddressTokenResp {
+            token: conv_token_model_to_resp(token),
+            token_id: Some(token_id.to_string()),
+            token_instance: Some(token_instance),
+            value: value.as_ref().map(|v| v.to_string()),
+        });
+    }
+
+    Ok(Json(BaseResponse::success(resp)))
+}
+
+pub fn conv_token_model_to_resp(token: &TokenModel) -> TokenResp {
+    TokenResp {
+        address: chain_ident!(token.contract_address_hash.clone()),
+        circulating_market_cap: token.circulating_market_cap.map(|f| f.to_string()),
+        decimals: token.decimals.map(|f| f.to_string()),
+        exchange_rate: None,
+        holders: token.holder_count.map(|f| f.to_string()),
+        icon_url: token.icon_url.clone(),
+        name: token.name.clone(),
+        symbol: token.symbol.clone(),
+        total_supply: token.total_supply.as_ref().map(|f| f.to_string()),
+        r#type: token.r#type.clone(),
+    }
+}
+
 fn conv_model_to_resp(model: &TokenBalanceModel, token: &TokenModel) -> AddressTokenResp {
     let mut resp = AddressTokenResp {
         token: TokenResp {
@@ -1,5 +1,9 @@
+use common::consts;
 use entities::blocks::Model;
-use repo::dal::block::Query as DbQuery;
+use repo::dal::{
+    block::Query as DbQuery, block_second_degree_relation::Query as RelationQuery,
+    last_fetched_counter::Query as CounterQuery,
+};
 use sea_orm::prelude::Decimal;
 
 use super::*;
@@ -20,9 +24,16 @@ pub struct BlockResp {
     pub base_fee_per_gas: Option<Decimal>,
     pub total_transaction: u64,
     pub total_withdraw: u64,
+    pub consensus: bool,
+    pub finalized: bool,
+    pub uncles: Vec<String>,
 }
 
-fn conv_model_to_resp(model: Model) -> BlockResp {
+fn conv_model_to_resp(
+    model: Model,
+    finalized_number: Option<Decimal>,
+    uncles: Vec<String>,
+) -> BlockResp {
     BlockResp {
         difficulty: model.difficulty,
         gas_limit: model.gas_limit,
@@ -38,6 +49,10 @@ fn conv_model_to_resp(model: Model) -> BlockResp {
         base_fee_per_gas: model.base_fee_per_gas,
         total_transaction: 0, // need graphQL to finish
         total_withdraw: 0,
+        consensus: model.consensus,
+        finalized: model.consensus
+            && finalized_number.is_some_and(|n| Decimal::from(model.number) <= n),
+        uncles,
     }
 }
 
@@ -58,8 +73,24 @@ pub async fn get_block(
             .map_err(AppError::from)?
     };
 
-    match block {
-        Some(block) => Ok(Json(BaseResponse::success(conv_model_to_resp(block)))),
-        None => Err(AppError::from(CoreError::NotFound)),
-    }
+    let finalized_number = CounterQuery::find_by_type(conn, consts::LAST_FINALIZED_BLOCK_NUMBER)
+        .await
+        .map_err(AppError::from)?
+        .and_then(|counter| counter.value);
+
+    let Some(block) = block else {
+        return Err(AppError::from(CoreError::NotFound));
+    };
+    let uncles = RelationQuery::find_by_nephew_hash(conn, block.hash.clone())
+        .await
+        .map_err(AppError::from)?
+        .into_iter()
+        .map(|relation| chain_ident!(relation.uncle_hash))
+        .collect();
+
+    Ok(Json(BaseResponse::success(conv_model_to_resp(
+        block,
+        finalized_number,
+        uncles,
+    ))))
 }
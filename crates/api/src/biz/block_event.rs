@@ -0,0 +1,50 @@
+use axum::extract::Query;
+use entities::block_events::Model;
+use repo::dal::block_event::Query as DbQuery;
+use serde_json::Value;
+
+use super::*;
+
+const MAX_LIMIT: u64 = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockEventResp {
+    pub sequence: i64,
+    pub event_type: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockEventQueryParams {
+    pub after: i64,
+    pub limit: Option<u64>,
+}
+
+/// block events following the `after` sequence, consumers replay the gaps they detect with it.
+pub async fn get_block_events(
+    Extension(state): Extension<Arc<AppState>>,
+    Query(params): Query<BlockEventQueryParams>,
+) -> Result<Json<BaseResponse<Vec<BlockEventResp>>>, AppError> {
+    let conn = get_conn(&state);
+    let limit = params.limit.unwrap_or(100).clamp(1, MAX_LIMIT);
+
+    let res = DbQuery::find_after(conn, params.after, limit)
+        .await
+        .map_err(AppError::from)?;
+
+    Ok(Json(BaseResponse::success(
+        res.iter().map(conv_model_to_resp).collect(),
+    )))
+}
+
+fn conv_model_to_resp(model: &Model) -> BlockEventResp {
+    BlockEventResp {
+        sequence: model.sequence,
+        event_type: model.event_type.clone(),
+        block_number: model.block_number,
+        block_hash: chain_ident!(model.block_hash.clone()),
+        payload: model.payload.clone(),
+    }
+}
@@ -0,0 +1,303 @@
+use axum::extract::Query;
+use common::consts::LOG_RECEIVER_CONTRACTS_VERSION;
+use entities::log_receiver_contract::Model;
+use ethers::utils::keccak256;
+use repo::dal::{
+    last_fetched_counter::Mutation as CounterMutation,
+    log_receiver_chain::Mutation as ChainMutation,
+    log_receiver_contract::{Mutation as ContractMutation, Query as ContractQuery},
+};
+use sea_orm::{DatabaseTransaction, TransactionTrait};
+use serde_json::Value;
+
+use crate::{
+    auth::jwt::AdminClaims,
+    checker::base::{check_address, check_hash},
+};
+
+use super::*;
+
+const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogReceiverContractResp {
+    pub id: i64,
+    pub chain_name: String,
+    pub chain_id: i64,
+    pub address: String,
+    pub event_sign: String,
+    pub paused: bool,
+    pub start_height: Option<i64>,
+    pub end_height: Option<i64>,
+}
+
+/// `event` is an event signature like `Transfer(address,address,uint256)` or its topic,
+/// `abi` the abi item of the event and only read if `event` is absent. either is stored as
+/// the topic.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateLogReceiverContract {
+    pub chain_name: String,
+    pub chain_id: i64,
+    pub address: String,
+    pub event: Option<String>,
+    pub abi: Option<Value>,
+    #[validate(range(min = 0))]
+    pub start_height: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogReceiverContractsParams {
+    pub chain_id: i64,
+    pub page_size: Option<u64>,
+    pub page: Option<u64>,
+}
+
+pub async fn create_log_receiver_contract(
+    AdminClaims(_): AdminClaims,
+    Extension(state): Extension<Arc<AppState>>,
+    Json(payload): Json<CreateLogReceiverContract>,
+) -> Result<Json<BaseResponse<LogReceiverContractResp>>, AppError> {
+    payload.validate().map_err(AppError::from)?;
+    let address = chain_ident!(check_address(payload.address)?);
+    let event_sign = match (payload.event, payload.abi) {
+        (Some(event), _) => check_event_sign(event)?,
+        (None, Some(abi)) => abi_event_sign(&abi)?,
+        (None, None) => return Err(AppError::from(CoreError::Param("event".to_string()))),
+    };
+
+    let conn = get_conn(&state);
+    let form_data = Model {
+        id: 0,
+        chain_name: payload.chain_name,
+        chain_id: payload.chain_id,
+        address,
+        event_sign,
+        paused: false,
+        start_height: payload.start_height,
+        end_height: None,
+    };
+    let txn = conn.begin().await.map_err(AppError::from)?;
+    let model = ContractMutation::create_scanner_contract(&txn, form_data)
+        .await
+        .map_err(AppError::from)?;
+    commit_changed(txn).await?;
+
+    Ok(Json(BaseResponse::success(conv_model_to_resp(&model))))
+}
+
+pub async fn get_log_receiver_contracts(
+    AdminClaims(_): AdminClaims,
+    Extension(state): Extension<Arc<AppState>>,
+    Query(params): Query<LogReceiverContractsParams>,
+) -> Result<Json<BaseResponse<Vec<LogReceiverContractResp>>>, AppError> {
+    let conn = get_conn(&state);
+    let (models, _) = ContractQuery::find_scanner_contract_in_page(
+        conn,
+        params.chain_id,
+        params.page.unwrap_or(1).max(1),
+        params.page_size.unwrap_or(20).clamp(1, MAX_PAGE_SIZE),
+    )
+    .await
+    .map_err(AppError::from)?;
+
+    Ok(Json(BaseResponse::success(
+        models.iter().map(conv_model_to_resp).collect(),
+    )))
+}
+
+pub async fn pause_log_receiver_contract(
+    claims: AdminClaims,
+    state: Extension<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<BaseResponse<LogReceiverContractResp>>, AppError> {
+    set_paused(claims, state, id, true).await
+}
+
+pub async fn resume_log_receiver_contract(
+    claims: AdminClaims,
+    state: Extension<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<BaseResponse<LogReceiverContractResp>>, AppError> {
+    set_paused(claims, state, id, false).await
+}
+
+pub async fn delete_log_receiver_contract(
+    AdminClaims(_): AdminClaims,
+    Extension(state): Extension<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<BaseResponse<i64>>, AppError> {
+    let conn = get_conn(&state);
+    let txn = conn.begin().await.map_err(AppError::from)?;
+    let res = ContractMutation::delete(&txn, id)
+        .await
+        .map_err(AppError::from)?;
+    if res.rows_affected == 0 {
+        return Err(AppError::from(CoreError::NotFound));
+    }
+    // the backfill cursors `<task_name>#<id>` go with the contract
+    ChainMutation::delete_contract_tasks(&txn, id)
+        .await
+        .map_err(AppError::from)?;
+    commit_changed(txn).await?;
+
+    Ok(Json(BaseResponse::success(id)))
+}
+
+async fn set_paused(
+    AdminClaims(_): AdminClaims,
+    Extension(state): Extension<Arc<AppState>>,
+    id: i64,
+    paused: bool,
+) -> Result<Json<BaseResponse<LogReceiverContractResp>>, AppError> {
+    let conn = get_conn(&state);
+    let txn = conn.begin().await.map_err(AppError::from)?;
+    let res = ContractMutation::set_paused(&txn, id, paused)
+        .await
+        .map_err(AppError::from)?;
+    if res.rows_affected == 0 {
+        return Err(AppError::from(CoreError::NotFound));
+    }
+    commit_changed(txn).await?;
+
+    match ContractQuery::find_by_id(conn, id)
+        .await
+        .map_err(AppError::from)?
+    {
+        Some(model) => Ok(Json(BaseResponse::success(conv_model_to_resp(&model)))),
+        None => Err(AppError::from(CoreError::NotFound)),
+    }
+}
+
+/// bump the version of the contracts together with their change, the scanner reloads them
+/// once it sees the new version.
+async fn commit_changed(txn: DatabaseTransaction) -> Result<(), AppError> {
+    CounterMutation::increment(&txn, LOG_RECEIVER_CONTRACTS_VERSION)
+        .await
+        .map_err(AppError::from)?;
+    txn.commit().await.map_err(AppError::from)
+}
+
+/// the topic of an event signature or of the topic itself
+fn check_event_sign(event: String) -> Result<String, AppError> {
+    let event = event.split_whitespace().collect::<String>();
+    if event.starts_with("0x") || event.starts_with("0X") {
+        return Ok(chain_ident!(check_hash(event)?));
+    }
+
+    match event.split_once('(') {
+        Some((name, _))
+            if !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && event.ends_with(')') =>
+        {
+            Ok(chain_ident!(keccak256(event)))
+        }
+        _ => Err(AppError::from(CoreError::Param(event))),
+    }
+}
+
+/// the topic of an event abi item, hashed from its signature like
+/// `Transfer(address,address,uint256)`
+fn abi_event_sign(abi: &Value) -> Result<String, AppError> {
+    let param = || AppError::from(CoreError::Param("abi".to_string()));
+    if abi.get("type").is_some_and(|t| t != "event") {
+        return Err(param());
+    }
+    let name = abi.get("name").and_then(Value::as_str).ok_or_else(param)?;
+    let inputs = abi
+        .get("inputs")
+        .and_then(Value::as_array)
+        .ok_or_else(param)?;
+
+    check_event_sign(format!(
+        "{}({})",
+        name,
+        abi_param_types(inputs).ok_or_else(param)?
+    ))
+}
+
+fn abi_param_types(params: &[Value]) -> Option<String> {
+    let mut types = vec![];
+    for param in params.iter() {
+        let ty = param.get("type")?.as_str()?;
+        match ty.strip_prefix("tuple") {
+            // tuples are written as their components, followed by their array suffix
+            Some(suffix) => {
+                let components = param.get("components")?.as_array()?;
+                types.push(format!("({}){}", abi_param_types(components)?, suffix));
+            }
+            None => types.push(ty.to_string()),
+        }
+    }
+    Some(types.join(","))
+}
+
+fn conv_model_to_resp(model: &Model) -> LogReceiverContractResp {
+    LogReceiverContractResp {
+        id: model.id,
+        chain_name: model.chain_name.clone(),
+        chain_id: model.chain_id,
+        address: model.address.clone(),
+        event_sign: model.event_sign.clone(),
+        paused: model.paused,
+        start_height: model.start_height,
+        end_height: model.end_height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{abi_event_sign, abi_param_types, check_event_sign};
+
+    const TRANSFER_TOPIC: &str =
+        "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+    #[test]
+    fn test_check_event_sign() {
+        assert_eq!(
+            check_event_sign("Transfer(address, address, uint256)".to_string()).ok(),
+            Some(TRANSFER_TOPIC.to_string())
+        );
+        assert_eq!(
+            check_event_sign(TRANSFER_TOPIC.to_uppercase().replacen("0X", "0x", 1)).ok(),
+            Some(TRANSFER_TOPIC.to_string())
+        );
+        assert!(check_event_sign("Transfer".to_string()).is_err());
+        assert!(check_event_sign("Trans-fer(address)".to_string()).is_err());
+        assert!(check_event_sign("0xddf252ad".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_abi_event_sign() {
+        let abi = json!({
+            "type": "event",
+            "name": "Transfer",
+            "inputs": [
+                {"name": "from", "type": "address", "indexed": true},
+                {"name": "to", "type": "address", "indexed": true},
+                {"name": "value", "type": "uint256", "indexed": false},
+            ],
+        });
+        assert_eq!(abi_event_sign(&abi).ok(), Some(TRANSFER_TOPIC.to_string()));
+        assert!(abi_event_sign(&json!({"type": "function", "name": "f", "inputs": []})).is_err());
+        assert!(abi_event_sign(&json!({"type": "event", "inputs": []})).is_err());
+    }
+
+    #[test]
+    fn test_abi_param_types() {
+        let params = json!([
+            {"type": "uint256"},
+            {"type": "tuple[]", "components": [
+                {"type": "address"},
+                {"type": "tuple", "components": [{"type": "bytes32"}, {"type": "bool"}]},
+            ]},
+        ]);
+        assert_eq!(
+            abi_param_types(params.as_array().unwrap()).unwrap(),
+            "uint256,(address,(bytes32,bool))[]"
+        );
+        assert!(abi_param_types(json!([{"type": "tuple"}]).as_array().unwrap()).is_none());
+    }
+}
@@ -1,10 +1,13 @@
 pub mod address;
 pub mod block;
+pub mod block_event;
 pub mod event;
 pub mod helth;
+pub mod log_receiver;
 pub mod response;
 pub mod state;
 pub mod token;
+pub mod token_instance;
 pub mod token_transfer;
 pub mod transaction;
 pub mod user;
@@ -1,6 +1,8 @@
 use sea_orm::DatabaseConnection;
 pub struct AppState {
     pub conn: DatabaseConnection,
+    /// lower case addresses allowed to use the admin routes
+    pub admins: Vec<String>,
 }
 
 pub fn get_conn(state: &AppState) -> &DatabaseConnection {
@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use common::consts;
+use entities::token_instances::Model as TokenInstanceModel;
+use repo::dal::{
+    current_token_balance::Query as CurrentTokenQuery, token::Query as TokenQuery,
+    token_instance::Query as TokenInstanceQuery,
+};
+use serde_json::Value;
+
+use crate::checker::base::check_address;
+
+use super::address::{conv_token_model_to_resp, TokenInstanceResp};
+use super::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenIdHolderResp {
+    pub address: String,
+    pub value: Option<String>,
+}
+
+pub fn check_token_id(token_id: String) -> Result<BigDecimal, AppError> {
+    match BigDecimal::from_str(&token_id) {
+        Ok(id) if id.is_integer() && id >= BigDecimal::from(0) => Ok(id),
+        _ => Err(AppError::from(CoreError::Param(token_id))),
+    }
+}
+
+pub async fn get_token_instance(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((id, token_id)): Path<(String, String)>,
+) -> Result<Json<BaseResponse<TokenInstanceResp>>, AppError> {
+    let conn = get_conn(&state);
+
+    let address = check_address(id)?;
+    let token_id = check_token_id(token_id)?;
+
+    let token = match TokenQuery::find_by_hash(conn, address.clone())
+        .await
+        .map_err(AppError::from)?
+    {
+        Some(t) => t,
+        None => return Err(AppError::from(CoreError::NotFound)),
+    };
+    let instance =
+        match TokenInstanceQuery::find_by_token_id(conn, address.clone(), token_id.clone())
+            .await
+            .map_err(AppError::from)?
+        {
+            Some(i) => i,
+            None => return Err(AppError::from(CoreError::NotFound)),
+        };
+
+    let mut resp = conv_instance_model_to_resp(&instance);
+    // an ERC-1155 id has a single owner only while one address holds all of its supply
+    if token.r#type == consts::ERC1155 {
+        let holders = CurrentTokenQuery::find_token_id_holders(conn, address, token_id)
+            .await
+            .map_err(AppError::from)?;
+        resp.owner = match holders.as_slice() {
+            [holder] => Some(chain_ident!(holder.address_hash.clone())),
+            _ => None,
+        };
+        resp.is_unique = Some((holders.len() == 1).to_string());
+    }
+    resp.token = Some(conv_token_model_to_resp(&token));
+
+    Ok(Json(BaseResponse::success(resp)))
+}
+
+pub async fn get_token_instance_holders(
+    Extension(state): Extension<Arc<AppState>>,
+    Path((id, token_id)): Path<(String, String)>,
+) -> Result<Json<BaseResponse<Vec<TokenIdHolderResp>>>, AppError> {
+    let conn = get_conn(&state);
+
+    let address = check_address(id)?;
+    let token_id = check_token_id(token_id)?;
+
+    let holders = CurrentTokenQuery::find_token_id_holders(conn, address, token_id)
+        .await
+        .map_err(AppError::from)?;
+
+    let resp = holders
+        .iter()
+        .map(|holder| TokenIdHolderResp {
+            address: chain_ident!(holder.address_hash.clone()),
+            value: holder.value.as_ref().map(|v| v.to_string()),
+        })
+        .collect();
+
+    Ok(Json(BaseResponse::success(resp)))
+}
+
+pub fn conv_instance_model_to_resp(model: &TokenInstanceModel) -> TokenInstanceResp {
+    let field = |name: &str| {
+        model
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get(name))
+            .and_then(Value::as_str)
+            .map(|s| s.to_string())
+    };
+
+    TokenInstanceResp {
+        animation_url: field("animation_url"),
+        external_app_url: field("external_url"),
+        id: Some(model.token_id.to_string()),
+        image_url: field("image").or_else(|| field("image_url")),
+        is_unique: model.owner_address_hash.as_ref().map(|_| true.to_string()),
+        metadata: model.metadata.as_ref().map(|m| m.to_string()),
+        owner: model
+            .owner_address_hash
+            .as_ref()
+            .map(|owner| chain_ident!(owner.clone())),
+        token: None,
+    }
+}
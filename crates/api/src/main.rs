@@ -30,5 +30,6 @@ async fn main() {
     let conn = connect_db(db_cfg).await.unwrap();
     info!(message = "connected db");
 
-    router::route(addr, state::AppState { conn }).await
+    let admins = api.admins();
+    router::route(addr, state::AppState { conn, admins }).await
 }
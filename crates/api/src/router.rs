@@ -1,13 +1,13 @@
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post},
     Extension, Router,
 };
 use std::{net::SocketAddr, sync::Arc};
 use tokio::signal;
 
 use crate::{
-    biz::{address, token_transfer},
+    biz::{address, block_event, log_receiver, token_instance, token_transfer},
     err,
 };
 
@@ -23,6 +23,7 @@ pub async fn route(addr: SocketAddr, app_state: state::AppState) {
         .route("/info", get(helth::info))
         .route("/user/create", post(user::create_user))
         .route("/block/:id", get(block::get_block))
+        .route("/block-events", get(block_event::get_block_events))
         .route("/txs", post(transaction::gets_transaction))
         .route("/tx/:id", get(transaction::get_transaction))
         .route("/tx/:id/logs", get(event::get_transaction_logs))
@@ -32,6 +33,32 @@ pub async fn route(addr: SocketAddr, app_state: state::AppState) {
         )
         .route("/address/:id", get(address::get_address))
         .route("/address/:id/tokens", get(address::get_address_tokens))
+        .route("/address/:id/nfts", get(address::get_address_nfts))
+        .route(
+            "/token/:id/instance/:token_id",
+            get(token_instance::get_token_instance),
+        )
+        .route(
+            "/token/:id/instance/:token_id/holders",
+            get(token_instance::get_token_instance_holders),
+        )
+        .route(
+            "/admin/log-receiver/contracts",
+            get(log_receiver::get_log_receiver_contracts)
+                .post(log_receiver::create_log_receiver_contract),
+        )
+        .route(
+            "/admin/log-receiver/contracts/:id",
+            delete(log_receiver::delete_log_receiver_contract),
+        )
+        .route(
+            "/admin/log-receiver/contracts/:id/pause",
+            post(log_receiver::pause_log_receiver_contract),
+        )
+        .route(
+            "/admin/log-receiver/contracts/:id/resume",
+            post(log_receiver::resume_log_receiver_contract),
+        )
         .route("/protected", get(jwt::protected))
         .route("/authorize/bearer", post(jwt::authorize))
         .route("/authorize/api", post(jwt::authorize_api_token))
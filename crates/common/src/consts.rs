@@ -4,6 +4,7 @@ pub const ERC721: &str = "ERC-721";
 pub const WETH: &str = "WTH";
 pub const UNKNOWN: &str = "Unknown";
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenKind {
     ERC20,
     ERC721,
@@ -27,5 +28,10 @@ pub const ERC1155_BATCH_TRANSFER_SIGNATURE: &str =
     "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
 pub const BRIDGE_HASH: &str = "0x3c798bbcf33115b42c728b8504cff11dd58736e9fa789f1cda2738db7d696b2a";
 
+// counter types of `last_fetched_counters`
+pub const LAST_FINALIZED_BLOCK_NUMBER: &str = "last_finalized_block_number";
+// bumped on every change of the log receiver contracts, the scanner reloads them after
+pub const LOG_RECEIVER_CONTRACTS_VERSION: &str = "log_receiver_contracts_version";
+
 pub const BURN_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
 pub const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
@@ -3,6 +3,16 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Api {
     pub port: u16,
+    /// addresses allowed on the admin routes, their tokens are signed with `ADMIN_JWT_SECRET`
+    pub admins: Option<Vec<String>>,
 }
 
-impl Api {}
+impl Api {
+    pub fn admins(&self) -> Vec<String> {
+        self.admins
+            .iter()
+            .flatten()
+            .map(|admin| admin.to_lowercase())
+            .collect()
+    }
+}
@@ -3,10 +3,226 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Chain {
     pub url: String,
+    /// extra rpc endpoints used together with `url` for failover and load balancing
+    pub urls: Option<Vec<String>>,
+    /// blocks an endpoint may lag behind the best one before it is taken as unhealthy
+    pub rpc_max_lag: Option<u64>,
+    /// websocket url or ipc path to subscribe `newHeads`, polling is used if absent
+    pub ws_url: Option<String>,
     pub chain_id: Option<u64>,
     pub chain_name: String,
     pub contracts: Option<Vec<String>>,
     pub interval: u64,
+    /// max blocks indexed per iteration while behind the chain tip
+    pub batch_size: Option<u64>,
+    /// blocks the indexed head lags behind the followed block
+    pub confirmations: Option<u64>,
+    /// block tag the indexed head follows, defaults to `latest`
+    pub follow: Option<FollowTag>,
+    /// seconds a single rpc call may take
+    pub rpc_timeout: Option<u64>,
+    /// retries of a transient rpc failure
+    pub rpc_retries: Option<u32>,
+    /// api used to trace internal transactions, defaults to `trace_block`
+    pub tracer: Option<Tracer>,
+    /// blocks a fetched token total supply is reused for mints and burns, defaults to 0
+    pub total_supply_ttl: Option<u64>,
+    /// gateway `ipfs://` token uris are fetched through, defaults to `https://ipfs.io/ipfs`
+    pub ipfs_gateway: Option<String>,
+    /// Multicall3 contract reads are aggregated through
+    pub multicall: Option<Multicall>,
+    pub backfill: Option<Backfill>,
+    pub recount: Option<Recount>,
+    /// logs of the contracts registered in `log_receiver_contract` delivered to a sink
+    pub log_receiver: Option<LogReceiver>,
+    /// delivery of the watchlist notifications to the webhooks of their owners
+    pub watchlist: Option<Watchlist>,
 }
 
-impl Chain {}
+impl Chain {
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints = vec![self.url.clone()];
+        for url in self.urls.iter().flatten() {
+            if !endpoints.contains(url) {
+                endpoints.push(url.clone());
+            }
+        }
+        endpoints
+    }
+
+    pub fn rpc_max_lag(&self) -> u64 {
+        self.rpc_max_lag.unwrap_or(5)
+    }
+
+    pub fn batch_size(&self) -> u64 {
+        self.batch_size.unwrap_or(10)
+    }
+
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations.unwrap_or(0)
+    }
+
+    pub fn rpc_timeout(&self) -> u64 {
+        self.rpc_timeout.unwrap_or(10)
+    }
+
+    pub fn rpc_retries(&self) -> u32 {
+        self.rpc_retries.unwrap_or(3)
+    }
+
+    pub fn follow(&self) -> FollowTag {
+        self.follow.clone().unwrap_or_default()
+    }
+
+    pub fn tracer(&self) -> Tracer {
+        self.tracer.clone().unwrap_or_default()
+    }
+
+    pub fn total_supply_ttl(&self) -> u64 {
+        self.total_supply_ttl.unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowTag {
+    #[default]
+    Latest,
+    Safe,
+    Finalized,
+}
+
+/// `trace_block` of parity/erigon style nodes, `call_tracer` uses `debug_traceBlockByNumber`
+/// of geth style nodes, `none` indexes blocks without internal transactions.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Tracer {
+    #[default]
+    TraceBlock,
+    CallTracer,
+    None,
+}
+
+/// historical range to index, `to` defaults to the block before the first indexed one. the
+/// watchlist notifications of backfilled blocks are only sent to the webhooks if
+/// `deliver_watchlist` is set.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct Backfill {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub chunk_size: Option<u64>,
+    pub concurrency: Option<usize>,
+    pub deliver_watchlist: Option<bool>,
+}
+
+impl Backfill {
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or(100)
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(4)
+    }
+
+    pub fn deliver_watchlist(&self) -> bool {
+        self.deliver_watchlist.unwrap_or(false)
+    }
+}
+
+/// height range whose address counters are rebuilt once at startup, `to` defaults to the
+/// indexed head.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct Recount {
+    pub from: u64,
+    pub to: Option<u64>,
+    pub chunk_size: Option<u64>,
+}
+
+impl Recount {
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or(1000)
+    }
+}
+
+/// `address` defaults to the address Multicall3 is deployed at on most chains.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct Multicall {
+    pub address: Option<String>,
+    pub chunk_size: Option<usize>,
+}
+
+impl Multicall {
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size.unwrap_or(100)
+    }
+}
+
+/// scans `eth_getLogs` from `from` on, `range` blocks per request. the cursor of `task_name`
+/// is kept in `log_receiver_chain`, it defaults to `<chain_name>:<chain_id>`.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct LogReceiver {
+    pub task_name: Option<String>,
+    pub from: Option<u64>,
+    pub range: Option<u64>,
+    pub interval: Option<u64>,
+    pub sink: Option<LogSink>,
+}
+
+impl LogReceiver {
+    pub fn range(&self) -> u64 {
+        self.range.unwrap_or(100).max(1)
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(3).max(1)
+    }
+
+    pub fn sink(&self) -> LogSink {
+        self.sink.clone().unwrap_or_default()
+    }
+}
+
+/// where the logs grouped per transaction are delivered, `kafka` uses the `kafka` config.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LogSink {
+    #[default]
+    Db,
+    Kafka,
+    Webhook {
+        url: String,
+    },
+}
+
+/// a failed webhook delivery is retried after `retry_delay` seconds, doubled per attempt, and
+/// moved to the dead letters after `max_attempts`.
+#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+pub struct Watchlist {
+    pub interval: Option<u64>,
+    pub batch_size: Option<u64>,
+    pub max_attempts: Option<i32>,
+    pub retry_delay: Option<u64>,
+    pub timeout: Option<u64>,
+}
+
+impl Watchlist {
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(3).max(1)
+    }
+
+    pub fn batch_size(&self) -> u64 {
+        self.batch_size.unwrap_or(100).max(1)
+    }
+
+    pub fn max_attempts(&self) -> i32 {
+        self.max_attempts.unwrap_or(8).max(1)
+    }
+
+    pub fn retry_delay(&self) -> u64 {
+        self.retry_delay.unwrap_or(30)
+    }
+
+    pub fn timeout(&self) -> u64 {
+        self.timeout.unwrap_or(10)
+    }
+}
@@ -6,6 +6,10 @@ pub struct Kafka {
     pub topics: Vec<String>,  // test1,test2
     pub group_id: Option<String>,
     pub log_level: Option<String>,
+    /// events are produced to `<topic_prefix><event type>`
+    pub topic_prefix: Option<String>,
+    /// seconds a produced message may wait for its acknowledgement
+    pub message_timeout: Option<u64>,
 }
 
 impl Kafka {
@@ -16,4 +20,8 @@ impl Kafka {
     pub fn brokers_to_str(&self) -> String {
         self.brokers.join(",")
     }
+
+    pub fn message_timeout(&self) -> u64 {
+        self.message_timeout.unwrap_or(5)
+    }
 }
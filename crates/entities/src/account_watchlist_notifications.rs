@@ -35,6 +35,9 @@ pub struct Model {
     pub to_address_hash_hash: Option<Vec<u8>>,
     #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
     pub transaction_hash_hash: Option<Vec<u8>>,
+    #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
+    pub block_hash: Option<Vec<u8>>,
+    pub log_index: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
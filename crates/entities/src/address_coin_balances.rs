@@ -18,6 +18,9 @@ pub struct Model {
     pub value_fetched_at: Option<DateTime>,
     pub inserted_at: DateTime,
     pub updated_at: DateTime,
+    /// failed fetches of the balance, it is given up after the fetcher's max attempts
+    pub fetch_attempts: i32,
+    pub next_fetch_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
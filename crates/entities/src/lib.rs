@@ -2,6 +2,7 @@ pub mod prelude;
 
 pub mod log_receiver_chain;
 pub mod log_receiver_contract;
+pub mod log_receiver_logs;
 pub mod user;
 
 pub mod account_api_keys;
@@ -12,7 +13,9 @@ pub mod account_public_tags_requests;
 pub mod account_tag_addresses;
 pub mod account_tag_transactions;
 pub mod account_watchlist_addresses;
+pub mod account_watchlist_deliveries;
 pub mod account_watchlist_notifications;
+pub mod account_watchlist_webhooks;
 pub mod account_watchlists;
 pub mod address_coin_balances;
 pub mod address_coin_balances_daily;
@@ -23,6 +26,7 @@ pub mod address_to_tags;
 pub mod address_token_balances;
 pub mod addresses;
 pub mod administrators;
+pub mod block_events;
 pub mod block_rewards;
 pub mod block_second_degree_relations;
 pub mod blocks;
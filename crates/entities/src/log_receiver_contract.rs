@@ -6,11 +6,16 @@ use sea_orm::entity::prelude::*;
 #[sea_orm(table_name = "log_receiver_contract")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u64,
+    pub id: i64,
     pub chain_name: String,
-    pub chain_id: u32,
+    pub chain_id: i64,
     pub address: String,
     pub event_sign: String,
+    pub paused: bool,
+    /// height the logs of the contract are backfilled from
+    pub start_height: Option<i64>,
+    /// height the log receiver cursor had when it took the contract over from the backfill
+    pub end_height: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -19,9 +24,15 @@ pub enum Relation {}
 impl ActiveModelBehavior for ActiveModel {}
 
 impl Entity {
-    pub fn find_one(address: &str, chain_id: u32) -> Select<Entity> {
+    pub fn find_one(address: &str, chain_id: i64) -> Select<Entity> {
         Self::find()
             .filter(Column::Address.eq(address))
             .filter(Column::ChainId.eq(chain_id))
     }
+
+    pub fn find_active_by_chain_id(chain_id: i64) -> Select<Entity> {
+        Self::find()
+            .filter(Column::ChainId.eq(chain_id))
+            .filter(Column::Paused.eq(false))
+    }
 }
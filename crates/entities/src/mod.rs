@@ -10,7 +10,9 @@ pub mod account_public_tags_requests;
 pub mod account_tag_addresses;
 pub mod account_tag_transactions;
 pub mod account_watchlist_addresses;
+pub mod account_watchlist_deliveries;
 pub mod account_watchlist_notifications;
+pub mod account_watchlist_webhooks;
 pub mod account_watchlists;
 pub mod address_coin_balances;
 pub mod address_coin_balances_daily;
@@ -21,6 +23,7 @@ pub mod address_to_tags;
 pub mod address_token_balances;
 pub mod addresses;
 pub mod administrators;
+pub mod block_events;
 pub mod block_rewards;
 pub mod block_second_degree_relations;
 pub mod blocks;
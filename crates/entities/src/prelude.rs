@@ -19,6 +19,7 @@ pub use super::address_to_tags::Entity as AddressToTags;
 pub use super::address_token_balances::Entity as AddressTokenBalances;
 pub use super::addresses::Entity as Addresses;
 pub use super::administrators::Entity as Administrators;
+pub use super::block_events::Entity as BlockEvents;
 pub use super::block_rewards::Entity as BlockRewards;
 pub use super::block_second_degree_relations::Entity as BlockSecondDegreeRelations;
 pub use super::blocks::Entity as Blocks;
@@ -5,12 +5,9 @@ use sea_orm::entity::prelude::*;
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "token_instances")]
 pub struct Model {
-    #[sea_orm(
-        primary_key,
-        auto_increment = false,
-        column_type = "Decimal(Some((78, 0)))"
-    )]
-    pub token_id: Decimal,
+    // part of the primary key in the table, sea-orm can not take `BigDecimal` as a key
+    #[sea_orm(column_type = "Decimal(Some((78, 0)))")]
+    pub token_id: BigDecimal,
     #[sea_orm(
         primary_key,
         auto_increment = false,
@@ -22,6 +19,13 @@ pub struct Model {
     pub inserted_at: DateTime,
     pub updated_at: DateTime,
     pub error: Option<String>,
+    #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
+    pub owner_address_hash: Option<Vec<u8>>,
+    pub owner_updated_at_block: Option<i64>,
+    pub owner_updated_at_log_index: Option<i32>,
+    /// failed metadata fetches, the fetch is retried from `refetch_after` on
+    pub retries_count: i32,
+    pub refetch_after: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
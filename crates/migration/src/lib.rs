@@ -3,6 +3,14 @@ pub use sea_orm_migration::prelude::*;
 mod m20230101_000001_create_scanner_height;
 mod m20230928_085606_create_blocks;
 mod m20230928_094000_create_address;
+mod m20240301_000001_create_block_events;
+mod m20240315_000001_create_log_receiver;
+mod m20240320_000001_alter_log_receiver_contract;
+mod m20240325_000001_create_account_watchlist_webhooks;
+mod m20240401_000001_alter_address_coin_balances_fetch;
+mod m20240402_000001_alter_token_instances_refetch;
+mod m20240403_000001_alter_token_instances_owner;
+mod m20240404_000001_alter_account_watchlist_notifications;
 
 pub struct Migrator;
 
@@ -13,6 +21,14 @@ impl MigratorTrait for Migrator {
             Box::new(m20230101_000001_create_scanner_height::Migration),
             Box::new(m20230928_085606_create_blocks::Migration),
             Box::new(m20230928_094000_create_address::Migration),
+            Box::new(m20240301_000001_create_block_events::Migration),
+            Box::new(m20240315_000001_create_log_receiver::Migration),
+            Box::new(m20240320_000001_alter_log_receiver_contract::Migration),
+            Box::new(m20240325_000001_create_account_watchlist_webhooks::Migration),
+            Box::new(m20240401_000001_alter_address_coin_balances_fetch::Migration),
+            Box::new(m20240402_000001_alter_token_instances_refetch::Migration),
+            Box::new(m20240403_000001_alter_token_instances_owner::Migration),
+            Box::new(m20240404_000001_alter_account_watchlist_notifications::Migration),
         ]
     }
 }
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlockEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BlockEvents::Sequence)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BlockEvents::EventType).string().not_null())
+                    .col(
+                        ColumnDef::new(BlockEvents::BlockNumber)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BlockEvents::BlockHash).binary().not_null())
+                    .col(
+                        ColumnDef::new(BlockEvents::Payload)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BlockEvents::InsertedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BlockEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BlockEvents {
+    Table,
+    Sequence,
+    EventType,
+    BlockNumber,
+    BlockHash,
+    Payload,
+    InsertedAt,
+}
@@ -0,0 +1,189 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LogReceiverChain::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LogReceiverChain::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverChain::TaskName)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverChain::ChainName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverChain::Height)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LogReceiverChain::CreatedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(LogReceiverChain::UpdatedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LogReceiverContract::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LogReceiverContract::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverContract::ChainName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverContract::ChainId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverContract::Address)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverContract::EventSign)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LogReceiverLogs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::TaskName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::BlockNumber)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::BlockHash)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::TransactionIndex)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::ToAddressHash)
+                            .binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::LogDetails)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LogReceiverLogs::InsertedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("log_receiver_logs_task_name_block_hash_transaction_index_index")
+                    .table(LogReceiverLogs::Table)
+                    .col(LogReceiverLogs::TaskName)
+                    .col(LogReceiverLogs::BlockHash)
+                    .col(LogReceiverLogs::TransactionIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LogReceiverLogs::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(LogReceiverContract::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(LogReceiverChain::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LogReceiverChain {
+    Table,
+    Id,
+    TaskName,
+    ChainName,
+    Height,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum LogReceiverContract {
+    Table,
+    Id,
+    ChainName,
+    ChainId,
+    Address,
+    EventSign,
+}
+
+#[derive(DeriveIden)]
+enum LogReceiverLogs {
+    Table,
+    Id,
+    TaskName,
+    BlockNumber,
+    BlockHash,
+    TransactionIndex,
+    ToAddressHash,
+    LogDetails,
+    InsertedAt,
+}
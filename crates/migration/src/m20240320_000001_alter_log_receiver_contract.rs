@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LogReceiverContract::Table)
+                    .add_column(
+                        ColumnDef::new(LogReceiverContract::Paused)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(ColumnDef::new(LogReceiverContract::StartHeight).big_integer())
+                    .add_column(ColumnDef::new(LogReceiverContract::EndHeight).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("log_receiver_contract_chain_id_address_event_sign_index")
+                    .table(LogReceiverContract::Table)
+                    .col(LogReceiverContract::ChainId)
+                    .col(LogReceiverContract::Address)
+                    .col(LogReceiverContract::EventSign)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("log_receiver_contract_chain_id_address_event_sign_index")
+                    .table(LogReceiverContract::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(LogReceiverContract::Table)
+                    .drop_column(LogReceiverContract::Paused)
+                    .drop_column(LogReceiverContract::StartHeight)
+                    .drop_column(LogReceiverContract::EndHeight)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LogReceiverContract {
+    Table,
+    ChainId,
+    Address,
+    EventSign,
+    Paused,
+    StartHeight,
+    EndHeight,
+}
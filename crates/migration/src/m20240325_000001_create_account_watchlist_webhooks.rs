@@ -0,0 +1,178 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountWatchlistWebhooks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::IdentityId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::Url)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::Secret)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::InsertedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistWebhooks::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountWatchlistDeliveries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::NotificationId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::WebhookId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::NextAttemptAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AccountWatchlistDeliveries::LastError).text())
+                    .col(ColumnDef::new(AccountWatchlistDeliveries::DeliveredAt).timestamp())
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::InsertedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountWatchlistDeliveries::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("account_watchlist_deliveries_notification_id_webhook_id_index")
+                    .table(AccountWatchlistDeliveries::Table)
+                    .col(AccountWatchlistDeliveries::NotificationId)
+                    .col(AccountWatchlistDeliveries::WebhookId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("account_watchlist_deliveries_status_next_attempt_at_index")
+                    .table(AccountWatchlistDeliveries::Table)
+                    .col(AccountWatchlistDeliveries::Status)
+                    .col(AccountWatchlistDeliveries::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(AccountWatchlistDeliveries::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(AccountWatchlistWebhooks::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AccountWatchlistWebhooks {
+    Table,
+    Id,
+    IdentityId,
+    Url,
+    Secret,
+    Enabled,
+    InsertedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AccountWatchlistDeliveries {
+    Table,
+    Id,
+    NotificationId,
+    WebhookId,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    LastError,
+    DeliveredAt,
+    InsertedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AddressCoinBalances::Table)
+                    .add_column(
+                        ColumnDef::new(AddressCoinBalances::FetchAttempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(ColumnDef::new(AddressCoinBalances::NextFetchAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AddressCoinBalances::Table)
+                    .drop_column(AddressCoinBalances::FetchAttempts)
+                    .drop_column(AddressCoinBalances::NextFetchAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AddressCoinBalances {
+    Table,
+    FetchAttempts,
+    NextFetchAt,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TokenInstances::Table)
+                    .add_column(ColumnDef::new(TokenInstances::OwnerAddressHash).binary())
+                    .add_column(ColumnDef::new(TokenInstances::OwnerUpdatedAtBlock).big_integer())
+                    .add_column(ColumnDef::new(TokenInstances::OwnerUpdatedAtLogIndex).integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("token_instances_owner_address_hash_index")
+                    .table(TokenInstances::Table)
+                    .col(TokenInstances::OwnerAddressHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("token_instances_owner_address_hash_index")
+                    .table(TokenInstances::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TokenInstances::Table)
+                    .drop_column(TokenInstances::OwnerAddressHash)
+                    .drop_column(TokenInstances::OwnerUpdatedAtBlock)
+                    .drop_column(TokenInstances::OwnerUpdatedAtLogIndex)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TokenInstances {
+    Table,
+    OwnerAddressHash,
+    OwnerUpdatedAtBlock,
+    OwnerUpdatedAtLogIndex,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AccountWatchlistNotifications::Table)
+                    .add_column(ColumnDef::new(AccountWatchlistNotifications::BlockHash).binary())
+                    .add_column(ColumnDef::new(AccountWatchlistNotifications::LogIndex).integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        // a block synced again writes the same notifications
+        manager
+            .create_index(
+                Index::create()
+                    .name("account_watchlist_notifications_activity_index")
+                    .table(AccountWatchlistNotifications::Table)
+                    .col(AccountWatchlistNotifications::WatchlistAddressId)
+                    .col(AccountWatchlistNotifications::TransactionHash)
+                    .col(AccountWatchlistNotifications::Direction)
+                    .col(AccountWatchlistNotifications::Type)
+                    .col(AccountWatchlistNotifications::BlockHash)
+                    .col(AccountWatchlistNotifications::LogIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("account_watchlist_notifications_activity_index")
+                    .table(AccountWatchlistNotifications::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AccountWatchlistNotifications::Table)
+                    .drop_column(AccountWatchlistNotifications::BlockHash)
+                    .drop_column(AccountWatchlistNotifications::LogIndex)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AccountWatchlistNotifications {
+    Table,
+    WatchlistAddressId,
+    TransactionHash,
+    Direction,
+    Type,
+    BlockHash,
+    LogIndex,
+}
@@ -0,0 +1,24 @@
+use ::entities::{
+    account_watchlist_addresses::{Column, Entity, Model},
+    account_watchlists::{Entity as WatchlistEntity, Model as WatchlistModel},
+};
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    /// the watched entries of the addresses together with the watchlist they belong to
+    pub async fn find_by_address_hashes<C>(
+        db: &C,
+        addresses: Vec<Vec<u8>>,
+    ) -> Result<Vec<(Model, Option<WatchlistModel>)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::AddressHash.is_in(addresses))
+            .find_also_related(WatchlistEntity)
+            .all(db)
+            .await
+    }
+}
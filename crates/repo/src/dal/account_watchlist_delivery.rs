@@ -0,0 +1,162 @@
+use ::entities::account_watchlist_deliveries::{ActiveModel, Column, Entity, Model};
+use ::entities::account_watchlist_notifications::{
+    Column as NotificationColumn, Entity as NotificationEntity,
+};
+use chrono::{NaiveDateTime, Utc};
+use migration::{Expr, OnConflict};
+use sea_orm::*;
+
+pub const DELIVERY_PENDING: &str = "pending";
+pub const DELIVERY_DELIVERED: &str = "delivered";
+pub const DELIVERY_DEAD: &str = "dead";
+
+pub struct Query;
+
+impl Query {
+    /// pending deliveries whose next attempt is due, the longest waiting first
+    pub async fn find_due(
+        db: &DbConn,
+        now: NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::Status.eq(DELIVERY_PENDING))
+            .filter(Column::NextAttemptAt.lte(now))
+            .order_by_asc(Column::NextAttemptAt)
+            .order_by_asc(Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    /// a notification is delivered once per webhook.
+    pub async fn create<C>(db: &C, form_datas: &[Model]) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let mut model = form_data.clone().into_active_model();
+            model.id = NotSet;
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let res = Entity::insert_many::<ActiveModel, _>(datas)
+            .on_conflict(
+                OnConflict::columns([Column::NotificationId, Column::WebhookId])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await;
+
+        match res {
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn mark_delivered<C>(db: &C, id: i64, attempts: i32) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = Utc::now().naive_utc();
+        Entity::update_many()
+            .col_expr(Column::Status, Expr::value(DELIVERY_DELIVERED))
+            .col_expr(Column::Attempts, Expr::value(attempts))
+            .col_expr(Column::LastError, Expr::value(Option::<String>::None))
+            .col_expr(Column::DeliveredAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(DELIVERY_PENDING))
+            .exec(db)
+            .await
+    }
+
+    /// the pending deliveries of the notifications of the blocks are left dead, the
+    /// notifications go with the blocks.
+    pub async fn mark_dead_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+        error: String,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::Status, Expr::value(DELIVERY_DEAD))
+            .col_expr(Column::LastError, Expr::value(error))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::Status.eq(DELIVERY_PENDING))
+            .filter(
+                Column::NotificationId.in_subquery(
+                    NotificationEntity::find()
+                        .select_only()
+                        .column(NotificationColumn::Id)
+                        .filter(NotificationColumn::BlockHash.is_in(hashes))
+                        .into_query(),
+                ),
+            )
+            .exec(db)
+            .await
+    }
+
+    /// keep the failed delivery pending until `next_attempt_at`, or move it to the dead letters
+    /// if `next_attempt_at` is none.
+    pub async fn mark_failed<C>(
+        db: &C,
+        id: i64,
+        attempts: i32,
+        next_attempt_at: Option<NaiveDateTime>,
+        error: String,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = Utc::now().naive_utc();
+        let mut update = Entity::update_many()
+            .col_expr(Column::Attempts, Expr::value(attempts))
+            .col_expr(Column::LastError, Expr::value(error))
+            .col_expr(Column::UpdatedAt, Expr::value(now));
+        update = match next_attempt_at {
+            Some(next_attempt_at) => {
+                update.col_expr(Column::NextAttemptAt, Expr::value(next_attempt_at))
+            }
+            None => update.col_expr(Column::Status, Expr::value(DELIVERY_DEAD)),
+        };
+        update
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(DELIVERY_PENDING))
+            .exec(db)
+            .await
+    }
+
+    /// push the pending deliveries of the webhook due before `next_attempt_at` back to it
+    /// without using up their attempts, a webhook which is down is not tried again for each
+    /// of them.
+    pub async fn postpone_by_webhook<C>(
+        db: &C,
+        webhook_id: i64,
+        next_attempt_at: NaiveDateTime,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::NextAttemptAt, Expr::value(next_attempt_at))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::WebhookId.eq(webhook_id))
+            .filter(Column::Status.eq(DELIVERY_PENDING))
+            .filter(Column::NextAttemptAt.lt(next_attempt_at))
+            .exec(db)
+            .await
+    }
+}
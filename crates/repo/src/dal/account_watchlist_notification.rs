@@ -0,0 +1,171 @@
+use ::entities::account_watchlist_notifications::{ActiveModel, Column, Entity, Model};
+use migration::OnConflict;
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_ids(db: &DbConn, ids: Vec<i64>) -> Result<Vec<Model>, DbErr> {
+        Entity::find().filter(Column::Id.is_in(ids)).all(db).await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    /// insert the notifications not written yet and return them, their ids are referenced by
+    /// the deliveries. a block synced again returns none.
+    pub async fn create<C>(db: &C, form_datas: &[Model]) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if form_datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let mut model = form_data.clone().into_active_model();
+            model.id = NotSet;
+            datas.push(model);
+        }
+        let mut insert = Entity::insert_many::<ActiveModel, _>(datas)
+            .on_conflict(
+                OnConflict::columns([
+                    Column::WatchlistAddressId,
+                    Column::TransactionHash,
+                    Column::Direction,
+                    Column::Type,
+                    Column::BlockHash,
+                    Column::LogIndex,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .into_query();
+        insert.returning_all();
+
+        Entity::find()
+            .from_raw_sql(db.get_database_backend().build(&insert))
+            .all(db)
+            .await
+    }
+
+    pub async fn delete_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockHash.is_in(hashes))
+            .exec(db)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::orm::conn::connect_db;
+    use ::entities::account_watchlist_addresses::ActiveModel as WatchlistAddressModel;
+    use ::entities::account_watchlist_notifications::Model;
+    use chrono::Utc;
+    use config::db::DB;
+    use sea_orm::{ActiveModelTrait, ModelTrait, Set};
+
+    use super::Mutation;
+
+    fn setup_database() -> DB {
+        DB {
+            url: "172.22.215.113:5432".to_string(),
+            schema: "postgres".to_string(),
+            username: "postgres".to_string(),
+            password: "postgres".to_string(),
+            database: "soler".to_string(),
+            log_level: 3,
+        }
+    }
+
+    fn notification(watchlist_address_id: i64, block_hash: &[u8], log_index: i32) -> Model {
+        Model {
+            id: 0,
+            watchlist_address_id: Some(watchlist_address_id),
+            direction: Some("incoming".to_string()),
+            r#type: Some("COIN".to_string()),
+            method: Some("transfer".to_string()),
+            block_number: Some(1),
+            amount: None,
+            tx_fee: None,
+            viewed_at: None,
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            name: None,
+            subject: None,
+            from_address_hash: Some(vec![1]),
+            to_address_hash: Some(vec![2]),
+            transaction_hash: Some(vec![3]),
+            subject_hash: None,
+            from_address_hash_hash: None,
+            to_address_hash_hash: None,
+            transaction_hash_hash: None,
+            block_hash: Some(block_hash.to_vec()),
+            log_index: Some(log_index),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_create_synced_again() {
+        let db_cfg = setup_database();
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let conn = rt.block_on(connect_db(db_cfg)).unwrap();
+        let address = rt
+            .block_on(
+                WatchlistAddressModel {
+                    inserted_at: Set(Utc::now().naive_utc()),
+                    updated_at: Set(Utc::now().naive_utc()),
+                    address_hash: Set(Some(vec![2])),
+                    ..Default::default()
+                }
+                .insert(&conn),
+            )
+            .unwrap();
+        let block_hash = Utc::now().timestamp_nanos_opt().unwrap().to_be_bytes();
+        let notifications = vec![
+            notification(address.id, &block_hash, -1),
+            notification(address.id, &block_hash, 0),
+        ];
+
+        let created = rt
+            .block_on(Mutation::create(&conn, &notifications))
+            .unwrap();
+        assert_eq!(created.len(), 2);
+        // the block is synced again
+        let created = rt
+            .block_on(Mutation::create(&conn, &notifications))
+            .unwrap();
+        assert!(created.is_empty());
+        // the block of another branch at the same height
+        let created = rt
+            .block_on(Mutation::create(
+                &conn,
+                &[notification(address.id, &[0], -1)],
+            ))
+            .unwrap();
+        assert_eq!(created.len(), 1);
+
+        let deleted = rt
+            .block_on(Mutation::delete_by_block_hashes(
+                &conn,
+                vec![block_hash.to_vec()],
+            ))
+            .unwrap();
+        assert_eq!(deleted.rows_affected, 2);
+        rt.block_on(address.delete(&conn)).unwrap();
+    }
+}
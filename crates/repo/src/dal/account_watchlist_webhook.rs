@@ -0,0 +1,24 @@
+use ::entities::account_watchlist_webhooks::{Column, Entity, Model};
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_ids(db: &DbConn, ids: Vec<i64>) -> Result<Vec<Model>, DbErr> {
+        Entity::find().filter(Column::Id.is_in(ids)).all(db).await
+    }
+
+    pub async fn find_enabled_by_identity_ids<C>(
+        db: &C,
+        identity_ids: Vec<i64>,
+    ) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::IdentityId.is_in(identity_ids))
+            .filter(Column::Enabled.eq(true))
+            .all(db)
+            .await
+    }
+}
@@ -1,6 +1,12 @@
 use ::entities::addresses::{ActiveModel, Column, Entity, Model};
-use migration::OnConflict;
-use sea_orm::*;
+use chrono::Utc;
+use migration::{Expr, OnConflict};
+use sea_orm::{prelude::Decimal, *};
+
+#[derive(Debug, FromQueryResult)]
+pub struct AddressHash {
+    pub hash: Vec<u8>,
+}
 
 pub struct Query;
 
@@ -20,6 +26,32 @@ impl Query {
             .all(db)
             .await
     }
+
+    /// addresses which sent or received a transaction or token transfer in [from, to]
+    /// addresses whose current coin balance was fetched above `height`
+    pub async fn find_coin_balance_above(db: &DbConn, height: i64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::FetchedCoinBalanceBlockNumber.gt(height))
+            .all(db)
+            .await
+    }
+
+    pub async fn find_touched<C>(db: &C, from: i64, to: i64) -> Result<Vec<AddressHash>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        AddressHash::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT from_address_hash AS hash FROM transactions WHERE block_number BETWEEN $1 AND $2
+            UNION SELECT to_address_hash FROM transactions WHERE block_number BETWEEN $1 AND $2 AND to_address_hash IS NOT NULL
+            UNION SELECT created_contract_address_hash FROM transactions WHERE block_number BETWEEN $1 AND $2 AND created_contract_address_hash IS NOT NULL
+            UNION SELECT from_address_hash FROM token_transfers WHERE block_number BETWEEN $1 AND $2
+            UNION SELECT to_address_hash FROM token_transfers WHERE block_number BETWEEN $1 AND $2"#,
+            [from.into(), to.into()],
+        ))
+        .all(db)
+        .await
+    }
 }
 
 pub struct Mutation;
@@ -69,4 +101,122 @@ impl Mutation {
             .exec(db)
             .await
     }
+
+    /// add the counters of a newly indexed block
+    pub async fn increment_counters<C>(
+        db: &C,
+        hash: Vec<u8>,
+        transactions_count: i32,
+        token_transfers_count: i32,
+        gas_used: i64,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(
+                Column::TransactionsCount,
+                Expr::cust_with_values(
+                    "COALESCE(transactions_count, 0) + $1",
+                    [transactions_count],
+                ),
+            )
+            .col_expr(
+                Column::TokenTransfersCount,
+                Expr::cust_with_values(
+                    "COALESCE(token_transfers_count, 0) + $1",
+                    [token_transfers_count],
+                ),
+            )
+            .col_expr(
+                Column::GasUsed,
+                Expr::cust_with_values("COALESCE(gas_used, 0) + $1", [gas_used]),
+            )
+            .filter(Column::Hash.eq(hash))
+            .exec(db)
+            .await
+    }
+
+    /// rebuild the counters of the addresses from the consensus transactions and token transfers
+    pub async fn recount<C>(db: &C, hashes: Vec<Vec<u8>>) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(
+                Column::TransactionsCount,
+                Expr::cust(
+                    r#"(SELECT count(*) FROM transactions t WHERE t.block_hash IS NOT NULL
+                    AND (t.from_address_hash = addresses.hash OR t.to_address_hash = addresses.hash
+                    OR t.created_contract_address_hash = addresses.hash))"#,
+                ),
+            )
+            .col_expr(
+                Column::TokenTransfersCount,
+                Expr::cust(
+                    r#"(SELECT count(*) FROM token_transfers tt
+                    WHERE tt.from_address_hash = addresses.hash OR tt.to_address_hash = addresses.hash)"#,
+                ),
+            )
+            .col_expr(
+                Column::GasUsed,
+                Expr::cust(
+                    r#"(SELECT COALESCE(sum(t.gas_used), 0) FROM transactions t
+                    WHERE t.block_hash IS NOT NULL AND t.from_address_hash = addresses.hash)"#,
+                ),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::Hash.is_in(hashes))
+            .exec(db)
+            .await
+    }
+
+    /// keep the balance at the highest fetched block as the current one
+    pub async fn update_coin_balance<C>(
+        db: &C,
+        hash: Vec<u8>,
+        value: Decimal,
+        block_number: i64,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::FetchedCoinBalance, Expr::value(value))
+            .col_expr(
+                Column::FetchedCoinBalanceBlockNumber,
+                Expr::value(block_number),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::Hash.eq(hash))
+            .filter(
+                Condition::any()
+                    .add(Column::FetchedCoinBalanceBlockNumber.is_null())
+                    .add(Column::FetchedCoinBalanceBlockNumber.lte(block_number)),
+            )
+            .exec(db)
+            .await
+    }
+
+    /// set the current coin balance whatever block it was fetched at
+    pub async fn set_coin_balance<C>(
+        db: &C,
+        hash: Vec<u8>,
+        value: Option<Decimal>,
+        block_number: Option<i64>,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::FetchedCoinBalance, Expr::value(value))
+            .col_expr(
+                Column::FetchedCoinBalanceBlockNumber,
+                Expr::value(block_number),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::Hash.eq(hash))
+            .exec(db)
+            .await
+    }
 }
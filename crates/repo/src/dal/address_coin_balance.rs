@@ -0,0 +1,132 @@
+use ::entities::address_coin_balances::{ActiveModel, Column, Entity, Model};
+use chrono::{NaiveDateTime, Utc};
+use migration::{Expr, OnConflict};
+use sea_orm::{prelude::Decimal, *};
+
+pub struct Query;
+
+impl Query {
+    /// balances queued by the block handler which are not fetched from the chain yet, failed
+    /// ones once their retry is due and until they failed `max_attempts` times.
+    pub async fn find_unfetched(
+        db: &DbConn,
+        now: NaiveDateTime,
+        max_attempts: i32,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::ValueFetchedAt.is_null())
+            .filter(Column::FetchAttempts.lt(max_attempts))
+            .filter(
+                Condition::any()
+                    .add(Column::NextFetchAt.is_null())
+                    .add(Column::NextFetchAt.lte(now)),
+            )
+            .order_by_asc(Column::BlockNumber)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// the latest fetched balance of the address at or below `height`
+    pub async fn find_latest_fetched_not_above(
+        db: &DbConn,
+        address_hash: Vec<u8>,
+        height: i64,
+    ) -> Result<Option<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::AddressHash.eq(address_hash))
+            .filter(Column::BlockNumber.lte(height))
+            .filter(Column::ValueFetchedAt.is_not_null())
+            .order_by_desc(Column::BlockNumber)
+            .one(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let model = form_data.clone().into_active_model();
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let res = Entity::insert_many(datas)
+            .on_conflict(
+                OnConflict::columns([Column::AddressHash, Column::BlockNumber])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(db)
+            .await;
+
+        if matches!(res, Err(DbErr::RecordNotInserted)) {
+            return Ok(InsertResult {
+                last_insert_id: (vec![], 0),
+            });
+        }
+
+        res
+    }
+
+    pub async fn update_value<C>(
+        db: &C,
+        address: Vec<u8>,
+        block_number: i64,
+        value: Decimal,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = Utc::now().naive_utc();
+        Entity::update_many()
+            .col_expr(Column::Value, Expr::value(value))
+            .col_expr(Column::ValueFetchedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::AddressHash.eq(address))
+            .filter(Column::BlockNumber.eq(block_number))
+            .exec(db)
+            .await
+    }
+
+    /// count the failed fetch, the balance is fetched again from `next_fetch_at` on
+    pub async fn mark_fetch_failed<C>(
+        db: &C,
+        address: Vec<u8>,
+        block_number: i64,
+        attempts: i32,
+        next_fetch_at: Option<NaiveDateTime>,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::FetchAttempts, Expr::value(attempts))
+            .col_expr(Column::NextFetchAt, Expr::value(next_fetch_at))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::AddressHash.eq(address))
+            .filter(Column::BlockNumber.eq(block_number))
+            .exec(db)
+            .await
+    }
+
+    pub async fn delete_above<C>(db: &C, height: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockNumber.gt(height))
+            .exec(db)
+            .await
+    }
+}
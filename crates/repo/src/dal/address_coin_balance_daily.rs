@@ -0,0 +1,33 @@
+use sea_orm::*;
+
+pub struct Mutation;
+
+impl Mutation {
+    /// set the balance of the day `block_number` belongs to, to the balance at the last block
+    /// of that day fetched so far.
+    pub async fn refresh<C>(
+        db: &C,
+        address: Vec<u8>,
+        block_number: i64,
+    ) -> Result<ExecResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO address_coin_balances_daily (address_hash, day, value, inserted_at, updated_at)
+            SELECT acb.address_hash, b.timestamp::date, acb.value, now(), now()
+            FROM address_coin_balances acb
+            JOIN blocks b ON b.number = acb.block_number AND b.consensus
+            WHERE acb.address_hash = $1
+              AND acb.value_fetched_at IS NOT NULL
+              AND b.timestamp::date = (SELECT timestamp::date FROM blocks WHERE number = $2 AND consensus LIMIT 1)
+            ORDER BY acb.block_number DESC
+            LIMIT 1
+            ON CONFLICT (address_hash, day)
+            DO UPDATE SET value = EXCLUDED.value, updated_at = EXCLUDED.updated_at"#,
+            [address.into(), block_number.into()],
+        ))
+        .await
+    }
+}
@@ -1,11 +1,20 @@
 use ::entities::blocks::{ActiveModel, Column, Entity, Model};
+use chrono::Utc;
+use migration::{Expr, OnConflict};
 use sea_orm::*;
 
+#[derive(Debug, FromQueryResult)]
+pub struct BlockGap {
+    pub from_number: i64,
+    pub to_number: i64,
+}
+
 pub struct Query;
 
 impl Query {
     pub async fn select_latest(db: &DbConn) -> Result<Option<Model>, DbErr> {
         Entity::find()
+            .filter(Column::Consensus.eq(true))
             .order_by_desc(Column::Number)
             .limit(1)
             .one(db)
@@ -19,10 +28,43 @@ impl Query {
     pub async fn find_by_height(db: &DbConn, height: i64) -> Result<Option<Model>, DbErr> {
         Entity::find()
             .filter(Column::Number.eq(height))
+            .filter(Column::Consensus.eq(true))
             .one(db)
             .await
     }
 
+    // consensus blocks above the giving height, used to find blocks orphaned by a reorg.
+    pub async fn find_above(db: &DbConn, height: i64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::Number.gt(height))
+            .filter(Column::Consensus.eq(true))
+            .order_by_asc(Column::Number)
+            .all(db)
+            .await
+    }
+
+    // holes of consensus blocks in the inclusive range [from, to], both ends are taken as
+    // sentinels so leading and trailing holes are found as well.
+    pub async fn find_gaps(db: &DbConn, from: i64, to: i64) -> Result<Vec<BlockGap>, DbErr> {
+        BlockGap::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT number + 1 AS from_number, next_number - 1 AS to_number
+            FROM (
+                SELECT number, LEAD(number) OVER (ORDER BY number) AS next_number
+                FROM (
+                    SELECT number FROM blocks WHERE consensus AND number BETWEEN $1 AND $2
+                    UNION ALL SELECT $1 - 1
+                    UNION ALL SELECT $2 + 1
+                ) AS numbers
+            ) AS t
+            WHERE next_number > number + 1
+            ORDER BY from_number"#,
+            [from.into(), to.into()],
+        ))
+        .all(db)
+        .await
+    }
+
     pub async fn find_max_number(db: &DbConn) -> Result<i64, DbErr> {
         let res = Entity::find()
             .filter(Column::Consensus.eq(true))
@@ -73,26 +115,65 @@ impl Mutation {
     where
         C: ConnectionTrait,
     {
-        ActiveModel {
-            difficulty: Set(form_data.difficulty),
-            gas_limit: Set(form_data.gas_limit),
-            gas_used: Set(form_data.gas_used),
-            hash: Set(form_data.hash.to_owned()),
-            miner_hash: Set(form_data.miner_hash.to_owned()),
-            nonce: Set(form_data.nonce.to_owned()),
-            number: Set(form_data.number),
-            parent_hash: Set(form_data.parent_hash.to_owned()),
-            size: Set(form_data.size),
-            timestamp: Set(form_data.timestamp),
-            base_fee_per_gas: Set(form_data.base_fee_per_gas),
-            total_difficulty: Set(form_data.total_difficulty),
-            consensus: Set(form_data.consensus),
-            refetch_needed: Set(form_data.refetch_needed),
-            is_empty: Set(form_data.is_empty),
-            inserted_at: Set(form_data.inserted_at),
-            updated_at: Set(form_data.updated_at),
+        // a block which lost consensus before can become canonical again
+        Entity::insert(to_active_model(form_data))
+            .on_conflict(
+                OnConflict::column(Column::Hash)
+                    .update_columns([Column::Consensus, Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec_with_returning(db)
+            .await
+    }
+
+    /// store an uncle as a non-consensus block, a block already stored under its hash, like a
+    /// canonical one, is left as it is.
+    pub async fn create_uncle<C>(db: &C, form_data: &Model) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let res = Entity::insert(to_active_model(form_data))
+            .on_conflict(OnConflict::column(Column::Hash).do_nothing().to_owned())
+            .exec_without_returning(db)
+            .await;
+
+        match res {
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err),
         }
-        .insert(db)
-        .await
+    }
+
+    pub async fn lose_consensus<C>(db: &C, hashes: Vec<Vec<u8>>) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::Consensus, Expr::value(false))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::Hash.is_in(hashes))
+            .exec(db)
+            .await
+    }
+}
+
+fn to_active_model(form_data: &Model) -> ActiveModel {
+    ActiveModel {
+        difficulty: Set(form_data.difficulty),
+        gas_limit: Set(form_data.gas_limit),
+        gas_used: Set(form_data.gas_used),
+        hash: Set(form_data.hash.to_owned()),
+        miner_hash: Set(form_data.miner_hash.to_owned()),
+        nonce: Set(form_data.nonce.to_owned()),
+        number: Set(form_data.number),
+        parent_hash: Set(form_data.parent_hash.to_owned()),
+        size: Set(form_data.size),
+        timestamp: Set(form_data.timestamp),
+        base_fee_per_gas: Set(form_data.base_fee_per_gas),
+        total_difficulty: Set(form_data.total_difficulty),
+        consensus: Set(form_data.consensus),
+        refetch_needed: Set(form_data.refetch_needed),
+        is_empty: Set(form_data.is_empty),
+        inserted_at: Set(form_data.inserted_at),
+        updated_at: Set(form_data.updated_at),
     }
 }
@@ -0,0 +1,61 @@
+use ::entities::block_events::{Column, Entity, Model};
+use sea_orm::*;
+
+// key of the advisory lock the writers of block events take
+const BLOCK_EVENTS_LOCK: i64 = 0x0062_6c6f_636b;
+
+pub struct Query;
+
+impl Query {
+    /// events following `sequence` in order, a consumer replays from its cursor with it.
+    pub async fn find_after(db: &DbConn, sequence: i64, limit: u64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::Sequence.gt(sequence))
+            .order_by_asc(Column::Sequence)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    /// append the events in order, each one takes the sequence following the last one.
+    /// the writers wait for each other until their transaction ends, so neither concurrent
+    /// writers nor rolled back transactions leave a gap in the sequence.
+    pub async fn append<C>(db: &C, form_datas: &[Model]) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if form_datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_advisory_xact_lock($1)",
+            [BLOCK_EVENTS_LOCK.into()],
+        ))
+        .await?;
+
+        for form_data in form_datas.iter() {
+            db.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"INSERT INTO block_events
+                (sequence, event_type, block_number, block_hash, payload, inserted_at)
+                SELECT COALESCE(MAX(sequence), 0) + 1, $1, $2, $3, $4, $5 FROM block_events"#,
+                [
+                    form_data.event_type.clone().into(),
+                    form_data.block_number.into(),
+                    form_data.block_hash.clone().into(),
+                    form_data.payload.clone().into(),
+                    form_data.inserted_at.into(),
+                ],
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+}
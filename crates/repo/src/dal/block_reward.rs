@@ -0,0 +1,55 @@
+use ::entities::block_rewards::{ActiveModel, Column, Entity, Model};
+use migration::OnConflict;
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_block_hash(db: &DbConn, hash: Vec<u8>) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::BlockHash.eq(hash))
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let model = form_data.clone().into_active_model();
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        Entity::insert_many(datas)
+            .on_conflict(
+                OnConflict::columns([Column::AddressHash, Column::AddressType, Column::BlockHash])
+                    .update_columns([Column::Reward, Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await
+    }
+
+    pub async fn delete_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockHash.is_in(hashes))
+            .exec(db)
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+use ::entities::block_second_degree_relations::{ActiveModel, Column, Entity, Model};
+use chrono::Utc;
+use migration::{Expr, OnConflict};
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_nephew_hash(db: &DbConn, hash: Vec<u8>) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::NephewHash.eq(hash))
+            .order_by_asc(Column::Index)
+            .all(db)
+            .await
+    }
+
+    /// relations whose uncle block has not been fetched yet
+    pub async fn find_unfetched(db: &DbConn, limit: u64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::UncleFetchedAt.is_null())
+            .order_by_asc(Column::NephewHash)
+            .order_by_asc(Column::Index)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let model = form_data.clone().into_active_model();
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let res = Entity::insert_many(datas)
+            .on_conflict(
+                OnConflict::columns([Column::NephewHash, Column::UncleHash])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(db)
+            .await;
+
+        if matches!(res, Err(DbErr::RecordNotInserted)) {
+            return Ok(InsertResult {
+                last_insert_id: (vec![], vec![]),
+            });
+        }
+
+        res
+    }
+
+    pub async fn mark_fetched<C>(db: &C, uncle_hash: Vec<u8>) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::UncleFetchedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::UncleHash.eq(uncle_hash))
+            .exec(db)
+            .await
+    }
+
+    pub async fn delete<C>(
+        db: &C,
+        nephew_hash: Vec<u8>,
+        uncle_hash: Vec<u8>,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::NephewHash.eq(nephew_hash))
+            .filter(Column::UncleHash.eq(uncle_hash))
+            .exec(db)
+            .await
+    }
+}
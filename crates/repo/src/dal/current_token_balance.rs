@@ -1,9 +1,56 @@
-use entities::address_current_token_balances::{ActiveModel, Entity, Model};
+use bigdecimal::BigDecimal;
+use entities::address_current_token_balances::{ActiveModel, Column, Entity, Model};
 use sea_orm::*;
 
+#[derive(Debug, FromQueryResult)]
+pub struct ValueChange {
+    pub old_value: Option<BigDecimal>,
+    pub value: Option<BigDecimal>,
+}
+
 pub struct Query;
 
-impl Query {}
+impl Query {
+    pub async fn find_above(db: &DbConn, height: i64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::BlockNumber.gt(height))
+            .all(db)
+            .await
+    }
+
+    /// addresses holding a positive balance of the token id
+    pub async fn find_token_id_holders(
+        db: &DbConn,
+        token_contract_address: Vec<u8>,
+        token_id: BigDecimal,
+    ) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::TokenContractAddressHash.eq(token_contract_address))
+            .filter(Column::TokenId.eq(token_id))
+            .filter(Column::Value.gt(BigDecimal::from(0)))
+            .order_by_desc(Column::Value)
+            .all(db)
+            .await
+    }
+
+    /// token ids the address holds a positive balance of
+    pub async fn find_token_ids_by_address(
+        db: &DbConn,
+        address: Vec<u8>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<Model>, u64), DbErr> {
+        let paginator = Entity::find()
+            .filter(Column::AddressHash.eq(address))
+            .filter(Column::TokenId.is_not_null())
+            .filter(Column::Value.gt(BigDecimal::from(0)))
+            .order_by_desc(Column::BlockNumber)
+            .paginate(db, page_size);
+        let num_pages = paginator.num_pages().await?;
+
+        paginator.fetch_page(page - 1).await.map(|p| (p, num_pages))
+    }
+}
 
 pub struct Mutation;
 
@@ -21,6 +68,7 @@ impl Mutation {
         Entity::insert_many(batch).exec(db).await
     }
 
+    /// insert the balances not tracked yet, `update_value` is the only writer of the value.
     pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<ExecResult, DbErr>
     where
         C: ConnectionTrait,
@@ -43,4 +91,46 @@ impl Mutation {
         db.execute(Statement::from_string(DatabaseBackend::Postgres, stmt))
             .await
     }
+
+    /// move the current balance to the fetched value of `block_number`, nothing is updated if the
+    /// current balance is already at a higher block. returns the previous and the new value.
+    pub async fn update_value<C>(
+        db: &C,
+        address: Vec<u8>,
+        token_contract_address: Vec<u8>,
+        token_id: Option<BigDecimal>,
+        block_number: i64,
+        value: Option<BigDecimal>,
+    ) -> Result<Option<ValueChange>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ValueChange::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE address_current_token_balances
+            SET old_value = value, value = $5, block_number = $4, value_fetched_at = now(), updated_at = now()
+            WHERE address_hash = $1 AND token_contract_address_hash = $2
+            AND COALESCE(token_id, -1) = COALESCE($3, -1) AND block_number <= $4
+            RETURNING old_value, value"#,
+            [
+                address.into(),
+                token_contract_address.into(),
+                token_id.into(),
+                block_number.into(),
+                value.into(),
+            ],
+        ))
+        .one(db)
+        .await
+    }
+
+    pub async fn delete_above<C>(db: &C, height: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockNumber.gt(height))
+            .exec(db)
+            .await
+    }
 }
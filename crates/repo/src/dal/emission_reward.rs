@@ -0,0 +1,21 @@
+use ::entities::emission_rewards::{Entity, Model};
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    /// the emission reward whose `block_range` contains `number`.
+    pub async fn find_by_block_number<C>(db: &C, number: i64) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"SELECT block_range::text AS block_range, reward FROM emission_rewards WHERE block_range @> $1::int8 LIMIT 1"#,
+                [number.into()],
+            ))
+            .one(db)
+            .await
+    }
+}
@@ -68,4 +68,17 @@ impl Mutation {
 
         Events::insert_many(datas).exec(db).await
     }
+
+    pub async fn delete_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Events::delete_many()
+            .filter(Column::BlockHash.is_in(hashes))
+            .exec(db)
+            .await
+    }
 }
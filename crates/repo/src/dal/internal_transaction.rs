@@ -27,4 +27,17 @@ impl Mutation {
 
         Entity::insert_many(batch).exec(db).await
     }
+
+    pub async fn delete_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockHash.is_in(hashes))
+            .exec(db)
+            .await
+    }
 }
@@ -0,0 +1,57 @@
+use ::entities::last_fetched_counters::{ActiveModel, Column, Entity, Model};
+use chrono::Utc;
+use migration::OnConflict;
+use sea_orm::{prelude::Decimal, *};
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_type(db: &DbConn, counter_type: &str) -> Result<Option<Model>, DbErr> {
+        Entity::find_by_id(counter_type.to_string()).one(db).await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn upsert<C>(
+        db: &C,
+        counter_type: &str,
+        value: Decimal,
+    ) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let data = ActiveModel {
+            counter_type: Set(counter_type.to_string()),
+            value: Set(Some(value)),
+            inserted_at: Set(Utc::now().naive_utc()),
+            updated_at: Set(Utc::now().naive_utc()),
+        };
+
+        Entity::insert(data)
+            .on_conflict(
+                OnConflict::column(Column::CounterType)
+                    .update_columns([Column::Value, Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await
+    }
+
+    /// add one to the counter, a missing counter starts at 1.
+    pub async fn increment<C>(db: &C, counter_type: &str) -> Result<ExecResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO last_fetched_counters (counter_type, value, inserted_at, updated_at)
+            VALUES ($1, 1, $2, $2)
+            ON CONFLICT (counter_type) DO UPDATE
+            SET value = COALESCE(last_fetched_counters.value, 0) + 1, updated_at = EXCLUDED.updated_at"#,
+            [counter_type.into(), Utc::now().naive_utc().into()],
+        ))
+        .await
+    }
+}
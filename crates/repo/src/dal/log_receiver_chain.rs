@@ -1,5 +1,7 @@
 use ::entities::log_receiver_chain;
 use ::entities::log_receiver_chain::Entity as ScannerHeight;
+use chrono::Utc;
+use migration::Expr;
 use sea_orm::*;
 
 pub struct Query;
@@ -48,7 +50,7 @@ impl Mutation {
 
     pub async fn update_height_by_id(
         db: &DbConn,
-        id: u64,
+        id: i64,
         form_data: log_receiver_chain::Model,
     ) -> Result<log_receiver_chain::Model, DbErr> {
         let height: log_receiver_chain::ActiveModel = ScannerHeight::find_by_id(id)
@@ -71,7 +73,7 @@ impl Mutation {
     pub async fn update_height_by_task_name(
         db: &DbConn,
         task_name: &str,
-        height: u64,
+        height: i64,
     ) -> Result<log_receiver_chain::Model, DbErr> {
         ScannerHeight::update(log_receiver_chain::ActiveModel {
             id: Unchanged(1),
@@ -84,7 +86,43 @@ impl Mutation {
         .await
     }
 
-    pub async fn delete_task(db: &DbConn, id: u64) -> Result<DeleteResult, DbErr> {
+    /// move the height of the task from `from` to `to`, false if another runner moved it
+    /// in between.
+    pub async fn advance_height<C>(
+        db: &C,
+        task_name: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let res = ScannerHeight::update_many()
+            .col_expr(log_receiver_chain::Column::Height, Expr::value(to))
+            .col_expr(
+                log_receiver_chain::Column::UpdatedAt,
+                Expr::value(Utc::now()),
+            )
+            .filter(log_receiver_chain::Column::TaskName.eq(task_name))
+            .filter(log_receiver_chain::Column::Height.eq(from))
+            .exec(db)
+            .await?;
+
+        Ok(res.rows_affected == 1)
+    }
+
+    /// the backfill cursors `<task_name>#<contract id>` of a contract
+    pub async fn delete_contract_tasks<C>(db: &C, contract_id: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ScannerHeight::delete_many()
+            .filter(log_receiver_chain::Column::TaskName.ends_with(format!("#{}", contract_id)))
+            .exec(db)
+            .await
+    }
+
+    pub async fn delete_task(db: &DbConn, id: i64) -> Result<DeleteResult, DbErr> {
         let post: log_receiver_chain::ActiveModel = ScannerHeight::find_by_id(id)
             .one(db)
             .await?
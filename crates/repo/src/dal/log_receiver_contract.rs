@@ -1,5 +1,6 @@
 use ::entities::log_receiver_contract;
 use ::entities::log_receiver_contract::Entity as ScannerContract;
+use migration::Expr;
 use sea_orm::*;
 
 pub struct Query;
@@ -7,20 +8,40 @@ pub struct Query;
 impl Query {
     pub async fn select_one(
         db: &DbConn,
-        chain_id: u32,
+        chain_id: i64,
         address: &str,
     ) -> Result<Option<log_receiver_contract::Model>, DbErr> {
         ScannerContract::find_one(address, chain_id).one(db).await
     }
 
+    pub async fn find_by_id(
+        db: &DbConn,
+        id: i64,
+    ) -> Result<Option<log_receiver_contract::Model>, DbErr> {
+        ScannerContract::find_by_id(id).one(db).await
+    }
+
+    /// the contracts which are not paused
+    pub async fn find_active_by_chain_id(
+        db: &DbConn,
+        chain_id: i64,
+    ) -> Result<Vec<log_receiver_contract::Model>, DbErr> {
+        ScannerContract::find_active_by_chain_id(chain_id)
+            .order_by_asc(log_receiver_contract::Column::Id)
+            .all(db)
+            .await
+    }
+
     // If ok, returns (post models, num pages).
     pub async fn find_scanner_contract_in_page(
         db: &DbConn,
+        chain_id: i64,
         page: u64,
         posts_per_page: u64,
     ) -> Result<(Vec<log_receiver_contract::Model>, u64), DbErr> {
         // Setup paginator
         let paginator = ScannerContract::find()
+            .filter(log_receiver_contract::Column::ChainId.eq(chain_id))
             .order_by_asc(log_receiver_contract::Column::Id)
             .paginate(db, posts_per_page);
         let num_pages = paginator.num_pages().await?;
@@ -33,24 +54,30 @@ impl Query {
 pub struct Mutation;
 
 impl Mutation {
-    pub async fn create_scanner_contract(
-        db: &DbConn,
+    pub async fn create_scanner_contract<C>(
+        db: &C,
         form_data: log_receiver_contract::Model,
-    ) -> Result<log_receiver_contract::ActiveModel, DbErr> {
+    ) -> Result<log_receiver_contract::Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
         log_receiver_contract::ActiveModel {
-            id: Set(0),
             chain_id: Set(form_data.chain_id),
             chain_name: Set(form_data.chain_name.to_owned()),
             address: Set(form_data.address.to_owned()),
             event_sign: Set(form_data.event_sign.to_owned()),
+            paused: Set(form_data.paused),
+            start_height: Set(form_data.start_height),
+            end_height: Set(None),
+            ..Default::default()
         }
-        .save(db)
+        .insert(db)
         .await
     }
 
     pub async fn update_height_by_id(
         db: &DbConn,
-        id: u64,
+        id: i64,
         form_data: log_receiver_contract::Model,
     ) -> Result<log_receiver_contract::Model, DbErr> {
         let event: log_receiver_contract::ActiveModel = ScannerContract::find_by_id(id)
@@ -65,12 +92,51 @@ impl Mutation {
             chain_name: Set(form_data.chain_name.to_owned()),
             address: Set(form_data.address.to_owned()),
             event_sign: Set(form_data.event_sign.to_owned()),
+            paused: Set(form_data.paused),
+            start_height: Set(form_data.start_height),
+            end_height: Set(form_data.end_height),
         }
         .update(db)
         .await
     }
 
-    pub async fn delete_task(db: &DbConn, id: u64) -> Result<DeleteResult, DbErr> {
+    pub async fn set_paused<C>(db: &C, id: i64, paused: bool) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ScannerContract::update_many()
+            .col_expr(log_receiver_contract::Column::Paused, Expr::value(paused))
+            .filter(log_receiver_contract::Column::Id.eq(id))
+            .exec(db)
+            .await
+    }
+
+    /// the end of the backfill is only set once
+    pub async fn set_end_height<C>(db: &C, id: i64, end_height: i64) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let res = ScannerContract::update_many()
+            .col_expr(
+                log_receiver_contract::Column::EndHeight,
+                Expr::value(end_height),
+            )
+            .filter(log_receiver_contract::Column::Id.eq(id))
+            .filter(log_receiver_contract::Column::EndHeight.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(res.rows_affected == 1)
+    }
+
+    pub async fn delete<C>(db: &C, id: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ScannerContract::delete_by_id(id).exec(db).await
+    }
+
+    pub async fn delete_task(db: &DbConn, id: i64) -> Result<DeleteResult, DbErr> {
         let post: log_receiver_contract::ActiveModel = ScannerContract::find_by_id(id)
             .one(db)
             .await?
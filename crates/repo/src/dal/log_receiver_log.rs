@@ -0,0 +1,62 @@
+use ::entities::log_receiver_logs::{ActiveModel, Column, Entity, Model};
+use migration::OnConflict;
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_task_name(
+        db: &DbConn,
+        task_name: &str,
+        page: u64,
+        logs_per_page: u64,
+    ) -> Result<(Vec<Model>, u64), DbErr> {
+        let paginator = Entity::find()
+            .filter(Column::TaskName.eq(task_name))
+            .order_by_asc(Column::BlockNumber)
+            .order_by_asc(Column::TransactionIndex)
+            .paginate(db, logs_per_page);
+        let num_pages = paginator.num_pages().await?;
+
+        paginator.fetch_page(page - 1).await.map(|p| (p, num_pages))
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    /// the logs of a transaction are kept once per task, a range delivered again is skipped.
+    pub async fn create<C>(db: &C, form_datas: &[Model]) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let mut model = form_data.clone().into_active_model();
+            model.id = NotSet;
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let res = Entity::insert_many::<ActiveModel, _>(datas)
+            .on_conflict(
+                OnConflict::columns([
+                    Column::TaskName,
+                    Column::BlockHash,
+                    Column::TransactionIndex,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await;
+
+        match res {
+            Ok(_) | Err(DbErr::RecordNotInserted) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
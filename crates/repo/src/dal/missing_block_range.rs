@@ -0,0 +1,57 @@
+use ::entities::missing_block_ranges::{ActiveModel, Column, Entity, Model};
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    // ranges are inclusive [from_number, to_number], the newest ones come first.
+    pub async fn find_pending(db: &DbConn, limit: u64) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .order_by_desc(Column::ToNumber)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_all(db: &DbConn) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .order_by_asc(Column::FromNumber)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn create<C>(
+        db: &C,
+        ranges: &[(i64, i64)],
+    ) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut batch = vec![];
+        for (from, to) in ranges.iter() {
+            let data = ActiveModel {
+                id: NotSet,
+                from_number: Set(Some(*from as i32)),
+                to_number: Set(Some(*to as i32)),
+            };
+            batch.push(data);
+        }
+
+        if batch.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        Entity::insert_many(batch).exec(db).await
+    }
+
+    pub async fn delete<C>(db: &C, id: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_by_id(id).exec(db).await
+    }
+}
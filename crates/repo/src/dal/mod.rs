@@ -1,13 +1,28 @@
+pub mod account_watchlist_address;
+pub mod account_watchlist_delivery;
+pub mod account_watchlist_notification;
+pub mod account_watchlist_webhook;
 pub mod address;
+pub mod address_coin_balance;
+pub mod address_coin_balance_daily;
 pub mod block;
+pub mod block_event;
+pub mod block_reward;
+pub mod block_second_degree_relation;
 pub mod current_token_balance;
+pub mod emission_reward;
 pub mod event;
 pub mod internal_transaction;
+pub mod last_fetched_counter;
 pub mod log_receiver_chain;
 pub mod log_receiver_contract;
+pub mod log_receiver_log;
+pub mod missing_block_range;
 pub mod token;
 pub mod token_balance;
+pub mod token_instance;
 pub mod token_transfer;
 pub mod transaction;
+pub mod transaction_fork;
 pub mod user;
 pub mod withdrawal;
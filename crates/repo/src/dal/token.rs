@@ -1,8 +1,14 @@
 use ::entities::tokens::{ActiveModel, Column, Entity, Model};
 use chrono::Utc;
-use migration::OnConflict;
+use migration::{Expr, OnConflict};
 use sea_orm::*;
 
+// addresses holding a positive balance of the token, the burn address is not a holder
+const HOLDER_COUNT_SQL: &str = r#"(SELECT count(DISTINCT b.address_hash)::int
+    FROM address_current_token_balances b
+    WHERE b.token_contract_address_hash = tokens.contract_address_hash
+    AND b.address_hash <> '\x0000000000000000000000000000000000000000'::bytea AND b.value > 0)"#;
+
 pub struct Query;
 
 impl Query {
@@ -21,10 +27,13 @@ impl Query {
             .await
     }
 
-    pub async fn find_by_contract_addresses(
-        db: &DbConn,
+    pub async fn find_by_contract_addresses<C>(
+        db: &C,
         addresses: Vec<Vec<u8>>,
-    ) -> Result<Vec<Model>, DbErr> {
+    ) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
         Entity::find()
             .filter(Column::ContractAddressHash.is_in(addresses))
             .all(db)
@@ -167,6 +176,45 @@ impl Mutation {
         .await
     }
 
+    pub async fn increment_holder_count<C>(
+        db: &C,
+        hash: Vec<u8>,
+        delta: i32,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(
+                Column::HolderCount,
+                Expr::cust_with_values("GREATEST(COALESCE(holder_count, 0) + $1, 0)", [delta]),
+            )
+            .filter(Column::ContractAddressHash.eq(hash))
+            .exec(db)
+            .await
+    }
+
+    /// recompute the holder count of the giving tokens from the current balances, only the
+    /// tokens whose count drifted are updated.
+    pub async fn reconcile_holder_counts<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::HolderCount, Expr::cust(HOLDER_COUNT_SQL))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::ContractAddressHash.is_in(hashes))
+            .filter(Expr::cust(format!(
+                "holder_count IS DISTINCT FROM {}",
+                HOLDER_COUNT_SQL
+            )))
+            .exec(db)
+            .await
+    }
+
     pub async fn update_total_supply<C>(db: &C, form_data: &Model) -> Result<Model, DbErr>
     where
         C: ConnectionTrait,
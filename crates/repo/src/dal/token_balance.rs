@@ -1,4 +1,5 @@
 use ::entities::address_token_balances::{ActiveModel, Column, Entity, Model};
+use bigdecimal::BigDecimal;
 use chrono::Utc;
 use entities::{address_token_balances::Relation, tokens};
 use migration::OnConflict;
@@ -38,6 +39,27 @@ impl Query {
             .await
     }
 
+    // latest balance of the giving (address, token, token_id) which is not above the giving height.
+    pub async fn find_latest_not_above(
+        db: &DbConn,
+        address: Vec<u8>,
+        token_contract_address: Vec<u8>,
+        token_id: Option<BigDecimal>,
+        height: i64,
+    ) -> Result<Option<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::AddressHash.eq(address))
+            .filter(Column::TokenContractAddressHash.eq(token_contract_address))
+            .filter(match token_id {
+                Some(id) => Column::TokenId.eq(id),
+                None => Column::TokenId.is_null(),
+            })
+            .filter(Column::BlockNumber.lte(height))
+            .order_by_desc(Column::BlockNumber)
+            .one(db)
+            .await
+    }
+
     // Builds an `Ecto.Query` to fetch the unfetched token balances.
     // Unfetched token balances are the ones that have the column `value_fetched_at` nil or the value is null. This query also
     // ignores the burn_address for tokens ERC-721 since the most tokens ERC-721 don't allow get the
@@ -178,4 +200,14 @@ impl Mutation {
             .exec(db)
             .await
     }
+
+    pub async fn delete_above<C>(db: &C, height: i64) -> Result<DeleteResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::delete_many()
+            .filter(Column::BlockNumber.gt(height))
+            .exec(db)
+            .await
+    }
 }
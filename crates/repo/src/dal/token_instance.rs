@@ -0,0 +1,195 @@
+use ::entities::token_instances::{Column, Entity, Model};
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDateTime, Utc};
+use migration::Expr;
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_token_id(
+        db: &DbConn,
+        token_contract_address: Vec<u8>,
+        token_id: BigDecimal,
+    ) -> Result<Option<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::TokenContractAddressHash.eq(token_contract_address))
+            .filter(Column::TokenId.eq(token_id))
+            .one(db)
+            .await
+    }
+
+    /// instances of the `(token_contract_address, token_id)` pairs in one query
+    pub async fn find_by_token_ids(
+        db: &DbConn,
+        token_ids: Vec<(Vec<u8>, BigDecimal)>,
+    ) -> Result<Vec<Model>, DbErr> {
+        if token_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        Entity::find()
+            .filter(
+                Expr::tuple([
+                    Expr::col(Column::TokenContractAddressHash).into(),
+                    Expr::col(Column::TokenId).into(),
+                ])
+                .in_tuples(token_ids),
+            )
+            .all(db)
+            .await
+    }
+
+    /// ERC-721 instances currently owned by the address
+    pub async fn find_by_owner(
+        db: &DbConn,
+        owner: Vec<u8>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<Model>, u64), DbErr> {
+        let paginator = Entity::find()
+            .filter(Column::OwnerAddressHash.eq(owner))
+            .order_by_desc(Column::OwnerUpdatedAtBlock)
+            .order_by_desc(Column::OwnerUpdatedAtLogIndex)
+            .paginate(db, page_size);
+        let num_pages = paginator.num_pages().await?;
+
+        paginator.fetch_page(page - 1).await.map(|p| (p, num_pages))
+    }
+
+    /// instances whose metadata has not been fetched yet, failed ones once their refetch is due
+    pub async fn find_unfetched(
+        db: &DbConn,
+        now: NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::Metadata.is_null())
+            .filter(
+                Condition::any().add(Column::Error.is_null()).add(
+                    Condition::all()
+                        .add(Column::RefetchAfter.is_not_null())
+                        .add(Column::RefetchAfter.lte(now)),
+                ),
+            )
+            .order_by_asc(Column::InsertedAt)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    /// insert new instances, the owner of existing ones moves to the giving owner if it was
+    /// transferred later than the stored owner.
+    pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<ExecResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let model = form_data.clone().into_active_model();
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let mut stmt = Entity::insert_many(datas)
+            .build(DatabaseBackend::Postgres)
+            .to_string();
+        stmt = format!(
+            r#"{} ON CONFLICT ("token_id", "token_contract_address_hash") DO UPDATE SET
+            "owner_address_hash" = EXCLUDED."owner_address_hash",
+            "owner_updated_at_block" = EXCLUDED."owner_updated_at_block",
+            "owner_updated_at_log_index" = EXCLUDED."owner_updated_at_log_index",
+            "updated_at" = EXCLUDED."updated_at"
+            WHERE EXCLUDED."owner_address_hash" IS NOT NULL
+            AND ("token_instances"."owner_updated_at_block" IS NULL
+            OR ("token_instances"."owner_updated_at_block", "token_instances"."owner_updated_at_log_index")
+            < (EXCLUDED."owner_updated_at_block", EXCLUDED."owner_updated_at_log_index"))"#,
+            stmt
+        );
+        db.execute(Statement::from_string(DatabaseBackend::Postgres, stmt))
+            .await
+    }
+
+    /// give the instances transferred above `height` back to the owner of the latest transfer
+    /// not above it, the token transfers of the orphaned blocks have to be deleted first.
+    pub async fn restore_owners_above<C>(db: &C, height: i64) -> Result<ExecResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE token_instances ti
+            SET owner_address_hash = latest.to_address_hash,
+                owner_updated_at_block = latest.block_number,
+                owner_updated_at_log_index = latest.log_index,
+                updated_at = now()
+            FROM token_instances orphaned
+            LEFT JOIN LATERAL (
+                SELECT tt.to_address_hash, tt.block_number, tt.log_index
+                FROM token_transfers tt
+                WHERE tt.token_contract_address_hash = orphaned.token_contract_address_hash
+                AND tt.token_id = orphaned.token_id AND tt.block_number <= $1
+                ORDER BY tt.block_number DESC, tt.log_index DESC
+                LIMIT 1
+            ) latest ON true
+            WHERE ti.token_contract_address_hash = orphaned.token_contract_address_hash
+            AND ti.token_id = orphaned.token_id AND orphaned.owner_updated_at_block > $1"#,
+            [height.into()],
+        ))
+        .await
+    }
+
+    /// store the fetched metadata
+    pub async fn update_metadata<C>(
+        db: &C,
+        token_contract_address: Vec<u8>,
+        token_id: BigDecimal,
+        metadata: JsonValue,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::Metadata, Expr::value(metadata))
+            .col_expr(Column::Error, Expr::value(Option::<String>::None))
+            .col_expr(
+                Column::RefetchAfter,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::TokenContractAddressHash.eq(token_contract_address))
+            .filter(Column::TokenId.eq(token_id))
+            .exec(db)
+            .await
+    }
+
+    /// store the error of a failed fetch, it is fetched again from `refetch_after` on or given
+    /// up if that is none.
+    pub async fn mark_fetch_failed<C>(
+        db: &C,
+        token_contract_address: Vec<u8>,
+        token_id: BigDecimal,
+        error: String,
+        retries_count: i32,
+        refetch_after: Option<NaiveDateTime>,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::Error, Expr::value(error))
+            .col_expr(Column::RetriesCount, Expr::value(retries_count))
+            .col_expr(Column::RefetchAfter, Expr::value(refetch_after))
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::TokenContractAddressHash.eq(token_contract_address))
+            .filter(Column::TokenId.eq(token_id))
+            .exec(db)
+            .await
+    }
+}
@@ -1,6 +1,8 @@
 use ::entities::transactions::{ActiveModel, Column, Entity, Model};
+use chrono::Utc;
 use entities::{blocks, token_transfers};
-use sea_orm::*;
+use migration::{Expr, OnConflict};
+use sea_orm::{prelude::Decimal, *};
 
 pub struct Query;
 
@@ -9,6 +11,16 @@ impl Query {
         Entity::find().filter(Column::Hash.eq(hash)).one(db).await
     }
 
+    pub async fn find_by_block_hashes(
+        db: &DbConn,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::BlockHash.is_in(hashes))
+            .all(db)
+            .await
+    }
+
     pub async fn find_by_hash_with_relation(
         db: &DbConn,
         hash: Vec<u8>,
@@ -118,6 +130,53 @@ impl Mutation {
             batch.push(data);
         }
 
-        Entity::insert_many(batch).exec(db).await
+        // transaction may be forked from an orphaned block and collated again
+        Entity::insert_many(batch)
+            .on_conflict(
+                OnConflict::column(Column::Hash)
+                    .update_columns([
+                        Column::BlockHash,
+                        Column::BlockNumber,
+                        Column::Index,
+                        Column::Status,
+                        Column::Error,
+                        Column::RevertReason,
+                        Column::GasUsed,
+                        Column::CumulativeGasUsed,
+                        Column::CreatedContractAddressHash,
+                        Column::CreatedContractCodeIndexedAt,
+                        Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(db)
+            .await
+    }
+
+    // detach transactions from blocks which lost consensus, they keep `old_block_hash`
+    // until collated again.
+    pub async fn fork_by_block_hashes<C>(
+        db: &C,
+        hashes: Vec<Vec<u8>>,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::OldBlockHash, Expr::col(Column::BlockHash).into())
+            .col_expr(Column::BlockHash, Expr::value(Option::<Vec<u8>>::None))
+            .col_expr(Column::BlockNumber, Expr::value(Option::<i32>::None))
+            .col_expr(Column::Index, Expr::value(Option::<i32>::None))
+            .col_expr(Column::Status, Expr::value(Option::<i32>::None))
+            .col_expr(Column::Error, Expr::value(Option::<String>::None))
+            .col_expr(Column::GasUsed, Expr::value(Option::<Decimal>::None))
+            .col_expr(
+                Column::CumulativeGasUsed,
+                Expr::value(Option::<Decimal>::None),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(Utc::now().naive_utc()))
+            .filter(Column::BlockHash.is_in(hashes))
+            .exec(db)
+            .await
     }
 }
@@ -0,0 +1,51 @@
+use ::entities::transaction_forks::{ActiveModel, Column, Entity, Model};
+use migration::OnConflict;
+use sea_orm::*;
+
+pub struct Query;
+
+impl Query {
+    pub async fn find_by_uncle_hash(db: &DbConn, hash: Vec<u8>) -> Result<Vec<Model>, DbErr> {
+        Entity::find()
+            .filter(Column::UncleHash.eq(hash))
+            .order_by_asc(Column::Index)
+            .all(db)
+            .await
+    }
+}
+
+pub struct Mutation;
+
+impl Mutation {
+    pub async fn save<C>(db: &C, form_datas: &[Model]) -> Result<InsertResult<ActiveModel>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut datas = vec![];
+        for form_data in form_datas.iter() {
+            let model = form_data.clone().into_active_model();
+            datas.push(model);
+        }
+
+        if datas.is_empty() {
+            return Err(DbErr::RecordNotInserted);
+        }
+
+        let res = Entity::insert_many(datas)
+            .on_conflict(
+                OnConflict::columns([Column::UncleHash, Column::Index])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(db)
+            .await;
+
+        if matches!(res, Err(DbErr::RecordNotInserted)) {
+            return Ok(InsertResult {
+                last_insert_id: (0, vec![]),
+            });
+        }
+
+        res
+    }
+}
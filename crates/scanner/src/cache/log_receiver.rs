@@ -1,13 +1,23 @@
-use repo::dal::log_receiver_contract::Query as ContractQuery;
-use sea_orm::DbConn;
+use common::consts::LOG_RECEIVER_CONTRACTS_VERSION;
+use ethers::{
+    types::{Address, Log, H256},
+    utils::keccak256,
+};
+use repo::dal::{
+    last_fetched_counter::Query as CounterQuery, log_receiver_contract::Query as ContractQuery,
+};
+use sea_orm::{prelude::Decimal, DbConn, DbErr};
 use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct LogReceiverContract {
+    pub id: i64,
     pub chain_name: String,
-    pub chain_id: u32,
+    pub chain_id: i64,
     pub address: String,
     pub event_sign: String,
+    pub start_height: Option<i64>,
+    pub end_height: Option<i64>,
 }
 
 impl LogReceiverContract {
@@ -17,6 +27,24 @@ impl LogReceiverContract {
             self.chain_name, self.chain_id, self.address, self.event_sign
         )
     }
+
+    pub fn address(&self) -> Option<Address> {
+        self.address.parse().ok()
+    }
+
+    /// topic0 of the event, `event_sign` is either the topic or the signature like
+    /// `Transfer(address,address,uint256)`.
+    pub fn topic(&self) -> Option<H256> {
+        event_topic(&self.event_sign)
+    }
+}
+
+pub fn event_topic(event_sign: &str) -> Option<H256> {
+    match event_sign.parse::<H256>() {
+        Ok(topic) => Some(topic),
+        Err(_) if event_sign.contains('(') => Some(H256::from(keccak256(event_sign))),
+        Err(_) => None,
+    }
 }
 
 pub struct ContractAddrCache {
@@ -48,23 +76,180 @@ impl ContractAddrCache {
     pub fn exist(&self, k: String) -> bool {
         self.contact_map.contains_key(&k)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.contact_map.is_empty()
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = &LogReceiverContract> {
+        self.contact_map.values()
+    }
+
+    /// the contracts and event topics `eth_getLogs` is filtered by
+    pub fn filter_params(&self) -> (Vec<Address>, Vec<H256>) {
+        let mut addresses = vec![];
+        let mut topics = vec![];
+        for contract in self.contact_map.values() {
+            if let (Some(address), Some(topic)) = (contract.address(), contract.topic()) {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+                if !topics.contains(&topic) {
+                    topics.push(topic);
+                }
+            }
+        }
+        (addresses, topics)
+    }
+
+    /// whether the log is emitted by a registered contract with an event registered for it,
+    /// the filter alone also matches the events registered for other contracts.
+    pub fn matches(&self, chain_name: &str, chain_id: i64, log: &Log) -> bool {
+        match log.topics.first() {
+            Some(topic) => self.exist(
+                LogReceiverContract {
+                    id: 0,
+                    chain_name: chain_name.to_string(),
+                    chain_id,
+                    address: format!("{:?}", log.address),
+                    event_sign: format!("{:?}", topic),
+                    start_height: None,
+                    end_height: None,
+                }
+                .cache_key(),
+            ),
+            None => false,
+        }
+    }
 }
 
-#[allow(dead_code)]
-async fn update_contract_cache(conn: &DbConn) -> ContractAddrCache {
+/// the active contracts registered for the chain id, addresses and events are keyed as lower
+/// case hex.
+pub async fn update_contract_cache(
+    conn: &DbConn,
+    chain_name: &str,
+    chain_id: i64,
+) -> Result<ContractAddrCache, DbErr> {
     let mut contract_addr_cache: ContractAddrCache = ContractAddrCache::new();
-    let (contracts, _) = ContractQuery::find_scanner_contract_in_page(conn, 1, 100)
-        .await
-        .unwrap();
+    let contracts = ContractQuery::find_active_by_chain_id(conn, chain_id).await?;
     for v in contracts {
-        let data = LogReceiverContract {
-            chain_name: v.chain_name,
+        let mut data = LogReceiverContract {
+            id: v.id,
+            chain_name: chain_name.to_string(),
             chain_id: v.chain_id,
             address: v.address,
             event_sign: v.event_sign,
+            start_height: v.start_height,
+            end_height: v.end_height,
         };
-        contract_addr_cache.insert(data.cache_key(), data);
+        match (data.address(), data.topic()) {
+            (Some(address), Some(topic)) => {
+                data.address = format!("{:?}", address);
+                data.event_sign = format!("{:?}", topic);
+                contract_addr_cache.insert(data.cache_key(), data);
+            }
+            _ => tracing::warn!(
+                "skip log receiver contract {} with event {}",
+                data.address,
+                data.event_sign
+            ),
+        }
     }
 
-    contract_addr_cache
+    Ok(contract_addr_cache)
+}
+
+/// The contract cache of a chain, reloaded once the admin api changed the contracts.
+pub struct LiveContractAddrCache {
+    chain_name: String,
+    chain_id: i64,
+    version: Option<Option<Decimal>>,
+    cache: ContractAddrCache,
+}
+
+impl LiveContractAddrCache {
+    pub fn new(chain_name: &str, chain_id: i64) -> Self {
+        LiveContractAddrCache {
+            chain_name: chain_name.to_string(),
+            chain_id,
+            version: None,
+            cache: ContractAddrCache::new(),
+        }
+    }
+
+    pub fn cache(&self) -> &ContractAddrCache {
+        &self.cache
+    }
+
+    /// reload the contracts if their version moved, true if they were reloaded.
+    pub async fn refresh(&mut self, conn: &DbConn) -> Result<bool, DbErr> {
+        let version = CounterQuery::find_by_type(conn, LOG_RECEIVER_CONTRACTS_VERSION)
+            .await?
+            .and_then(|counter| counter.value);
+        if self.version == Some(version) {
+            return Ok(false);
+        }
+
+        self.cache = update_contract_cache(conn, &self.chain_name, self.chain_id).await?;
+        self.version = Some(version);
+        Ok(true)
+    }
+
+    /// reload the contracts on the next refresh
+    pub fn invalidate(&mut self) {
+        self.version = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, Log, H256};
+
+    use super::{event_topic, ContractAddrCache, LogReceiverContract};
+
+    #[test]
+    fn test_contract_addr_cache_matches() {
+        let transfer = event_topic("Transfer(address,address,uint256)").unwrap();
+        assert_eq!(event_topic(&format!("{:?}", transfer)).unwrap(), transfer);
+
+        let contract = LogReceiverContract {
+            id: 1,
+            chain_name: "eth".to_string(),
+            chain_id: 5,
+            address: format!("{:?}", Address::from_low_u64_be(1)),
+            event_sign: format!("{:?}", transfer),
+            start_height: None,
+            end_height: None,
+        };
+        let mut cache = ContractAddrCache::new();
+        cache.insert(contract.cache_key(), contract);
+
+        let log = Log {
+            address: Address::from_low_u64_be(1),
+            topics: vec![transfer],
+            ..Default::default()
+        };
+        assert!(cache.matches("eth", 5, &log));
+        assert!(!cache.matches("eth", 1, &log));
+        assert!(!cache.matches(
+            "eth",
+            5,
+            &Log {
+                address: Address::from_low_u64_be(2),
+                ..log.clone()
+            }
+        ));
+        assert!(!cache.matches(
+            "eth",
+            5,
+            &Log {
+                topics: vec![H256::zero()],
+                ..log
+            }
+        ));
+        assert_eq!(
+            cache.filter_params(),
+            (vec![Address::from_low_u64_be(1)], vec![transfer])
+        );
+    }
 }
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use ethers::providers::ProviderError;
 use sea_orm::DbErr;
 use thiserror::Error;
 
@@ -21,6 +24,12 @@ pub enum ScannerError {
         #[source]
         err: DbErr,
     },
+    #[error("Delete Error: source {src}, err {err}")]
+    Delete {
+        src: String,
+        #[source]
+        err: DbErr,
+    },
     #[error("Query Error: {0}")]
     Query(#[source] DbErr),
 
@@ -29,6 +38,9 @@ pub enum ScannerError {
 
     #[error("NewBigDecimal Error: source {0}")]
     NewBigDecimal(String),
+
+    #[error("Reorg Error: {0}")]
+    Reorg(String),
 }
 
 #[derive(Error, Debug)]
@@ -36,6 +48,9 @@ pub enum FetchError {
     #[error("token not found in db")]
     TokenNotFound,
 
+    #[error("Fetch Total Supply Error: {0}")]
+    TokenTotalSupply(String),
+
     #[error("Update Total Supply Error: source {src}, err {err}")]
     TokenUpdateSupply {
         src: String,
@@ -43,3 +58,32 @@ pub enum FetchError {
         err: DbErr,
     },
 }
+
+#[derive(Error, Debug)]
+pub enum RpcError {
+    #[error("Provider Error: method {method}, err {err}")]
+    Provider {
+        method: String,
+        #[source]
+        err: ProviderError,
+    },
+
+    #[error("JsonRpc Error: method {method}, code {code}, message {message}")]
+    JsonRpc {
+        method: String,
+        code: i64,
+        message: String,
+    },
+
+    #[error("Transport Error: {0}")]
+    Transport(String),
+
+    #[error("Timeout Error: method {method} not finished in {timeout:?}")]
+    Timeout { method: String, timeout: Duration },
+
+    #[error("Not Found Error: {0}")]
+    NotFound(String),
+
+    #[error("Invalid Url Error: {0}")]
+    InvalidUrl(String),
+}
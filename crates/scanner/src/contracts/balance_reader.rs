@@ -1,15 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
 use ethers::{
     prelude::abigen,
-    providers::{Http, Provider},
     types::{Address, H160, U256},
 };
 
 use common::consts;
 
+use crate::contracts::multicall::{Multicall, ReadCall, DEFAULT_CHUNK_SIZE};
+use crate::evms::pool::{PoolProvider, RpcPool};
+
 abigen!(
     TokenBalance,
     r#"[
@@ -21,9 +23,11 @@ abigen!(
     Erc1155TokenBalance,
     r#"[
         function balanceOf(address _owner, uint256 _id) external view returns (uint256)
+        function balanceOfBatch(address[] _owners, uint256[] _ids) external view returns (uint256[])
     ]"#,
 );
 
+#[derive(Clone)]
 pub struct TokenBalanceRequest {
     pub token_hash: String,
     pub address_hash: String,
@@ -33,29 +37,191 @@ pub struct TokenBalanceRequest {
 }
 
 pub struct BalanceReader {
-    provider: Provider<Http>,
+    provider: PoolProvider,
+    multicall: Arc<Multicall>,
 }
 
 impl BalanceReader {
     pub fn new(rpc_url: &str) -> Self {
-        let provider = Provider::<Http>::try_from(rpc_url).unwrap();
+        let pool = RpcPool::new(&[rpc_url.to_string()], 0).unwrap();
+
+        Self::from_pool(&pool)
+    }
+
+    pub fn from_pool(pool: &RpcPool) -> Self {
+        BalanceReader {
+            provider: pool.provider(),
+            multicall: Arc::new(Multicall::from_pool(pool, None, DEFAULT_CHUNK_SIZE)),
+        }
+    }
 
-        BalanceReader { provider }
+    pub fn with_multicall(mut self, multicall: Arc<Multicall>) -> Self {
+        self.multicall = multicall;
+        self
     }
 
+    /// balances in the order of the requests. they are aggregated through Multicall3 if it is
+    /// deployed, otherwise ERC-1155 requests of the same contract and block are read with one
+    /// `balanceOfBatch` call. whatever is left is read one by one.
     pub async fn get_balances_of(
         &self,
         token_balance_requests: Vec<TokenBalanceRequest>,
         _abi: Vec<HashMap<String, serde_json::Value>>,
     ) -> Vec<Result<U256>> {
-        let mut resp = vec![];
+        let mut resp: Vec<Option<Result<U256>>> =
+            token_balance_requests.iter().map(|_| None).collect();
+
+        match self.multicall.is_deployed().await {
+            Ok(true) => {
+                self.aggregate_balances(&token_balance_requests, &mut resp)
+                    .await
+            }
+            Ok(false) => {}
+            Err(err) => tracing::warn!(message = "multicall deployment check", err = ?err),
+        }
+
+        for (_, mut group) in group_erc1155_requests(&token_balance_requests).into_iter() {
+            group.retain(|idx| resp[*idx].is_none());
+            if group.len() < 2 {
+                continue;
+            }
+
+            let reqs = group
+                .iter()
+                .map(|idx| token_balance_requests[*idx].clone())
+                .collect::<Vec<_>>();
+            match self.erc1155_balance_of_batch(&reqs).await {
+                Ok(balances) => {
+                    for (idx, balance) in group.into_iter().zip(balances) {
+                        resp[idx] = Some(Ok(balance));
+                    }
+                }
+                Err(err) => tracing::warn!(message = "erc1155 balance of batch", err = ?err),
+            }
+        }
+
+        let mut balances = vec![];
+        for (req, fetched) in token_balance_requests.into_iter().zip(resp) {
+            match fetched {
+                Some(balance) => balances.push(balance),
+                None => balances.push(self.token_balance_call_contract(req).await),
+            }
+        }
+
+        balances
+    }
+
+    /// fill in the balances read through Multicall3, calls of a block are aggregated together.
+    async fn aggregate_balances(
+        &self,
+        reqs: &[TokenBalanceRequest],
+        resp: &mut [Option<Result<U256>>],
+    ) {
+        let client = Arc::new(&self.provider);
+        let mut blocks: BTreeMap<Option<u64>, Vec<(usize, ReadCall)>> = BTreeMap::new();
+        for (idx, req) in reqs.iter().enumerate() {
+            let (Ok(contract_address), Ok(user)) = (
+                req.token_hash.parse::<Address>(),
+                req.address_hash.parse::<H160>(),
+            ) else {
+                continue;
+            };
+            let call = match (req.token_type, &req.token_id) {
+                (consts::TokenKind::ERC20, _) => ReadCall::from_call(
+                    &TokenBalance::new(contract_address, client.clone()).balance_of(user),
+                ),
+                (consts::TokenKind::ERC1155, Some(token_id)) => {
+                    let Ok(token_id) = U256::from_dec_str(token_id) else {
+                        continue;
+                    };
+                    ReadCall::from_call(
+                        &Erc1155TokenBalance::new(contract_address, client.clone())
+                            .balance_of(user, token_id),
+                    )
+                }
+                _ => None,
+            };
+            if let Some(call) = call {
+                blocks
+                    .entry(req.block_number)
+                    .or_default()
+                    .push((idx, call));
+            }
+        }
+
+        for (block_number, calls) in blocks.into_iter() {
+            let read_calls = calls
+                .iter()
+                .map(|(_, call)| call.clone())
+                .collect::<Vec<_>>();
+            match self.multicall.aggregate(&read_calls, block_number).await {
+                Ok(results) => {
+                    for ((idx, _), result) in calls.iter().zip(results) {
+                        let balance = result
+                            .and_then(|token| {
+                                token.into_uint().ok_or_else(|| anyhow!("not a uint"))
+                            })
+                            .map_err(|err| {
+                                anyhow!(
+                                    "Multicall get balance: contract_addr:{}, user:{}, err:{}",
+                                    reqs[*idx].token_hash,
+                                    reqs[*idx].address_hash,
+                                    err
+                                )
+                            });
+                        resp[*idx] = Some(balance);
+                    }
+                }
+                Err(err) => tracing::warn!(message = "multicall balances", err = ?err),
+            }
+        }
+    }
+
+    /// `balanceOfBatch` of requests sharing the ERC-1155 contract and the block number.
+    pub async fn erc1155_balance_of_batch(
+        &self,
+        reqs: &[TokenBalanceRequest],
+    ) -> Result<Vec<U256>> {
+        let Some(first) = reqs.first() else {
+            return Ok(vec![]);
+        };
+
+        let mut owners = vec![];
+        let mut ids = vec![];
+        for req in reqs.iter() {
+            owners.push(req.address_hash.parse::<H160>()?);
+            let token_id = req
+                .token_id
+                .as_ref()
+                .ok_or_else(|| anyhow!("Erc1155 balance request without token id"))?;
+            ids.push(U256::from_dec_str(token_id)?);
+        }
 
-        for req in token_balance_requests {
-            let res = self.token_balance_call_contract(req).await;
-            resp.push(res);
+        let client = Arc::new(&self.provider);
+        let contract_address: Address = first.token_hash.parse()?;
+        let contract = Erc1155TokenBalance::new(contract_address, client);
+        let call = contract.balance_of_batch(owners, ids);
+        let call = match first.block_number {
+            Some(num) => call.block(num),
+            None => call,
+        };
+        let balances = call.call().await.map_err(|err| {
+            anyhow!(
+                "Erc1155 get balance batch: contract_addr:{}, err:{}",
+                first.token_hash,
+                err
+            )
+        })?;
+        if balances.len() != reqs.len() {
+            return Err(anyhow!(
+                "Erc1155 get balance batch: contract_addr:{}, {} balances for {} requests",
+                first.token_hash,
+                balances.len(),
+                reqs.len()
+            ));
         }
 
-        resp
+        Ok(balances)
     }
 
     pub async fn token_balance_call_contract(
@@ -107,11 +273,51 @@ impl BalanceReader {
     }
 }
 
+/// indexes of the ERC-1155 requests grouped by contract and block number
+fn group_erc1155_requests(
+    reqs: &[TokenBalanceRequest],
+) -> BTreeMap<(String, Option<u64>), Vec<usize>> {
+    let mut groups: BTreeMap<(String, Option<u64>), Vec<usize>> = BTreeMap::new();
+    for (idx, req) in reqs.iter().enumerate() {
+        if req.token_type == consts::TokenKind::ERC1155 && req.token_id.is_some() {
+            groups
+                .entry((req.token_hash.to_lowercase(), req.block_number))
+                .or_default()
+                .push(idx);
+        }
+    }
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use common::consts;
 
-    use super::{BalanceReader, TokenBalanceRequest};
+    use super::{group_erc1155_requests, BalanceReader, TokenBalanceRequest};
+
+    #[test]
+    fn test_group_erc1155_requests() {
+        let req = |token_hash: &str, block_number: u64, token_id: Option<&str>, token_type| {
+            TokenBalanceRequest {
+                token_hash: token_hash.to_string(),
+                address_hash: "0xeA400aF528338401EF19494DA6010DCefdb09804".to_string(),
+                block_number: Some(block_number),
+                token_id: token_id.map(|id| id.to_string()),
+                token_type,
+            }
+        };
+        let reqs = vec![
+            req("0xAA", 1, Some("1"), consts::TokenKind::ERC1155),
+            req("0xbb", 1, None, consts::TokenKind::ERC20),
+            req("0xaa", 1, Some("2"), consts::TokenKind::ERC1155),
+            req("0xaa", 2, Some("1"), consts::TokenKind::ERC1155),
+        ];
+
+        let groups = group_erc1155_requests(&reqs);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&("0xaa".to_string(), Some(1))], vec![0, 2]);
+        assert_eq!(groups[&("0xaa".to_string(), Some(2))], vec![3]);
+    }
 
     #[test]
     fn test_total_supply() {
@@ -1,14 +1,17 @@
 use anyhow::{anyhow, Error};
 use ethers::{
     prelude::abigen,
-    providers::{Http, Provider},
     types::{Address, H160, U256},
 };
 
 use std::sync::Arc;
 
+use crate::contracts::multicall::{Multicall, ReadCall, DEFAULT_CHUNK_SIZE};
+use crate::evms::pool::{PoolProvider, RpcPool};
+
 pub struct IERC20Call {
-    provider: Provider<Http>,
+    provider: PoolProvider,
+    multicall: Arc<Multicall>,
 }
 
 abigen!(
@@ -30,8 +33,20 @@ abigen!(
 
 impl IERC20Call {
     pub fn new(rpc_url: &str) -> IERC20Call {
-        let provider = Provider::<Http>::try_from(rpc_url).unwrap();
-        Self { provider }
+        let pool = RpcPool::new(&[rpc_url.to_string()], 0).unwrap();
+        Self::from_pool(&pool)
+    }
+
+    pub fn from_pool(pool: &RpcPool) -> IERC20Call {
+        Self {
+            provider: pool.provider(),
+            multicall: Arc::new(Multicall::from_pool(pool, None, DEFAULT_CHUNK_SIZE)),
+        }
+    }
+
+    pub fn with_multicall(mut self, multicall: Arc<Multicall>) -> IERC20Call {
+        self.multicall = multicall;
+        self
     }
 
     pub async fn total_supply(
@@ -106,10 +121,22 @@ impl IERC20Call {
         }
     }
 
+    /// name, symbol, decimals and total supply read with one `aggregate3` call, they are read
+    /// one by one if Multicall3 is not deployed.
     pub async fn metadata(
         &self,
         contract_address: &str,
     ) -> Result<(String, String, u8, U256), Error> {
+        match self.multicall.is_deployed().await {
+            Ok(true) => match self.aggregate_metadata(contract_address).await {
+                Ok(Some(metadata)) => return Ok(metadata),
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            },
+            Ok(false) => {}
+            Err(err) => tracing::warn!(message = "multicall deployment check", err = ?err),
+        }
+
         let name = match self.name(contract_address).await {
             Ok(s) => s.to_string(),
             Err(e) => return Err(e),
@@ -132,6 +159,55 @@ impl IERC20Call {
 
         Ok((name, symbol, decimals, total_supply))
     }
+
+    /// `None` if the aggregate call itself failed and the calls have to be made one by one.
+    async fn aggregate_metadata(
+        &self,
+        contract_address: &str,
+    ) -> Result<Option<(String, String, u8, U256)>, Error> {
+        let client = Arc::new(&self.provider);
+        let address: Address = contract_address.parse()?;
+        let contract = IERC20::new(address, client);
+        let calls = [
+            ReadCall::from_call(&contract.name()),
+            ReadCall::from_call(&contract.symbol()),
+            ReadCall::from_call(&contract.decimals()),
+            ReadCall::from_call(&contract.total_supply()),
+        ]
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow!("Erc20 build metadata calls: {}", contract_address))?;
+
+        let mut results = match self.multicall.aggregate(&calls, None).await {
+            Ok(results) => results.into_iter(),
+            Err(err) => {
+                tracing::warn!(message = "multicall erc20 metadata", err = ?err);
+                return Ok(None);
+            }
+        };
+        let mut next = |field: &str| match results.next() {
+            Some(Ok(token)) => Ok(token),
+            Some(Err(err)) => Err(anyhow!("Erc20 get {}: {}", field, err)),
+            None => Err(anyhow!("Erc20 get {}: missing result", field)),
+        };
+
+        let name = next("name")?
+            .into_string()
+            .ok_or_else(|| anyhow!("Erc20 get name: not a string"))?;
+        let symbol = next("symbol")?
+            .into_string()
+            .ok_or_else(|| anyhow!("Erc20 get symbol: not a string"))?;
+        let decimals = next("decimals")?
+            .into_uint()
+            .filter(|decimals| *decimals <= U256::from(u8::MAX))
+            .ok_or_else(|| anyhow!("Erc20 get decimals: not an uint8"))?
+            .as_u32() as u8;
+        let total_supply = next("total supply")?
+            .into_uint()
+            .ok_or_else(|| anyhow!("Erc20 get total supply: not an uint"))?;
+
+        Ok(Some((name, symbol, decimals, total_supply)))
+    }
 }
 
 #[cfg(test)]
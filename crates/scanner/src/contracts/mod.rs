@@ -1,3 +1,5 @@
 pub mod balance_reader;
 pub mod decode;
 pub mod erc20;
+pub mod multicall;
+pub mod nft;
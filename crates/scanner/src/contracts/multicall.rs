@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use ethers::{
+    abi::{Function, Token},
+    contract::{
+        multicall_contract::{Call3, Multicall3},
+        ContractCall, MULTICALL_ADDRESS,
+    },
+    providers::Middleware,
+    types::{Address, Bytes},
+};
+use tokio::sync::OnceCell;
+
+use crate::evms::pool::{PoolProvider, RpcPool};
+
+pub const DEFAULT_CHUNK_SIZE: usize = 100;
+
+/// a read only contract call which can be aggregated
+#[derive(Clone, Debug)]
+pub struct ReadCall {
+    pub target: Address,
+    pub data: Bytes,
+    pub function: Function,
+}
+
+impl ReadCall {
+    pub fn from_call<M: Middleware, D>(call: &ContractCall<M, D>) -> Option<ReadCall> {
+        Some(ReadCall {
+            target: *call.tx.to()?.as_address()?,
+            data: call.tx.data()?.clone(),
+            function: call.function.clone(),
+        })
+    }
+}
+
+/// Aggregates read calls with `aggregate3` of Multicall3, every call is allowed to fail on
+/// its own. Callers read one by one when the contract is not deployed on the chain.
+pub struct Multicall {
+    provider: PoolProvider,
+    address: Address,
+    chunk_size: usize,
+    deployed: OnceCell<bool>,
+}
+
+impl Multicall {
+    pub fn new(rpc_url: &str) -> Multicall {
+        let pool = RpcPool::new(&[rpc_url.to_string()], 0).unwrap();
+        Self::from_pool(&pool, None, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// `address` defaults to the address Multicall3 is deployed at on most chains.
+    pub fn from_pool(pool: &RpcPool, address: Option<Address>, chunk_size: usize) -> Multicall {
+        Multicall {
+            provider: pool.provider(),
+            address: address.unwrap_or(MULTICALL_ADDRESS),
+            chunk_size: chunk_size.max(1),
+            deployed: OnceCell::new(),
+        }
+    }
+
+    /// whether the contract has code, a successful check is kept for good.
+    pub async fn is_deployed(&self) -> Result<bool> {
+        let deployed = self
+            .deployed
+            .get_or_try_init(|| async {
+                self.provider
+                    .get_code(self.address, None)
+                    .await
+                    .map(|code| !code.is_empty())
+                    .map_err(|err| anyhow!("Multicall3 get code: {}", err))
+            })
+            .await?;
+        Ok(*deployed)
+    }
+
+    /// results in the order of the calls, `chunk_size` calls are sent per `aggregate3`.
+    pub async fn aggregate(
+        &self,
+        calls: &[ReadCall],
+        block_number: Option<u64>,
+    ) -> Result<Vec<Result<Token>>> {
+        let client = Arc::new(&self.provider);
+        let contract = Multicall3::new(self.address, client);
+
+        let mut resp = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(self.chunk_size) {
+            let call3s = chunk
+                .iter()
+                .map(|call| Call3 {
+                    target: call.target,
+                    allow_failure: true,
+                    call_data: call.data.clone(),
+                })
+                .collect::<Vec<_>>();
+            let aggregate = contract.aggregate_3(call3s);
+            let aggregate = match block_number {
+                Some(num) => aggregate.block(num),
+                None => aggregate,
+            };
+            let results = aggregate
+                .call()
+                .await
+                .map_err(|err| anyhow!("Multicall3 aggregate3: {}", err))?;
+            if results.len() != chunk.len() {
+                bail!(
+                    "Multicall3 aggregate3: {} results for {} calls",
+                    results.len(),
+                    chunk.len()
+                );
+            }
+
+            for (call, result) in chunk.iter().zip(results) {
+                resp.push(decode_result(
+                    &call.function,
+                    result.success,
+                    &result.return_data,
+                ));
+            }
+        }
+
+        Ok(resp)
+    }
+}
+
+fn decode_result(function: &Function, success: bool, return_data: &Bytes) -> Result<Token> {
+    if !success || return_data.is_empty() {
+        bail!("{} reverted", function.name);
+    }
+
+    let mut tokens = function.decode_output(return_data)?;
+    match tokens.len() {
+        1 => Ok(tokens.remove(0)),
+        _ => Ok(Token::Tuple(tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ethers::{
+        abi::{self, Token},
+        providers::{Http, Provider},
+        types::{Address, Bytes, U256},
+    };
+
+    use crate::contracts::erc20::IERC20;
+
+    use super::{decode_result, ReadCall};
+
+    #[test]
+    fn test_decode_result() {
+        let provider = Provider::<Http>::try_from("http://localhost:8545").unwrap();
+        let contract = IERC20::new(Address::from_low_u64_be(1), Arc::new(provider));
+        let call = ReadCall::from_call(&contract.total_supply()).unwrap();
+        assert_eq!(call.target, Address::from_low_u64_be(1));
+
+        let data = Bytes::from(abi::encode(&[Token::Uint(U256::from(42))]));
+        assert_eq!(
+            decode_result(&call.function, true, &data).unwrap(),
+            Token::Uint(U256::from(42))
+        );
+        assert!(decode_result(&call.function, false, &data).is_err());
+        assert!(decode_result(&call.function, true, &Bytes::default()).is_err());
+    }
+}
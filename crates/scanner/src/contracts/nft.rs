@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    prelude::abigen,
+    types::{Address, U256},
+};
+
+use common::consts;
+
+use crate::evms::pool::{PoolProvider, RpcPool};
+
+abigen!(
+    IERC721Metadata,
+    r#"[
+        function tokenURI(uint256 tokenId) external view returns (string)
+    ]"#,
+);
+
+abigen!(
+    IERC1155MetadataURI,
+    r#"[
+        function uri(uint256 id) external view returns (string)
+    ]"#,
+);
+
+pub struct INFTCall {
+    provider: PoolProvider,
+}
+
+impl INFTCall {
+    pub fn new(rpc_url: &str) -> INFTCall {
+        let pool = RpcPool::new(&[rpc_url.to_string()], 0).unwrap();
+        Self::from_pool(&pool)
+    }
+
+    pub fn from_pool(pool: &RpcPool) -> INFTCall {
+        Self {
+            provider: pool.provider(),
+        }
+    }
+
+    /// `tokenURI` of ERC-721 or `uri` of ERC-1155 tokens, the `{id}` placeholder of ERC-1155
+    /// uris is substituted with the token id.
+    pub async fn token_uri(
+        &self,
+        contract_address: &str,
+        token_id: U256,
+        token_type: consts::TokenKind,
+    ) -> Result<String> {
+        let client = Arc::new(&self.provider);
+        let address: Address = contract_address.parse()?;
+        match token_type {
+            consts::TokenKind::ERC721 => {
+                let contract = IERC721Metadata::new(address, client);
+                contract
+                    .token_uri(token_id)
+                    .call()
+                    .await
+                    .map_err(|err| anyhow!("Erc721 get token uri: {}", err))
+            }
+            consts::TokenKind::ERC1155 => {
+                let contract = IERC1155MetadataURI::new(address, client);
+                let uri = contract
+                    .uri(token_id)
+                    .call()
+                    .await
+                    .map_err(|err| anyhow!("Erc1155 get uri: {}", err))?;
+                Ok(substitute_id(&uri, token_id))
+            }
+            _ => Err(anyhow!("token of {} has no token uri", contract_address)),
+        }
+    }
+}
+
+/// the id is the lowercase hex of the token id padded to 64 characters, see EIP-1155.
+fn substitute_id(uri: &str, token_id: U256) -> String {
+    uri.replace("{id}", &format!("{:064x}", token_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::substitute_id;
+
+    #[test]
+    fn test_substitute_id() {
+        assert_eq!(
+            substitute_id("https://token-cdn-domain/{id}.json", U256::from(314592)),
+            "https://token-cdn-domain/000000000000000000000000000000000000000000000000000000000004cce0.json"
+        );
+        assert_eq!(
+            substitute_id("ipfs://cid/1.json", U256::from(1)),
+            "ipfs://cid/1.json"
+        );
+    }
+}
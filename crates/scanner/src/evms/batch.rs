@@ -0,0 +1,330 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use config::chain::Tracer;
+use ethers::providers::JsonRpcError;
+use ethers::types::{Block, Trace, Transaction, TransactionReceipt, TxHash, U64};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use super::pool::RpcPool;
+use super::tracer::{parse_traces, trace_method, trace_request};
+use crate::common::err::RpcError;
+
+/// a block with its traces and receipts
+pub type BlockData = (Block<Transaction>, Vec<Trace>, Vec<TransactionReceipt>);
+
+/// Sends JSON-RPC batch requests to the endpoints of the pool, so the data of many blocks is
+/// fetched in a single round trip.
+#[derive(Debug)]
+pub struct BatchClient {
+    client: reqwest::Client,
+    pool: RpcPool,
+    timeout: Duration,
+    tracer: Tracer,
+    // cleared once a node answers `eth_getBlockReceipts` as unsupported
+    block_receipts: AtomicBool,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResponse {
+    id: u64,
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+impl BatchClient {
+    pub fn new(pool: RpcPool, timeout: Duration) -> BatchClient {
+        BatchClient {
+            client: reqwest::Client::new(),
+            pool,
+            timeout,
+            tracer: Tracer::default(),
+            block_receipts: AtomicBool::new(true),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = tracer;
+    }
+
+    /// send all calls in one batch, the results are in the order of the calls.
+    pub async fn request(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, JsonRpcError>>, RpcError> {
+        if calls.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let body = calls
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+            })
+            .collect::<Vec<_>>();
+
+        let mut last_err = RpcError::Transport("no rpc endpoint configured".to_string());
+        for url in self.pool.urls() {
+            match self.post(&url, &body).await {
+                Ok(resps) => return parse_batch_response(calls.len(), resps),
+                Err(err) => {
+                    tracing::warn!(message = "batch request failed, fail over", url, err = ?err);
+                    last_err = err;
+                }
+            }
+        }
+
+        Err(last_err)
+    }
+
+    async fn post(&self, url: &str, body: &[Value]) -> Result<Vec<BatchResponse>, RpcError> {
+        let send = async {
+            self.client
+                .post(url)
+                .json(body)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<BatchResponse>>()
+                .await
+        };
+
+        match tokio::time::timeout(self.timeout, send).await {
+            Ok(res) => res.map_err(|err| RpcError::Transport(err.to_string())),
+            Err(_) => Err(RpcError::Timeout {
+                method: "batch".to_string(),
+                timeout: self.timeout,
+            }),
+        }
+    }
+
+    /// fetch blocks with their traces of the configured tracer and receipts, none for blocks the node does not have yet.
+    /// receipts are fetched per transaction when the node lacks `eth_getBlockReceipts`.
+    pub async fn get_blocks(&self, numbers: &[u64]) -> Result<Vec<Option<BlockData>>, RpcError> {
+        let with_block_receipts = self.block_receipts.load(Ordering::Relaxed);
+        let mut calls = vec![];
+        for number in numbers.iter() {
+            let number = U64::from(*number);
+            calls.push(("eth_getBlockByNumber", json!([number, true])));
+            if let Some(call) = trace_request(&self.tracer, number) {
+                calls.push(call);
+            }
+            if with_block_receipts {
+                calls.push(("eth_getBlockReceipts", json!([number])));
+            }
+        }
+
+        let trace_method = trace_method(&self.tracer);
+        let mut results = self.request(&calls).await?.into_iter();
+        let mut blocks = vec![];
+        let mut without_receipts = vec![];
+        for idx in 0..numbers.len() {
+            let block = results.next().unwrap();
+            let traces = trace_method.map(|method| (method, results.next().unwrap()));
+            let receipts = if with_block_receipts {
+                results.next()
+            } else {
+                None
+            };
+
+            let Some(block) = decode::<Option<Block<Transaction>>>("eth_getBlockByNumber", block)?
+            else {
+                blocks.push(None);
+                continue;
+            };
+            let traces = match traces {
+                Some((method, traces)) => {
+                    let value = decode::<Value>(method, traces)?;
+                    parse_traces(&self.tracer, &block, value)?
+                }
+                None => vec![],
+            };
+            let receipts = match receipts {
+                Some(Err(err)) if is_unsupported(&err) => {
+                    tracing::warn!(message = "eth_getBlockReceipts unsupported, fetch receipts per transaction", err = ?err);
+                    self.block_receipts.store(false, Ordering::Relaxed);
+                    without_receipts.push(idx);
+                    vec![]
+                }
+                Some(receipts) => {
+                    decode::<Vec<TransactionReceipt>>("eth_getBlockReceipts", receipts)?
+                }
+                None => {
+                    without_receipts.push(idx);
+                    vec![]
+                }
+            };
+            blocks.push(Some((block, traces, receipts)));
+        }
+
+        if !without_receipts.is_empty() {
+            self.fill_receipts(&mut blocks, &without_receipts).await?;
+        }
+
+        Ok(blocks)
+    }
+
+    /// fetch block headers with the hashes of their transactions, none for blocks the node
+    /// does not have yet.
+    pub async fn get_headers(
+        &self,
+        numbers: &[u64],
+    ) -> Result<Vec<Option<Block<TxHash>>>, RpcError> {
+        let calls = numbers
+            .iter()
+            .map(|number| ("eth_getBlockByNumber", json!([U64::from(*number), false])))
+            .collect::<Vec<_>>();
+
+        self.request(&calls)
+            .await?
+            .into_iter()
+            .map(|res| decode("eth_getBlockByNumber", res))
+            .collect()
+    }
+
+    /// fetch transactions by hash, none for transactions the node does not know.
+    pub async fn get_transactions(
+        &self,
+        hashes: &[TxHash],
+    ) -> Result<Vec<Option<Transaction>>, RpcError> {
+        let calls = hashes
+            .iter()
+            .map(|hash| ("eth_getTransactionByHash", json!([hash])))
+            .collect::<Vec<_>>();
+
+        self.request(&calls)
+            .await?
+            .into_iter()
+            .map(|res| decode("eth_getTransactionByHash", res))
+            .collect()
+    }
+
+    async fn fill_receipts(
+        &self,
+        blocks: &mut [Option<BlockData>],
+        indexes: &[usize],
+    ) -> Result<(), RpcError> {
+        let mut calls = vec![];
+        for idx in indexes.iter() {
+            if let Some((block, _, _)) = &blocks[*idx] {
+                for tx in block.transactions.iter() {
+                    calls.push(("eth_getTransactionReceipt", json!([tx.hash])));
+                }
+            }
+        }
+
+        let mut results = self.request(&calls).await?.into_iter();
+        for idx in indexes.iter() {
+            if let Some((block, _, receipts)) = &mut blocks[*idx] {
+                for tx in block.transactions.iter() {
+                    let receipt = decode::<Option<TransactionReceipt>>(
+                        "eth_getTransactionReceipt",
+                        results.next().unwrap(),
+                    )?;
+                    match receipt {
+                        Some(receipt) => receipts.push(receipt),
+                        None => {
+                            return Err(RpcError::NotFound(format!("receipt {:#x}", tx.hash)));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_batch_response(
+    len: usize,
+    resps: Vec<BatchResponse>,
+) -> Result<Vec<Result<Value, JsonRpcError>>, RpcError> {
+    let mut results = (0..len).map(|_| None).collect::<Vec<_>>();
+    for resp in resps.into_iter() {
+        let Some(slot) = results.get_mut(resp.id as usize) else {
+            continue;
+        };
+        *slot = Some(match resp.error {
+            Some(err) => Err(err),
+            None => Ok(resp.result.unwrap_or(Value::Null)),
+        });
+    }
+
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(id, res)| {
+            res.ok_or_else(|| RpcError::Transport(format!("missing batch response of id {}", id)))
+        })
+        .collect()
+}
+
+fn decode<T: DeserializeOwned>(
+    method: &str,
+    res: Result<Value, JsonRpcError>,
+) -> Result<T, RpcError> {
+    match res {
+        Ok(value) => serde_json::from_value(value)
+            .map_err(|err| RpcError::Transport(format!("decode {} response: {}", method, err))),
+        Err(err) => Err(RpcError::JsonRpc {
+            method: method.to_string(),
+            code: err.code,
+            message: err.message,
+        }),
+    }
+}
+
+/// -32601 method not found, some nodes answer with a plain message instead
+fn is_unsupported(err: &JsonRpcError) -> bool {
+    let message = err.message.to_lowercase();
+    err.code == -32601
+        || message.contains("not supported")
+        || message.contains("does not exist")
+        || message.contains("not available")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::JsonRpcError;
+    use serde_json::json;
+
+    use super::{is_unsupported, parse_batch_response, BatchResponse};
+
+    #[test]
+    fn test_parse_batch_response() {
+        let resps: Vec<BatchResponse> = serde_json::from_value(json!([
+            {"jsonrpc": "2.0", "id": 1, "error": {"code": -32601, "message": "the method eth_getBlockReceipts does not exist/is not available"}},
+            {"jsonrpc": "2.0", "id": 0, "result": "0x10"},
+            {"jsonrpc": "2.0", "id": 2, "result": null}
+        ]))
+        .unwrap();
+
+        let results = parse_batch_response(3, resps).unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &json!("0x10"));
+        assert!(is_unsupported(results[1].as_ref().unwrap_err()));
+        assert!(results[2].as_ref().unwrap().is_null());
+    }
+
+    #[test]
+    fn test_parse_batch_response_missing() {
+        let resps: Vec<BatchResponse> =
+            serde_json::from_value(json!([{"jsonrpc": "2.0", "id": 0, "result": "0x10"}])).unwrap();
+        assert!(parse_batch_response(2, resps).is_err());
+    }
+
+    #[test]
+    fn test_is_unsupported() {
+        let err = JsonRpcError {
+            code: -32000,
+            message: "execution reverted".to_string(),
+            data: None,
+        };
+        assert!(!is_unsupported(&err));
+    }
+}
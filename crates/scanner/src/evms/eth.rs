@@ -1,10 +1,20 @@
-use ethers::providers::{Middleware, Provider};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config::chain::Tracer;
+use ethers::providers::{Middleware, ProviderError, RpcError as _};
 use ethers::types::{
-    Address, Block, BlockId, BlockNumber, BlockTrace, Bytes, Trace, TraceType, Transaction,
-    TransactionReceipt, TxHash, H160, H256, U256, U64,
+    Address, Block, BlockId, BlockNumber, BlockTrace, Bytes, Filter, Log, Trace, TraceType,
+    Transaction, TransactionReceipt, TxHash, H160, H256, U256, U64,
 };
 use serde::{Deserialize, Serialize};
 
+use super::batch::{BatchClient, BlockData};
+use super::heads::NewHeads;
+use super::pool::{PoolProvider, RpcPool};
+use crate::common::err::RpcError;
+
 /*
     1. event需要分开，下游的接收者只关心某一类event
         通过address判断是什么交易
@@ -55,151 +65,397 @@ pub struct LogDetail {
     pub removed: Option<bool>,
 }
 
+impl From<&Log> for LogDetail {
+    fn from(l: &Log) -> Self {
+        LogDetail {
+            address: l.address,
+            topics: l.topics.clone(),
+            data: l.data.clone(),
+            transaction_log_index: l.transaction_log_index,
+            log_index: l.log_index,
+            log_type: l.log_type.clone(),
+            removed: l.removed,
+        }
+    }
+}
+
 pub struct EthCli {
-    provider: Provider<ethers::providers::Http>,
+    provider: PoolProvider,
+    batch: BatchClient,
+    retries: u32,
+    timeout: Duration,
+    heads_url: Option<String>,
 }
 
 // TODO trace fail transaction
 impl EthCli {
-    pub fn new(url: &str) -> EthCli {
-        let provider = Provider::try_from(url).unwrap();
-        EthCli { provider }
+    pub fn new(url: &str) -> Result<EthCli, RpcError> {
+        let pool = RpcPool::new(&[url.to_string()], 0)?;
+        Ok(Self::from_pool(&pool))
     }
 
-    pub async fn get_block_number(&self) -> u64 {
-        let block_number = self.provider.get_block_number().await.unwrap();
-        block_number.as_u64()
+    pub fn from_pool(pool: &RpcPool) -> EthCli {
+        EthCli {
+            provider: pool.provider(),
+            batch: BatchClient::new(pool.clone(), DEFAULT_TIMEOUT),
+            retries: DEFAULT_RETRIES,
+            timeout: DEFAULT_TIMEOUT,
+            heads_url: None,
+        }
     }
 
-    pub async fn get_block(&self, block_number: u64) -> Block<TxHash> {
-        let block = self
-            .provider
-            .get_block(BlockNumber::Number(block_number.into()))
-            .await
-            .unwrap();
-        block.unwrap()
+    /// transient failures are retried `retries` times, each attempt is bounded by `timeout`.
+    pub fn with_retry(mut self, retries: u32, timeout: Duration) -> EthCli {
+        self.retries = retries;
+        self.timeout = timeout;
+        self.batch.set_timeout(timeout);
+        self
+    }
+
+    /// api used to trace the internal transactions of fetched blocks.
+    pub fn with_tracer(mut self, tracer: Tracer) -> EthCli {
+        self.batch.set_tracer(tracer);
+        self
+    }
+
+    /// websocket url or ipc path used to subscribe `newHeads`.
+    pub fn with_new_heads(mut self, url: Option<String>) -> EthCli {
+        self.heads_url = url;
+        self
+    }
+
+    /// subscribe `newHeads` if a websocket or ipc endpoint is configured.
+    pub fn new_heads(&self) -> Option<Arc<NewHeads>> {
+        self.heads_url.clone().map(NewHeads::subscribe)
     }
 
-    pub async fn get_block_with_tx(&self, block_number: u64) -> Option<Block<Transaction>> {
-        self.provider
-            .get_block_with_txs(BlockNumber::Number(block_number.into()))
+    async fn call<T, F, Fut>(&self, method: &str, f: F) -> Result<T, RpcError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        retry(method, self.retries, self.timeout, f).await
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64, RpcError> {
+        let block_number = self
+            .call("eth_blockNumber", || self.provider.get_block_number())
+            .await?;
+        Ok(block_number.as_u64())
+    }
+
+    // number of the block behind a tag like `finalized` or `safe`, none if the node does not support it.
+    pub async fn get_tag_block_number(&self, tag: BlockNumber) -> Option<u64> {
+        match self
+            .call("eth_getBlockByNumber", || self.provider.get_block(tag))
             .await
-            .unwrap()
+        {
+            Ok(block) => block.and_then(|b| b.number).map(|n| n.as_u64()),
+            Err(_) => None,
+        }
     }
 
-    pub async fn get_block_by_hash(&self, block_hash: H256) -> Block<TxHash> {
+    pub async fn get_block(&self, block_number: u64) -> Result<Block<TxHash>, RpcError> {
         let block = self
-            .provider
-            .get_block(BlockId::Hash(block_hash))
-            .await
-            .unwrap();
-        block.unwrap()
+            .call("eth_getBlockByNumber", || {
+                self.provider
+                    .get_block(BlockNumber::Number(block_number.into()))
+            })
+            .await?;
+        block.ok_or_else(|| RpcError::NotFound(format!("block {}", block_number)))
+    }
+
+    pub async fn get_block_with_tx(
+        &self,
+        block_number: u64,
+    ) -> Result<Option<Block<Transaction>>, RpcError> {
+        self.call("eth_getBlockByNumber", || {
+            self.provider
+                .get_block_with_txs(BlockNumber::Number(block_number.into()))
+        })
+        .await
+    }
+
+    /// fetch blocks with their traces and receipts in one batch request, none for blocks the
+    /// node does not have yet. failed transports are retried like single calls.
+    pub async fn get_blocks(&self, numbers: &[u64]) -> Result<Vec<Option<BlockData>>, RpcError> {
+        self.batch_call(|| self.batch.get_blocks(numbers)).await
     }
 
-    pub async fn get_block_receipt(&self, block_number: u64) -> Vec<TransactionReceipt> {
-        self.provider
-            .get_block_receipts(BlockNumber::Number(block_number.into()))
+    /// fetch block headers in one batch request, none for blocks the node does not have yet.
+    pub async fn get_headers(
+        &self,
+        numbers: &[u64],
+    ) -> Result<Vec<Option<Block<TxHash>>>, RpcError> {
+        self.batch_call(|| self.batch.get_headers(numbers)).await
+    }
+
+    /// fetch transactions in one batch request, none for transactions the node does not know.
+    pub async fn get_transactions(
+        &self,
+        hashes: &[TxHash],
+    ) -> Result<Vec<Option<Transaction>>, RpcError> {
+        self.batch_call(|| self.batch.get_transactions(hashes))
             .await
-            .unwrap()
+    }
+
+    // failed transports of batch requests are retried like single calls.
+    async fn batch_call<T, F, Fut>(&self, f: F) -> Result<T, RpcError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, RpcError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match f().await {
+                Ok(res) => return Ok(res),
+                Err(err @ (RpcError::Transport(_) | RpcError::Timeout { .. })) => err,
+                Err(err) => return Err(err),
+            };
+
+            if attempt >= self.retries {
+                return Err(err);
+            }
+            tracing::warn!(message = "batch call failed, retrying", attempt, err = ?err);
+            tokio::time::sleep(backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    pub async fn get_block_by_hash(&self, block_hash: H256) -> Result<Block<TxHash>, RpcError> {
+        let block = self
+            .call("eth_getBlockByHash", || {
+                self.provider.get_block(BlockId::Hash(block_hash))
+            })
+            .await?;
+        block.ok_or_else(|| RpcError::NotFound(format!("block {:#x}", block_hash)))
+    }
+
+    pub async fn get_uncle(
+        &self,
+        block_hash: H256,
+        index: u64,
+    ) -> Result<Option<Block<TxHash>>, RpcError> {
+        self.call("eth_getUncleByBlockHashAndIndex", || {
+            self.provider
+                .get_uncle(BlockId::Hash(block_hash), index.into())
+        })
+        .await
+    }
+
+    pub async fn get_block_receipt(
+        &self,
+        block_number: u64,
+    ) -> Result<Vec<TransactionReceipt>, RpcError> {
+        self.call("eth_getBlockReceipts", || {
+            self.provider
+                .get_block_receipts(BlockNumber::Number(block_number.into()))
+        })
+        .await
     }
 
     pub async fn get_transaction_receipt(
         &self,
         transaction_hash: H256,
-    ) -> Option<TransactionReceipt> {
-        self.provider
-            .get_transaction_receipt(transaction_hash)
-            .await
-            .unwrap()
+    ) -> Result<Option<TransactionReceipt>, RpcError> {
+        self.call("eth_getTransactionReceipt", || {
+            self.provider.get_transaction_receipt(transaction_hash)
+        })
+        .await
     }
 
-    pub async fn get_transaction(&self, transaction_hash: H256) -> Transaction {
+    pub async fn get_transaction(&self, transaction_hash: H256) -> Result<Transaction, RpcError> {
         let transaction = self
-            .provider
-            .get_transaction(transaction_hash)
-            .await
-            .unwrap();
-        transaction.unwrap()
+            .call("eth_getTransactionByHash", || {
+                self.provider.get_transaction(transaction_hash)
+            })
+            .await?;
+        transaction
+            .ok_or_else(|| RpcError::NotFound(format!("transaction {:#x}", transaction_hash)))
     }
 
-    pub async fn code_at(&self, address: Address, block_number: U64) -> Bytes {
-        self.provider
-            .get_code(address, Some(BlockId::Number(block_number.into())))
-            .await
-            .unwrap()
+    pub async fn code_at(&self, address: Address, block_number: U64) -> Result<Bytes, RpcError> {
+        self.call("eth_getCode", || {
+            self.provider
+                .get_code(address, Some(BlockId::Number(block_number.into())))
+        })
+        .await
     }
 
-    pub async fn trace_transaction(&self, transaction_hash: H256) -> Vec<Trace> {
-        self.provider
-            .trace_transaction(transaction_hash)
-            .await
-            .unwrap()
+    pub async fn trace_transaction(&self, transaction_hash: H256) -> Result<Vec<Trace>, RpcError> {
+        self.call("trace_transaction", || {
+            self.provider.trace_transaction(transaction_hash)
+        })
+        .await
     }
 
-    pub async fn trace_block(&self, number: u64) -> Vec<Trace> {
-        self.provider
-            .trace_block(BlockNumber::Number(number.into()))
-            .await
-            .unwrap()
+    pub async fn trace_block(&self, number: u64) -> Result<Vec<Trace>, RpcError> {
+        self.call("trace_block", || {
+            self.provider
+                .trace_block(BlockNumber::Number(number.into()))
+        })
+        .await
     }
 
-    pub async fn trace_replay_block_transactions(&self, number: u64) -> Vec<BlockTrace> {
-        self.provider
-            .trace_replay_block_transactions(
+    pub async fn trace_replay_block_transactions(
+        &self,
+        number: u64,
+    ) -> Result<Vec<BlockTrace>, RpcError> {
+        self.call("trace_replayBlockTransactions", || {
+            self.provider.trace_replay_block_transactions(
                 BlockNumber::Number(number.into()),
                 vec![TraceType::Trace],
             )
-            .await
-            .unwrap()
+        })
+        .await
     }
 
     pub async fn batch_get_tx_logs(
         &self,
         block_info: Block<Transaction>,
-    ) -> (Option<U256>, Vec<MyLog>) {
+    ) -> Result<(Option<U256>, Vec<MyLog>), RpcError> {
         let mut logs: Vec<MyLog> = Vec::new();
         if let Some(block_number) = block_info.number {
-            let receipts = self.get_block_receipt(block_number.as_u64()).await;
+            let receipts = self.get_block_receipt(block_number.as_u64()).await?;
             for receipt in receipts {
                 let my_log = MyLog {
                     block_hash: block_info.hash.unwrap(),
                     block_number: block_info.number.unwrap(),
                     block_timestamp: block_info.timestamp,
                     transaction_index: receipt.transaction_index,
-                    to: receipt.to.unwrap(),
-                    log_details: Vec::new(),
+                    to: receipt.to.unwrap_or_default(),
+                    log_details: receipt.logs.iter().map(LogDetail::from).collect(),
                 };
-
-                for l in receipt.logs.iter() {
-                    let mut log_details: Vec<LogDetail> = Vec::new();
-                    let detail = LogDetail {
-                        address: l.address,
-                        topics: l.topics.clone(),
-                        data: l.data.clone(),
-                        transaction_log_index: l.transaction_log_index,
-                        log_index: l.log_index,
-                        log_type: l.log_type.clone(),
-                        removed: l.removed,
-                    };
-                    log_details.push(detail);
-                }
                 logs.push(my_log);
             }
         }
 
-        (Some(block_info.timestamp), logs)
+        Ok((Some(block_info.timestamp), logs))
+    }
+
+    pub async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, RpcError> {
+        self.call("eth_getLogs", || self.provider.get_logs(filter))
+            .await
     }
 
-    pub async fn get_balance(&self, from: H160, block_number: Option<u64>) -> U256 {
-        if let Some(number) = block_number {
-            let amount = self
-                .provider
-                .get_balance(from, Some(BlockId::Number(number.into())))
-                .await
-                .unwrap();
-            return amount;
+    pub async fn get_balance(
+        &self,
+        from: H160,
+        block_number: Option<u64>,
+    ) -> Result<U256, RpcError> {
+        let block = block_number.map(|number| BlockId::Number(number.into()));
+        self.call("eth_getBalance", || self.provider.get_balance(from, block))
+            .await
+    }
+}
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const BASE_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// run `f` until it succeeds, retrying transient failures and timeouts with exponential backoff.
+pub async fn retry<T, F, Fut>(
+    method: &str,
+    retries: u32,
+    timeout: Duration,
+    f: F,
+) -> Result<T, RpcError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, ProviderError>>,
+{
+    let mut attempt = 0;
+    loop {
+        let err = match tokio::time::timeout(timeout, f()).await {
+            Ok(Ok(res)) => return Ok(res),
+            Ok(Err(err)) if !is_transient(&err) => {
+                return Err(RpcError::Provider {
+                    method: method.to_string(),
+                    err,
+                })
+            }
+            Ok(Err(err)) => RpcError::Provider {
+                method: method.to_string(),
+                err,
+            },
+            Err(_) => RpcError::Timeout {
+                method: method.to_string(),
+                timeout,
+            },
+        };
+
+        if attempt >= retries {
+            return Err(err);
         }
+        tracing::warn!(message = "rpc call failed, retrying", method, attempt, err = ?err);
+        tokio::time::sleep(backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// delay before the next attempt, doubled on every retry.
+pub fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// transport failures and rate limits are worth retrying, error responses of the node are not.
+fn is_transient(err: &ProviderError) -> bool {
+    match err {
+        ProviderError::HTTPError(_) => true,
+        ProviderError::JsonRpcClientError(_) => match err.as_error_response() {
+            // -32005 limit exceeded, 429 too many requests
+            Some(resp) => resp.code == -32005 || resp.code == 429,
+            None => !err.is_serde_error(),
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use super::{backoff, retry, BASE_BACKOFF, MAX_BACKOFF};
+    use crate::common::err::RpcError;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), BASE_BACKOFF);
+        assert_eq!(backoff(1), BASE_BACKOFF * 2);
+        assert_eq!(backoff(3), BASE_BACKOFF * 8);
+        assert_eq!(backoff(30), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_retry_timeout() {
+        let attempts = AtomicU32::new(0);
+        let res: Result<u64, RpcError> = retry("test", 1, Duration::from_millis(10), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(1)
+        })
+        .await;
+
+        assert!(matches!(res, Err(RpcError::Timeout { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_not_transient() {
+        let attempts = AtomicU32::new(0);
+        let res: Result<u64, RpcError> = retry("test", 3, Duration::from_secs(1), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(ethers::providers::ProviderError::CustomError(
+                "bad request".to_string(),
+            ))
+        })
+        .await;
 
-        self.provider.get_balance(from, None).await.unwrap()
+        assert!(matches!(res, Err(RpcError::Provider { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
     }
 }
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::providers::{Ipc, Middleware, Provider, PubsubClient, Ws};
+use futures::StreamExt;
+use tokio::sync::Notify;
+
+use super::eth::backoff;
+use crate::common::err::RpcError;
+
+/// Head notifications of a `newHeads` subscription over WebSocket or IPC. The subscription
+/// is reconnected with backoff when it drops, `is_live` tells the block task whether it
+/// has to fall back to polling meanwhile.
+#[derive(Debug, Default)]
+pub struct NewHeads {
+    notify: Notify,
+    live: AtomicBool,
+}
+
+impl NewHeads {
+    /// `url` is a `ws://`/`wss://` url or the path of an ipc socket.
+    pub fn subscribe(url: String) -> Arc<NewHeads> {
+        let heads = Arc::new(NewHeads::default());
+        let this = heads.clone();
+        tokio::task::spawn(async move {
+            let mut attempt = 0;
+            loop {
+                let res = if is_ws(&url) {
+                    match Provider::<Ws>::connect(url.as_str()).await {
+                        Ok(provider) => this.forward(provider, &mut attempt).await,
+                        Err(err) => Err(RpcError::Provider {
+                            method: "connect".to_string(),
+                            err,
+                        }),
+                    }
+                } else {
+                    match Provider::<Ipc>::connect_ipc(url.as_str()).await {
+                        Ok(provider) => this.forward(provider, &mut attempt).await,
+                        Err(err) => Err(RpcError::Provider {
+                            method: "connect".to_string(),
+                            err,
+                        }),
+                    }
+                };
+
+                this.live.store(false, Ordering::Relaxed);
+                // wake the block task up so it does not miss heads while reconnecting
+                this.notify.notify_one();
+                tracing::warn!(message = "newHeads subscription dropped, reconnecting", url, attempt, err = ?res.err());
+                tokio::time::sleep(backoff(attempt)).await;
+                attempt = attempt.saturating_add(1);
+            }
+        });
+
+        heads
+    }
+
+    async fn forward<P>(&self, provider: Provider<P>, attempt: &mut u32) -> Result<(), RpcError>
+    where
+        P: PubsubClient,
+    {
+        let mut stream = provider
+            .subscribe_blocks()
+            .await
+            .map_err(|err| RpcError::Provider {
+                method: "eth_subscribe".to_string(),
+                err,
+            })?;
+        self.live.store(true, Ordering::Relaxed);
+        *attempt = 0;
+        tracing::info!("newHeads subscription established");
+
+        while let Some(head) = stream.next().await {
+            tracing::debug!("new head {:?}", head.number);
+            self.notify.notify_one();
+        }
+
+        Ok(())
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live.load(Ordering::Relaxed)
+    }
+
+    /// wait for the next head, at most `timeout`.
+    pub async fn wait(&self, timeout: Duration) {
+        let _ = tokio::time::timeout(timeout, self.notify.notified()).await;
+    }
+}
+
+fn is_ws(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{is_ws, NewHeads};
+
+    #[test]
+    fn test_is_ws() {
+        assert!(is_ws("wss://mainnet.example.org"));
+        assert!(!is_ws("/var/run/geth.ipc"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_notified() {
+        let heads = NewHeads::default();
+        heads.notify.notify_one();
+        // a stored permit returns at once
+        tokio::time::timeout(
+            Duration::from_millis(100),
+            heads.wait(Duration::from_secs(10)),
+        )
+        .await
+        .unwrap();
+    }
+}
@@ -1 +1,5 @@
+pub mod batch;
 pub mod eth;
+pub mod heads;
+pub mod pool;
+pub mod tracer;
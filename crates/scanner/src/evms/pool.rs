@@ -0,0 +1,356 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ethers::providers::{
+    Http, JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError as EthersRpcError,
+};
+use ethers::types::U64;
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::common::err::RpcError;
+
+pub type PoolProvider = Provider<RpcPool>;
+
+// reads of the state at a block any synced endpoint answers alike
+const ROUND_ROBIN_METHODS: [&str; 3] = ["eth_getBalance", "eth_call", "eth_getCode"];
+
+/// A set of rpc endpoints shared by all clients. Balance and contract reads are distributed
+/// round-robin over the healthy endpoints, chain data is read from the healthy endpoint with the
+/// best head so a block, its receipts and traces come from one node. Calls fail over to the next
+/// endpoint on transport errors.
+#[derive(Debug)]
+pub struct RpcPool<C = Http> {
+    inner: Arc<PoolInner<C>>,
+}
+
+impl<C> Clone for RpcPool<C> {
+    fn clone(&self) -> Self {
+        RpcPool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolInner<C> {
+    endpoints: Vec<Endpoint<C>>,
+    next: AtomicUsize,
+    // blocks an endpoint may lag behind the best one before it is taken as unhealthy
+    max_lag: u64,
+}
+
+#[derive(Debug)]
+struct Endpoint<C> {
+    url: String,
+    client: C,
+    healthy: AtomicBool,
+    head: AtomicU64,
+}
+
+#[derive(Error, Debug)]
+pub enum PoolError {
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+
+    #[error("Serialize params: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("no rpc endpoint configured")]
+    NoEndpoint,
+}
+
+impl EthersRpcError for PoolError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            PoolError::Provider(err) => err.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            PoolError::Provider(err) => err.as_serde_error(),
+            PoolError::Serde(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<PoolError> for ProviderError {
+    fn from(err: PoolError) -> Self {
+        match err {
+            PoolError::Provider(err) => err,
+            err => ProviderError::JsonRpcClientError(Box::new(err)),
+        }
+    }
+}
+
+impl RpcPool<Http> {
+    pub fn new(urls: &[String], max_lag: u64) -> Result<RpcPool<Http>, RpcError> {
+        let mut clients = vec![];
+        for url in urls.iter() {
+            let client =
+                Http::from_str(url).map_err(|err| RpcError::InvalidUrl(err.to_string()))?;
+            clients.push((url.clone(), client));
+        }
+
+        Ok(Self::with_clients(clients, max_lag))
+    }
+}
+
+impl<C> RpcPool<C>
+where
+    C: JsonRpcClient,
+{
+    pub fn with_clients(clients: Vec<(String, C)>, max_lag: u64) -> RpcPool<C> {
+        let endpoints = clients
+            .into_iter()
+            .map(|(url, client)| Endpoint {
+                url,
+                client,
+                healthy: AtomicBool::new(true),
+                head: AtomicU64::new(0),
+            })
+            .collect();
+
+        RpcPool {
+            inner: Arc::new(PoolInner {
+                endpoints,
+                next: AtomicUsize::new(0),
+                max_lag,
+            }),
+        }
+    }
+
+    pub fn provider(&self) -> Provider<RpcPool<C>> {
+        Provider::new(self.clone())
+    }
+
+    /// urls of the healthy endpoints
+    pub fn healthy_urls(&self) -> Vec<String> {
+        self.inner
+            .endpoints
+            .iter()
+            .filter(|ep| ep.healthy.load(Ordering::Relaxed))
+            .map(|ep| ep.url.clone())
+            .collect()
+    }
+
+    /// urls in the order chain data is read from them
+    pub fn urls(&self) -> Vec<String> {
+        self.best_head_order()
+            .into_iter()
+            .map(|i| self.inner.endpoints[i].url.clone())
+            .collect()
+    }
+
+    /// endpoint indexes in the order a call of `method` tries them, the unhealthy ones are the
+    /// last resort.
+    fn order(&self, method: &str) -> Vec<usize> {
+        if ROUND_ROBIN_METHODS.contains(&method) {
+            self.round_robin_order()
+        } else {
+            self.best_head_order()
+        }
+    }
+
+    /// healthy endpoints rotated round-robin first
+    fn round_robin_order(&self) -> Vec<usize> {
+        let len = self.inner.endpoints.len();
+        if len == 0 {
+            return vec![];
+        }
+
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed) % len;
+        let rotated = (0..len).map(|i| (start + i) % len);
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) =
+            rotated.partition(|i| self.inner.endpoints[*i].healthy.load(Ordering::Relaxed));
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// healthy endpoints by their head of the last health check, the best first
+    fn best_head_order(&self) -> Vec<usize> {
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..self.inner.endpoints.len())
+            .partition(|i| self.inner.endpoints[*i].healthy.load(Ordering::Relaxed));
+        healthy.sort_by_key(|i| {
+            std::cmp::Reverse(self.inner.endpoints[*i].head.load(Ordering::Relaxed))
+        });
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// ask every endpoint for its head, endpoints which fail or lag more than `max_lag` blocks
+    /// behind the best head are skipped until they recover.
+    pub async fn health_check(&self) {
+        let heads = futures::future::join_all(self.inner.endpoints.iter().map(|ep| async move {
+            ep.client
+                .request::<_, U64>("eth_blockNumber", ())
+                .await
+                .map(|head| head.as_u64())
+                .ok()
+        }))
+        .await;
+
+        let best = heads.iter().flatten().max().copied().unwrap_or_default();
+        for (ep, head) in self.inner.endpoints.iter().zip(heads) {
+            let healthy = match head {
+                Some(head) => {
+                    ep.head.store(head, Ordering::Relaxed);
+                    best - head <= self.inner.max_lag
+                }
+                None => false,
+            };
+            if ep.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                tracing::warn!(
+                    "rpc endpoint {} healthy: {}, head: {:?}, best head: {}",
+                    ep.url,
+                    healthy,
+                    head,
+                    best
+                );
+            }
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<C> JsonRpcClient for RpcPool<C>
+where
+    C: JsonRpcClient,
+{
+    type Error = PoolError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        let mut last_err = None;
+        for idx in self.order(method) {
+            let ep = &self.inner.endpoints[idx];
+            match ep.client.request::<_, R>(method, &params).await {
+                // only the health check takes an endpoint back, it may answer and still lag
+                Ok(res) => return Ok(res),
+                // the node answered, another node would answer the same unless it is rate limited
+                Err(err)
+                    if err
+                        .as_error_response()
+                        .is_some_and(|resp| resp.code != -32005 && resp.code != 429) =>
+                {
+                    return Err(PoolError::Provider(err.into()));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        message = "rpc endpoint failed, fail over",
+                        url = ep.url,
+                        method,
+                        err = ?err
+                    );
+                    ep.healthy.store(false, Ordering::Relaxed);
+                    last_err = Some(PoolError::Provider(err.into()));
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or(PoolError::NoEndpoint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::{JsonRpcClient, MockProvider};
+    use ethers::types::U64;
+
+    use super::RpcPool;
+
+    fn pool(count: usize) -> (RpcPool<MockProvider>, Vec<MockProvider>) {
+        let mocks = (0..count).map(|_| MockProvider::new()).collect::<Vec<_>>();
+        let clients = mocks
+            .iter()
+            .enumerate()
+            .map(|(i, mock)| (format!("mock-{}", i), mock.clone()))
+            .collect();
+        (RpcPool::with_clients(clients, 2), mocks)
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let (pool, mocks) = pool(2);
+        mocks[0].push(U64::from(1)).unwrap();
+        mocks[1].push(U64::from(2)).unwrap();
+
+        let first: U64 = pool.request("eth_getBalance", ()).await.unwrap();
+        let second: U64 = pool.request("eth_getBalance", ()).await.unwrap();
+        assert_eq!(first, U64::from(1));
+        assert_eq!(second, U64::from(2));
+    }
+
+    #[tokio::test]
+    async fn test_best_head_first() {
+        let (pool, mocks) = pool(3);
+        mocks[0].push(U64::from(99)).unwrap();
+        mocks[1].push(U64::from(100)).unwrap();
+        mocks[2].push(U64::from(90)).unwrap();
+        pool.health_check().await;
+
+        mocks[1].push(U64::from(100)).unwrap();
+        mocks[1].push(U64::from(100)).unwrap();
+        let first: U64 = pool.request("eth_blockNumber", ()).await.unwrap();
+        let second: U64 = pool.request("eth_getBlockByNumber", ()).await.unwrap();
+        assert_eq!((first, second), (U64::from(100), U64::from(100)));
+        assert_eq!(
+            pool.urls(),
+            vec![
+                "mock-1".to_string(),
+                "mock-0".to_string(),
+                "mock-2".to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_answer_keeps_lagging_endpoint_out() {
+        let (pool, mocks) = pool(2);
+        mocks[0].push(U64::from(100)).unwrap();
+        mocks[1].push(U64::from(90)).unwrap();
+        pool.health_check().await;
+
+        // the lagging endpoint is tried last and answers once the best one fails
+        mocks[1].push(U64::from(90)).unwrap();
+        let res: U64 = pool.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(res, U64::from(90));
+        assert!(pool.healthy_urls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let (pool, mocks) = pool(2);
+        // the first endpoint has no response queued and fails
+        mocks[1].push(U64::from(2)).unwrap();
+
+        let res: U64 = pool.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(res, U64::from(2));
+        assert_eq!(pool.healthy_urls(), vec!["mock-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_lag() {
+        let (pool, mocks) = pool(3);
+        mocks[0].push(U64::from(100)).unwrap();
+        mocks[1].push(U64::from(97)).unwrap();
+        mocks[2].push(U64::from(99)).unwrap();
+
+        pool.health_check().await;
+        assert_eq!(
+            pool.healthy_urls(),
+            vec!["mock-0".to_string(), "mock-2".to_string()]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use config::chain::Tracer;
+use ethers::types::{
+    Action, ActionType, Block, Call, CallFrame, CallResult, CallType, Create, CreateResult, Res,
+    Suicide, Trace, Transaction, U256, U64,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::common::err::RpcError;
+
+/// result of one transaction in a `debug_traceBlockByNumber` response
+#[derive(Debug, Deserialize)]
+struct TxTrace {
+    result: Option<CallFrame>,
+    error: Option<String>,
+}
+
+/// rpc method of `tracer`, none if tracing is disabled.
+pub fn trace_method(tracer: &Tracer) -> Option<&'static str> {
+    match tracer {
+        Tracer::TraceBlock => Some("trace_block"),
+        Tracer::CallTracer => Some("debug_traceBlockByNumber"),
+        Tracer::None => None,
+    }
+}
+
+/// the rpc call tracing the block with `tracer`, none if tracing is disabled.
+pub fn trace_request(tracer: &Tracer, number: U64) -> Option<(&'static str, Value)> {
+    let method = trace_method(tracer)?;
+    match tracer {
+        Tracer::CallTracer => Some((method, json!([number, {"tracer": "callTracer"}]))),
+        _ => Some((method, json!([number]))),
+    }
+}
+
+/// decode the response of `trace_request` into parity style traces.
+pub fn parse_traces(
+    tracer: &Tracer,
+    block: &Block<Transaction>,
+    value: Value,
+) -> Result<Vec<Trace>, RpcError> {
+    match tracer {
+        Tracer::TraceBlock => serde_json::from_value(value)
+            .map_err(|err| RpcError::Transport(format!("decode trace_block response: {}", err))),
+        Tracer::CallTracer => {
+            let tx_traces: Vec<TxTrace> = serde_json::from_value(value).map_err(|err| {
+                RpcError::Transport(format!("decode debug_traceBlockByNumber response: {}", err))
+            })?;
+            call_frames_to_traces(block, tx_traces)
+        }
+        Tracer::None => Ok(vec![]),
+    }
+}
+
+/// flatten the call frames of every transaction depth first, the same order `trace_block`
+/// returns the traces in.
+fn call_frames_to_traces(
+    block: &Block<Transaction>,
+    tx_traces: Vec<TxTrace>,
+) -> Result<Vec<Trace>, RpcError> {
+    if tx_traces.len() != block.transactions.len() {
+        return Err(RpcError::Transport(format!(
+            "debug_traceBlockByNumber returned {} traces for {} transactions",
+            tx_traces.len(),
+            block.transactions.len()
+        )));
+    }
+
+    let mut traces = vec![];
+    for (tx, tx_trace) in block.transactions.iter().zip(tx_traces) {
+        let Some(frame) = tx_trace.result else {
+            return Err(RpcError::Transport(format!(
+                "trace transaction {:#x}: {}",
+                tx.hash,
+                tx_trace.error.unwrap_or_default()
+            )));
+        };
+        flatten_frame(block, tx, frame, vec![], &mut traces);
+    }
+
+    Ok(traces)
+}
+
+fn flatten_frame(
+    block: &Block<Transaction>,
+    tx: &Transaction,
+    frame: CallFrame,
+    trace_address: Vec<usize>,
+    traces: &mut Vec<Trace>,
+) {
+    let calls = frame.calls.clone().unwrap_or_default();
+    let to = frame
+        .to
+        .as_ref()
+        .and_then(|to| to.as_address())
+        .copied()
+        .unwrap_or_default();
+    let value = frame.value.unwrap_or(U256::zero());
+    let output = frame.output.clone().unwrap_or_default();
+
+    let (action_type, action, result) = match frame.typ.to_uppercase().as_str() {
+        "CREATE" | "CREATE2" => (
+            ActionType::Create,
+            Action::Create(Create {
+                from: frame.from,
+                value,
+                gas: frame.gas,
+                init: frame.input.clone(),
+            }),
+            Res::Create(CreateResult {
+                gas_used: frame.gas_used,
+                code: output,
+                address: to,
+            }),
+        ),
+        "SELFDESTRUCT" => (
+            ActionType::Suicide,
+            Action::Suicide(Suicide {
+                address: frame.from,
+                refund_address: to,
+                balance: value,
+            }),
+            Res::None,
+        ),
+        typ => (
+            ActionType::Call,
+            Action::Call(Call {
+                from: frame.from,
+                to,
+                value,
+                gas: frame.gas,
+                input: frame.input.clone(),
+                call_type: match typ {
+                    "CALLCODE" => CallType::CallCode,
+                    "DELEGATECALL" => CallType::DelegateCall,
+                    "STATICCALL" => CallType::StaticCall,
+                    _ => CallType::Call,
+                },
+            }),
+            Res::Call(CallResult {
+                gas_used: frame.gas_used,
+                output,
+            }),
+        ),
+    };
+
+    traces.push(Trace {
+        action,
+        result: match (&frame.error, &action_type) {
+            (Some(_), _) | (None, ActionType::Suicide) => None,
+            _ => Some(result),
+        },
+        trace_address: trace_address.clone(),
+        subtraces: calls.len(),
+        transaction_position: tx.transaction_index.map(|idx| idx.as_usize()),
+        transaction_hash: Some(tx.hash),
+        block_number: block.number.unwrap_or_default().as_u64(),
+        block_hash: block.hash.unwrap_or_default(),
+        action_type,
+        error: frame.error,
+    });
+
+    for (idx, call) in calls.into_iter().enumerate() {
+        let mut address = trace_address.clone();
+        address.push(idx);
+        flatten_frame(block, tx, call, address, traces);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::chain::Tracer;
+    use ethers::types::{Action, ActionType, Block, CallType, Transaction, H256, U64};
+    use serde_json::json;
+
+    use super::{parse_traces, trace_request};
+
+    #[test]
+    fn test_trace_request() {
+        assert!(trace_request(&Tracer::None, U64::from(1)).is_none());
+        let (method, params) = trace_request(&Tracer::CallTracer, U64::from(16)).unwrap();
+        assert_eq!(method, "debug_traceBlockByNumber");
+        assert_eq!(params, json!(["0x10", {"tracer": "callTracer"}]));
+    }
+
+    #[test]
+    fn test_parse_call_tracer() {
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(1),
+            transaction_index: Some(U64::from(0)),
+            ..Default::default()
+        };
+        let block = Block {
+            number: Some(U64::from(16)),
+            hash: Some(H256::from_low_u64_be(2)),
+            transactions: vec![tx],
+            ..Default::default()
+        };
+        let value = json!([{
+            "result": {
+                "type": "CALL",
+                "from": "0x0000000000000000000000000000000000000001",
+                "to": "0x0000000000000000000000000000000000000002",
+                "value": "0x1",
+                "gas": "0x5208",
+                "gasUsed": "0x5208",
+                "input": "0x",
+                "calls": [
+                    {
+                        "type": "DELEGATECALL",
+                        "from": "0x0000000000000000000000000000000000000002",
+                        "to": "0x0000000000000000000000000000000000000003",
+                        "gas": "0x100",
+                        "gasUsed": "0x10",
+                        "input": "0x",
+                        "error": "execution reverted"
+                    },
+                    {
+                        "type": "CREATE2",
+                        "from": "0x0000000000000000000000000000000000000002",
+                        "to": "0x0000000000000000000000000000000000000004",
+                        "value": "0x0",
+                        "gas": "0x100",
+                        "gasUsed": "0x10",
+                        "input": "0x00",
+                        "output": "0x01"
+                    }
+                ]
+            }
+        }]);
+
+        let traces = parse_traces(&Tracer::CallTracer, &block, value).unwrap();
+        assert_eq!(traces.len(), 3);
+        assert_eq!(traces[0].subtraces, 2);
+        assert!(traces[0].trace_address.is_empty());
+        assert_eq!(traces[1].trace_address, vec![0]);
+        assert!(traces[1].result.is_none());
+        assert!(
+            matches!(&traces[1].action, Action::Call(call) if call.call_type == CallType::DelegateCall)
+        );
+        assert_eq!(traces[2].trace_address, vec![1]);
+        assert_eq!(traces[2].action_type, ActionType::Create);
+        assert_eq!(traces[2].transaction_hash, Some(H256::from_low_u64_be(1)));
+        assert_eq!(traces[2].block_number, 16);
+    }
+}
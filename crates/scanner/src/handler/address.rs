@@ -1,8 +1,28 @@
 use std::collections::HashMap;
 
+use anyhow::bail;
+use bigdecimal::ToPrimitive;
 use chrono::Utc;
-use entities::addresses::Model as AddressModel;
+use entities::{
+    addresses::Model as AddressModel, token_transfers::Model as TokenTransferModel,
+    transactions::Model as TransactionModel,
+};
 use ethers::types::{ActionType, Block, Res, Trace, Transaction, TransactionReceipt, H256};
+use repo::dal::address::{Mutation as AddressMutation, Query as AddressQuery};
+use sea_orm::DbConn;
+use tokio_util::sync::CancellationToken;
+
+use crate::common::err::ScannerError;
+
+const RECOUNT_ADDRESS_CHUNK: usize = 500;
+
+/// counters one block adds to an address
+#[derive(Debug, Default, PartialEq)]
+pub struct AddressCounter {
+    pub transactions_count: i32,
+    pub token_transfers_count: i32,
+    pub gas_used: i64,
+}
 
 pub fn process_block_addresses(
     block: &Block<Transaction>,
@@ -41,7 +61,7 @@ pub fn process_addresses(
         decompiled: None,
         verified: None,
         gas_used: None,
-        transactions_count: Some(tx.nonce.as_u64() as i32),
+        transactions_count: None,
         token_transfers_count: None,
     };
 
@@ -121,3 +141,200 @@ fn get_contract_code_from_trace(traces: Option<&Vec<(Trace, i32)>>) -> Option<St
 
     None
 }
+
+/// participants of token transfers which are not among `known` addresses yet
+pub fn process_token_transfer_addresses(
+    known: &[AddressModel],
+    token_transfers: &[TokenTransferModel],
+) -> Vec<AddressModel> {
+    let mut addresses: HashMap<Vec<u8>, AddressModel> = HashMap::new();
+    for transfer in token_transfers.iter() {
+        for hash in [&transfer.from_address_hash, &transfer.to_address_hash] {
+            if known.iter().any(|address| &address.hash == hash) {
+                continue;
+            }
+            addresses
+                .entry(hash.clone())
+                .or_insert_with(|| AddressModel {
+                    fetched_coin_balance: None,
+                    fetched_coin_balance_block_number: None,
+                    hash: hash.clone(),
+                    contract_code: None,
+                    inserted_at: Utc::now().naive_utc(),
+                    updated_at: Utc::now().naive_utc(),
+                    nonce: None,
+                    decompiled: None,
+                    verified: None,
+                    gas_used: None,
+                    transactions_count: None,
+                    token_transfers_count: None,
+                });
+        }
+    }
+
+    addresses.into_values().collect()
+}
+
+/// transactions are counted once for the sender and once for the receiver or created contract,
+/// token transfers likewise, gas used is charged to the sender.
+pub fn count_block_addresses(
+    transactions: &[TransactionModel],
+    token_transfers: &[TokenTransferModel],
+) -> HashMap<Vec<u8>, AddressCounter> {
+    let mut counters: HashMap<Vec<u8>, AddressCounter> = HashMap::new();
+    for tx in transactions.iter() {
+        let to = tx
+            .to_address_hash
+            .as_ref()
+            .or(tx.created_contract_address_hash.as_ref());
+        counters
+            .entry(tx.from_address_hash.clone())
+            .or_default()
+            .transactions_count += 1;
+        if let Some(to) = to.filter(|to| **to != tx.from_address_hash) {
+            counters.entry(to.clone()).or_default().transactions_count += 1;
+        }
+        if let Some(gas_used) = tx.gas_used {
+            match gas_used.to_i64() {
+                Some(gas_used) => {
+                    let counter = counters.entry(tx.from_address_hash.clone()).or_default();
+                    counter.gas_used = counter.gas_used.saturating_add(gas_used);
+                }
+                None => tracing::warn!(
+                    "gas used {} of transaction 0x{} out of range",
+                    gas_used,
+                    hex::encode(&tx.hash)
+                ),
+            }
+        }
+    }
+
+    for transfer in token_transfers.iter() {
+        counters
+            .entry(transfer.from_address_hash.clone())
+            .or_default()
+            .token_transfers_count += 1;
+        if transfer.to_address_hash != transfer.from_address_hash {
+            counters
+                .entry(transfer.to_address_hash.clone())
+                .or_default()
+                .token_transfers_count += 1;
+        }
+    }
+
+    counters
+}
+
+/// rebuild the counters of every address touched in [from, to] in chunks of `chunk_size`
+/// blocks, returns the number of addresses recounted.
+pub async fn recount_addresses(
+    conn: &DbConn,
+    from: i64,
+    to: i64,
+    chunk_size: u64,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<usize> {
+    let mut recounted = 0;
+    let mut start = from;
+    while start <= to && !shutdown.is_cancelled() {
+        let end = to.min(start + chunk_size.max(1) as i64 - 1);
+        let hashes = AddressQuery::find_touched(conn, start, end)
+            .await
+            .map_err(ScannerError::Query)?
+            .into_iter()
+            .map(|address| address.hash)
+            .collect::<Vec<_>>();
+
+        for chunk in hashes.chunks(RECOUNT_ADDRESS_CHUNK) {
+            if let Err(e) = AddressMutation::recount(conn, chunk.to_vec()).await {
+                bail!(ScannerError::Update {
+                    src: "recount address counters".to_string(),
+                    err: e
+                });
+            }
+        }
+        recounted += hashes.len();
+        tracing::info!(
+            "recounted {} addresses from {} to {}",
+            hashes.len(),
+            start,
+            end
+        );
+        start = end + 1;
+    }
+
+    Ok(recounted)
+}
+
+#[cfg(test)]
+pub(crate) fn address(hash: &[u8]) -> AddressModel {
+    AddressModel {
+        fetched_coin_balance: None,
+        fetched_coin_balance_block_number: None,
+        hash: hash.to_vec(),
+        contract_code: None,
+        inserted_at: Utc::now().naive_utc(),
+        updated_at: Utc::now().naive_utc(),
+        nonce: None,
+        decompiled: None,
+        verified: None,
+        gas_used: None,
+        transactions_count: None,
+        token_transfers_count: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::transactions::Model as TransactionModel;
+    use sea_orm::prelude::Decimal;
+
+    use super::{count_block_addresses, AddressCounter};
+    use crate::handler::token::{token_transfer, transaction};
+
+    #[test]
+    fn test_count_block_addresses() {
+        let transactions = [
+            TransactionModel {
+                to_address_hash: Some(vec![2]),
+                // a scaled decimal counts its value, not its mantissa
+                gas_used: Some(Decimal::new(210000, 1)),
+                ..transaction(&[], &[1])
+            },
+            TransactionModel {
+                created_contract_address_hash: Some(vec![3]),
+                gas_used: Some(Decimal::from(50000)),
+                ..transaction(&[], &[1])
+            },
+            TransactionModel {
+                to_address_hash: Some(vec![2]),
+                gas_used: Some(Decimal::from(100)),
+                ..transaction(&[], &[2])
+            },
+        ];
+        let transfers = [
+            token_transfer(&[], &[2], &[4]),
+            token_transfer(&[], &[4], &[4]),
+        ];
+
+        let counters = count_block_addresses(&transactions, &transfers);
+        assert_eq!(
+            counters[&vec![1]],
+            AddressCounter {
+                transactions_count: 2,
+                token_transfers_count: 0,
+                gas_used: 71000,
+            }
+        );
+        assert_eq!(
+            counters[&vec![2]],
+            AddressCounter {
+                transactions_count: 2,
+                token_transfers_count: 1,
+                gas_used: 100,
+            }
+        );
+        assert_eq!(counters[&vec![3]].transactions_count, 1);
+        assert_eq!(counters[&vec![4]].token_transfers_count, 2);
+    }
+}
@@ -1,13 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use anyhow::bail;
+use bigdecimal::{BigDecimal, Zero};
 use chrono::Utc;
 use entities::address_current_token_balances::Model as CurrentTokenBalanceModel;
 use entities::address_token_balances::Model as AddressTokenBalanceModel;
 use entities::token_transfers::Model as TokenTransferModel;
 use entities::tokens::Model as TokenModel;
 use ethers::types::H160;
+use repo::dal::{
+    current_token_balance::Mutation as CurrentTokenMutation, token::Mutation as TokenMutation,
+    token_balance::Mutation as TokenBalanceMutation,
+};
+use sea_orm::{DbConn, TransactionTrait};
 
-use common::{chain_ident, consts};
+use crate::common::err::ScannerError;
+use common::consts;
 
 pub fn process_token_balances(
     token_map: &HashMap<Vec<u8>, TokenModel>,
@@ -19,17 +27,34 @@ pub fn process_token_balances(
     (address_token_balance, current_token_balance)
 }
 
+/// one balance row per (address, token, token id) touched in the block, batch transfers
+/// of ERC-1155 tokens expand into a row for every id. the values are fetched later.
 pub fn process_address_token_balances(
     token_map: &HashMap<Vec<u8>, TokenModel>,
     token_transfers: &[TokenTransferModel],
 ) -> Vec<AddressTokenBalanceModel> {
     let mut resp = vec![];
+    let mut seen: HashSet<(BalanceKey, i64)> = HashSet::new();
+    let mut push = |model: AddressTokenBalanceModel| {
+        if model.address_hash == H160::zero().as_bytes() {
+            return;
+        }
+        let key = (
+            model.address_hash.clone(),
+            model.token_contract_address_hash.clone(),
+            model.token_id.clone(),
+        );
+        if seen.insert((key, model.block_number)) {
+            resp.push(model);
+        }
+    };
+
     for token in token_transfers.iter() {
         let token_type = &token_map
             .get(&token.token_contract_address_hash)
             .unwrap()
             .r#type;
-        let mut from_model = AddressTokenBalanceModel {
+        let from_model = AddressTokenBalanceModel {
             address_hash: token.from_address_hash.clone(),
             block_number: token.block_number.unwrap_or(0),
             token_contract_address_hash: token.token_contract_address_hash.clone(),
@@ -44,32 +69,22 @@ pub fn process_address_token_balances(
 
         let mut to_model = from_model.clone();
         to_model.address_hash = token.to_address_hash.clone();
-        if let Some(token_ids) = &token.token_ids {
-            for token_id in token_ids {
-                let mut from_model_tmp = from_model.clone();
-                from_model_tmp.token_id = Some(token_id.clone());
-                resp.push(from_model_tmp);
-
-                let mut to_model_tmp = to_model.clone();
-                to_model_tmp.token_id = Some(token_id.clone());
-                resp.push(to_model_tmp);
-            }
-            continue;
-        }
-
-        if let Some(token_id) = token.token_id.as_ref() {
-            from_model.token_id = Some(token_id.clone());
-            to_model.token_id = Some(token_id.clone());
-        }
 
-        if chain_ident!(&from_model.address_hash) != chain_ident!(H160::zero().as_bytes()) {
-            resp.push(from_model);
-        }
+        let token_ids = match (&token.token_ids, &token.token_id) {
+            (Some(token_ids), _) => token_ids.iter().cloned().map(Some).collect::<Vec<_>>(),
+            (None, token_id) => vec![token_id.clone()],
+        };
+        let burned = is_erc721_burn(token_type, token.to_address_hash.clone());
+        for token_id in token_ids.into_iter() {
+            let mut from_model = from_model.clone();
+            from_model.token_id = token_id.clone();
+            push(from_model);
 
-        if !is_erc721_burn(token_type, token.to_address_hash.clone())
-            && chain_ident!(&to_model.address_hash) != chain_ident!(H160::zero().as_bytes())
-        {
-            resp.push(to_model);
+            if !burned {
+                let mut to_model = to_model.clone();
+                to_model.token_id = token_id;
+                push(to_model);
+            }
         }
     }
 
@@ -85,11 +100,29 @@ fn is_erc721_burn(token_type: &str, to_address: Vec<u8>) -> bool {
     false
 }
 
+// (address, token contract, token id)
+type BalanceKey = (Vec<u8>, Vec<u8>, Option<BigDecimal>);
+
+/// one current balance per (address, token, token id) touched by the transfers, batch
+/// transfers of ERC-1155 tokens expand into a balance for every id. the values are fetched
+/// later.
 pub fn process_current_token_balances(
     token_map: &HashMap<Vec<u8>, TokenModel>,
     token_transfers: &[TokenTransferModel],
 ) -> Vec<CurrentTokenBalanceModel> {
     let mut resp = vec![];
+    let mut seen: HashSet<BalanceKey> = HashSet::new();
+    let mut push = |model: CurrentTokenBalanceModel| {
+        let key = (
+            model.address_hash.clone(),
+            model.token_contract_address_hash.clone(),
+            model.token_id.clone(),
+        );
+        if seen.insert(key) {
+            resp.push(model);
+        }
+    };
+
     for token in token_transfers.iter() {
         let token_type = &token_map
             .get(&token.token_contract_address_hash)
@@ -100,7 +133,7 @@ pub fn process_current_token_balances(
             continue;
         }
 
-        let mut from_model = CurrentTokenBalanceModel {
+        let from_model = CurrentTokenBalanceModel {
             address_hash: token.from_address_hash.clone(),
             block_number: token.block_number.unwrap_or(0),
             token_contract_address_hash: token.token_contract_address_hash.clone(),
@@ -117,37 +150,223 @@ pub fn process_current_token_balances(
         let mut to_model = from_model.clone();
         to_model.address_hash = token.to_address_hash.clone();
 
-        if let Some(token_ids) = &token.token_ids {
-            for token_id in token_ids {
-                let mut from_model_tmp = from_model.clone();
-                from_model_tmp.token_id = Some(token_id.clone());
-                resp.push(from_model_tmp);
+        let token_ids = match (&token.token_ids, &token.token_id) {
+            (Some(token_ids), _) => token_ids.iter().cloned().map(Some).collect::<Vec<_>>(),
+            (None, token_id) => vec![token_id.clone()],
+        };
+        for token_id in token_ids.into_iter() {
+            let mut from_model = from_model.clone();
+            from_model.token_id = token_id.clone();
+            let mut to_model = to_model.clone();
+            to_model.token_id = token_id;
 
-                let mut to_model_tmp = to_model.clone();
-                to_model_tmp.token_id = Some(token_id.clone());
-                resp.push(to_model_tmp);
+            // the zero address is the source of mints and the sink of burns, not a holder
+            if token_type == consts::ERC1155 {
+                if from_model.address_hash != H160::zero().as_bytes() {
+                    push(from_model);
+                }
+                if to_model.address_hash != H160::zero().as_bytes() {
+                    push(to_model);
+                }
+                continue;
+            }
+
+            push(from_model);
+            push(to_model);
+        }
+    }
+
+    resp
+}
+
+/// +1 when the address starts holding the token, -1 when its balance drops to zero.
+pub fn holder_delta(
+    address: &[u8],
+    old_value: Option<&BigDecimal>,
+    value: Option<&BigDecimal>,
+) -> i32 {
+    if address == H160::zero().as_bytes() {
+        return 0;
+    }
+
+    let holds = |value: Option<&BigDecimal>| value.is_some_and(|v| v > &BigDecimal::zero());
+    match (holds(old_value), holds(value)) {
+        (false, true) => 1,
+        (true, false) => -1,
+        _ => 0,
+    }
+}
+
+/// save the fetched balances and move the current balances along, the holder count of every
+/// token is adjusted by the balances crossing zero. an address holding several ids of an
+/// ERC-1155 token is counted per id here, `token_holder_count_task` recomputes the exact count.
+pub async fn save_fetched_token_balances(
+    conn: &DbConn,
+    balances: &[AddressTokenBalanceModel],
+) -> anyhow::Result<()> {
+    let txn = conn.begin().await?;
+    let mut deltas: HashMap<Vec<u8>, i32> = HashMap::new();
+    for balance in balances.iter() {
+        match TokenBalanceMutation::update_balance(&txn, balance).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "update address token balance".to_string(),
+                    err: e
+                });
             }
-            continue;
         }
 
-        if let Some(token_id) = token.token_id.as_ref() {
-            from_model.token_id = Some(token_id.clone());
-            to_model.token_id = Some(token_id.clone());
+        let change = match CurrentTokenMutation::update_value(
+            &txn,
+            balance.address_hash.clone(),
+            balance.token_contract_address_hash.clone(),
+            balance.token_id.clone(),
+            balance.block_number,
+            balance.value.clone(),
+        )
+        .await
+        {
+            Ok(change) => change,
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "update address current token balance".to_string(),
+                    err: e
+                });
+            }
+        };
+
+        if let Some(change) = change {
+            *deltas
+                .entry(balance.token_contract_address_hash.clone())
+                .or_default() += holder_delta(
+                &balance.address_hash,
+                change.old_value.as_ref(),
+                change.value.as_ref(),
+            );
         }
+    }
 
-        resp.push(from_model);
-        resp.push(to_model);
+    for (token, delta) in deltas.into_iter().filter(|(_, delta)| *delta != 0) {
+        match TokenMutation::increment_holder_count(&txn, token, delta).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "update token holder count".to_string(),
+                    err: e
+                });
+            }
+        }
     }
 
-    resp
+    txn.commit().await?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use bigdecimal::BigDecimal;
     use common::consts;
+    use entities::token_transfers::Model as TokenTransferModel;
+    use ethers::types::H160;
     use hex::FromHex;
 
-    use crate::handler::address_token_balance::is_erc721_burn;
+    use crate::handler::address_token_balance::{
+        holder_delta, is_erc721_burn, process_address_token_balances,
+        process_current_token_balances,
+    };
+    use crate::handler::token::{token, token_transfer};
+
+    #[test]
+    fn test_process_erc1155_batch_address_token_balances() {
+        let contract = H160::from_low_u64_be(1).as_bytes().to_vec();
+        let alice = H160::from_low_u64_be(10).as_bytes().to_vec();
+        let token = token(&contract, consts::ERC1155);
+        let mint = TokenTransferModel {
+            block_number: Some(100),
+            amounts: Some(vec![BigDecimal::from(5), BigDecimal::from(6)]),
+            token_ids: Some(vec![BigDecimal::from(1), BigDecimal::from(2)]),
+            ..token_transfer(&contract, H160::zero().as_bytes(), &alice)
+        };
+        let mut single = mint.clone();
+        single.log_index = 1;
+        single.token_id = Some(BigDecimal::from(2));
+        single.token_ids = None;
+        single.amounts = None;
+
+        let token_map = HashMap::from([(contract.clone(), token)]);
+        let balances = process_address_token_balances(&token_map, &[mint, single]);
+        // one row per id of the receiver, the mint source and the repeated id are left out
+        assert_eq!(balances.len(), 2);
+        assert!(balances.iter().all(|b| b.address_hash == alice));
+        assert_eq!(
+            balances
+                .iter()
+                .map(|b| b.token_id.clone())
+                .collect::<Vec<_>>(),
+            vec![Some(BigDecimal::from(1)), Some(BigDecimal::from(2))]
+        );
+    }
+
+    #[test]
+    fn test_process_erc1155_current_token_balances() {
+        let contract = H160::from_low_u64_be(1).as_bytes().to_vec();
+        let alice = H160::from_low_u64_be(10).as_bytes().to_vec();
+        let bob = H160::from_low_u64_be(11).as_bytes().to_vec();
+        let token = token(&contract, consts::ERC1155);
+        let transfer = |from: &[u8], to: &[u8], token_id: i32, amount: i32| TokenTransferModel {
+            amount: Some(BigDecimal::from(amount)),
+            token_id: Some(BigDecimal::from(token_id)),
+            block_number: Some(100),
+            ..token_transfer(&contract, from, to)
+        };
+        let token_map = HashMap::from([(contract.clone(), token)]);
+        let transfers = vec![
+            transfer(H160::zero().as_bytes(), &alice, 1, 10),
+            transfer(&alice, &bob, 1, 4),
+            transfer(&alice, &bob, 2, 1),
+        ];
+
+        let balances = process_current_token_balances(&token_map, &transfers);
+        // one balance per holder and id, the mint source is not tracked and the values are
+        // left to the fetcher
+        assert_eq!(
+            balances
+                .iter()
+                .map(|b| (b.address_hash.clone(), b.token_id.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (alice.clone(), Some(BigDecimal::from(1))),
+                (bob.clone(), Some(BigDecimal::from(1))),
+                (alice, Some(BigDecimal::from(2))),
+                (bob, Some(BigDecimal::from(2))),
+            ]
+        );
+        assert!(balances.iter().all(|b| b.value.is_none()));
+    }
+
+    #[test]
+    fn test_holder_delta() {
+        let address = H160::from_low_u64_be(1);
+        let zero = BigDecimal::from(0);
+        let ten = BigDecimal::from(10);
+
+        assert_eq!(holder_delta(address.as_bytes(), None, Some(&ten)), 1);
+        assert_eq!(holder_delta(address.as_bytes(), Some(&zero), Some(&ten)), 1);
+        assert_eq!(
+            holder_delta(address.as_bytes(), Some(&ten), Some(&zero)),
+            -1
+        );
+        assert_eq!(holder_delta(address.as_bytes(), Some(&ten), Some(&ten)), 0);
+        assert_eq!(holder_delta(address.as_bytes(), None, Some(&zero)), 0);
+        assert_eq!(holder_delta(H160::zero().as_bytes(), None, Some(&ten)), 0);
+    }
 
     #[test]
     fn test_is_erc721_burn() {
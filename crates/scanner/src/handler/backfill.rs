@@ -0,0 +1,168 @@
+use anyhow::bail;
+use entities::missing_block_ranges::Model as MissingBlockRangeModel;
+use repo::dal::{
+    block::Query as BlockQuery,
+    missing_block_range::{Mutation as MissingRangeMutation, Query as MissingRangeQuery},
+};
+use sea_orm::DbConn;
+use tokio_util::sync::CancellationToken;
+
+use super::block::sync_block;
+use crate::common::err::ScannerError;
+use crate::evms::eth::EthCli;
+
+/// find the holes of indexed blocks in [from, to] which are not tracked yet and record
+/// them in `missing_block_ranges` split into chunks, returns the number of chunks enqueued.
+pub async fn enqueue_missing(
+    conn: &DbConn,
+    from: i64,
+    to: i64,
+    chunk_size: u64,
+) -> anyhow::Result<usize> {
+    if from > to {
+        return Ok(0);
+    }
+
+    let gaps = BlockQuery::find_gaps(conn, from, to)
+        .await
+        .map_err(ScannerError::Query)?;
+    if gaps.is_empty() {
+        return Ok(0);
+    }
+
+    let tracked = MissingRangeQuery::find_all(conn)
+        .await
+        .map_err(ScannerError::Query)?
+        .iter()
+        .filter_map(range_bounds)
+        .collect::<Vec<_>>();
+
+    let mut chunks = vec![];
+    for gap in gaps.iter() {
+        for (from, to) in subtract_ranges(gap.from_number, gap.to_number, &tracked) {
+            chunks.extend(chunk_range(from, to, chunk_size));
+        }
+    }
+
+    if !chunks.is_empty() {
+        if let Err(e) = MissingRangeMutation::create(conn, &chunks).await {
+            bail!(ScannerError::Create {
+                src: "create missing block ranges".to_string(),
+                err: e
+            });
+        }
+    }
+
+    Ok(chunks.len())
+}
+
+/// index every block of the range from the newest to the oldest, the range is removed once
+/// all of its blocks are indexed so an interrupted range is resumed after restart. the
+/// watchlist notifications of historical blocks are delivered only if `deliver_watchlist`.
+pub async fn backfill_range(
+    cli: &EthCli,
+    conn: &DbConn,
+    range: &MissingBlockRangeModel,
+    deliver_watchlist: bool,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<()> {
+    let Some((from, to)) = range_bounds(range) else {
+        bail!("invalid missing block range {}", range.id);
+    };
+
+    for number in (from..=to).rev() {
+        if shutdown.is_cancelled() {
+            return Ok(());
+        }
+        if BlockQuery::find_by_height(conn, number)
+            .await
+            .map_err(ScannerError::Query)?
+            .is_some()
+        {
+            continue;
+        }
+
+        if !sync_block(cli, conn, number as u64, deliver_watchlist).await? {
+            bail!("block {} not found on chain", number);
+        }
+    }
+
+    if let Err(e) = MissingRangeMutation::delete(conn, range.id).await {
+        bail!(ScannerError::Delete {
+            src: "delete missing block range".to_string(),
+            err: e
+        });
+    }
+    tracing::info!("backfill blocks from {} to {} finished", from, to);
+
+    Ok(())
+}
+
+fn range_bounds(range: &MissingBlockRangeModel) -> Option<(i64, i64)> {
+    match (range.from_number, range.to_number) {
+        (Some(from), Some(to)) if from <= to => Some((from as i64, to as i64)),
+        _ => None,
+    }
+}
+
+/// split the inclusive range [from, to] into chunks of at most `size` blocks, newest first.
+pub fn chunk_range(from: i64, to: i64, size: u64) -> Vec<(i64, i64)> {
+    let size = size.max(1) as i64;
+    let mut chunks = vec![];
+    let mut end = to;
+    while end >= from {
+        let start = (end - size + 1).max(from);
+        chunks.push((start, end));
+        end = start - 1;
+    }
+
+    chunks
+}
+
+/// parts of the inclusive range [from, to] not covered by any of `covered`.
+pub fn subtract_ranges(from: i64, to: i64, covered: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let mut covered = covered.to_vec();
+    covered.sort();
+
+    let mut rest = vec![];
+    let mut start = from;
+    for (c_from, c_to) in covered.into_iter() {
+        if c_to < start || c_from > to {
+            continue;
+        }
+        if c_from > start {
+            rest.push((start, c_from - 1));
+        }
+        start = start.max(c_to + 1);
+        if start > to {
+            return rest;
+        }
+    }
+    if start <= to {
+        rest.push((start, to));
+    }
+
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_range, subtract_ranges};
+
+    #[test]
+    fn test_chunk_range() {
+        assert_eq!(chunk_range(1, 10, 4), vec![(7, 10), (3, 6), (1, 2)]);
+        assert_eq!(chunk_range(5, 5, 100), vec![(5, 5)]);
+        assert!(chunk_range(6, 5, 100).is_empty());
+    }
+
+    #[test]
+    fn test_subtract_ranges() {
+        assert_eq!(subtract_ranges(1, 10, &[]), vec![(1, 10)]);
+        assert_eq!(
+            subtract_ranges(1, 10, &[(8, 12), (3, 4)]),
+            vec![(1, 2), (5, 7)]
+        );
+        assert!(subtract_ranges(3, 4, &[(1, 10)]).is_empty());
+    }
+}
@@ -1,216 +1,263 @@
-fn fetch_beneficiaries(
-    blocks: Vec<Block>,
-    all_transactions: Vec<Transaction>,
-    json_rpc_named_arguments: JsonRpcNamedArguments,
-) -> FetchedBeneficiaries {
-    match Application::get_env("indexer", "fetch_rewards_way") {
-        Some("manual") => fetch_beneficiaries_manual(blocks, all_transactions),
-        _ => fetch_beneficiaries_by_trace_block(blocks, json_rpc_named_arguments),
+use std::collections::HashMap;
+
+use chrono::Utc;
+use entities::block_rewards::Model as BlockRewardModel;
+use ethers::types::{
+    Action, Block, RewardType, Trace, Transaction, TransactionReceipt, H160, H256, U256,
+};
+use repo::dal::emission_reward::Query as EmissionRewardQuery;
+use sea_orm::{prelude::Decimal, ConnectionTrait};
+
+use crate::common::err::ScannerError;
+
+const VALIDATOR: &str = "validator";
+const UNCLE: &str = "uncle";
+const EMISSION_FUNDS: &str = "emission_funds";
+
+/// parts the reward of the block miner is made of
+#[derive(Debug, Default, PartialEq)]
+pub struct RewardParts {
+    pub miner_hash: H160,
+    pub block_hash: H256,
+    pub block_number: u64,
+    pub static_reward: U256,
+    pub txn_fees: U256,
+    pub burned_fees: U256,
+    pub uncle_reward: U256,
+}
+
+/// rewards of the block read from the reward traces of `trace_block`, computed from
+/// `emission_rewards` and the fees of the block for nodes without reward traces.
+pub async fn fetch_beneficiaries<C>(
+    conn: &C,
+    block: &Block<Transaction>,
+    traces: &[Trace],
+    receipts: &[TransactionReceipt],
+) -> anyhow::Result<Vec<BlockRewardModel>>
+where
+    C: ConnectionTrait,
+{
+    let beneficiaries = fetch_beneficiaries_by_trace_block(block, traces, receipts);
+    if !beneficiaries.is_empty() {
+        return Ok(beneficiaries);
     }
+
+    let block_number = block.number.unwrap_or_default().as_u64();
+    let static_reward = EmissionRewardQuery::find_by_block_number(conn, block_number as i64)
+        .await
+        .map_err(ScannerError::Query)?
+        .and_then(|emission| emission.reward)
+        .map(decimal_to_u256)
+        .unwrap_or_default();
+
+    let reward_parts = block_reward_by_parts(block, receipts, static_reward);
+    Ok(reward_parts_to_beneficiaries(reward_parts))
 }
 
-fn fetch_beneficiaries_manual(
-    blocks: Vec<Block>,
-    all_transactions: Vec<Transaction>,
-) -> FetchedBeneficiaries {
-    let block_transactions_map = all_transactions
-        .into_iter()
-        .group_by(|transaction| transaction.block_number);
+/// the gas payments of the block go to the miner on top of its trace reward
+fn fetch_beneficiaries_by_trace_block(
+    block: &Block<Transaction>,
+    traces: &[Trace],
+    receipts: &[TransactionReceipt],
+) -> Vec<BlockRewardModel> {
+    let block_hash = block.hash.unwrap_or_default();
+    let mut rewards: HashMap<(H160, &str), U256> = HashMap::new();
+    for trace in traces.iter() {
+        let Action::Reward(reward) = &trace.action else {
+            continue;
+        };
+        // a reorg happened between getting the block and tracing it
+        if trace.block_hash != block_hash {
+            tracing::debug!(
+                "reward trace of block {} maps to block hash {:#x} instead of {:#x}",
+                trace.block_number,
+                trace.block_hash,
+                block_hash
+            );
+            continue;
+        }
+
+        let address_type = match reward.reward_type {
+            RewardType::Uncle => UNCLE,
+            RewardType::EmptyStep => EMISSION_FUNDS,
+            RewardType::Block | RewardType::External => VALIDATOR,
+        };
+        *rewards.entry((reward.author, address_type)).or_default() += reward.value;
+    }
+
+    if rewards.is_empty() {
+        return vec![];
+    }
 
-    blocks
+    let gas_payment = txn_fees(block, receipts).saturating_sub(burned_fees(block, receipts));
+    *rewards
+        .entry((block.author.unwrap_or_default(), VALIDATOR))
+        .or_default() += gas_payment;
+
+    rewards
         .into_iter()
-        .map(|block| {
-            fetch_beneficiaries_manual(
-                block,
-                block_transactions_map
-                    .get(&block.number)
-                    .cloned()
-                    .unwrap_or_else(Vec::new),
-            )
-        })
-        .fold(FetchedBeneficiaries::default(), |acc, params_set| {
-            FetchedBeneficiaries {
-                params_set: acc.params_set.union(&params_set).cloned().collect(),
-                ..acc
-            }
+        .map(|((address, address_type), reward)| {
+            beneficiary(address, address_type, block_hash, reward)
         })
+        .collect()
 }
 
-fn fetch_beneficiaries_manual(
-    block: Block,
-    transactions: Vec<Transaction>,
-) -> FetchedBeneficiaries {
-    let reward_parts = Chain::block_reward_by_parts(block, transactions);
-    reward_parts_to_beneficiaries(reward_parts)
+pub fn block_reward_by_parts(
+    block: &Block<Transaction>,
+    receipts: &[TransactionReceipt],
+    static_reward: U256,
+) -> RewardParts {
+    RewardParts {
+        miner_hash: block.author.unwrap_or_default(),
+        block_hash: block.hash.unwrap_or_default(),
+        block_number: block.number.unwrap_or_default().as_u64(),
+        static_reward,
+        txn_fees: txn_fees(block, receipts),
+        burned_fees: burned_fees(block, receipts),
+        // the miner gets 1/32 of the static reward for every uncle included
+        uncle_reward: static_reward / 32 * block.uncles.len(),
+    }
 }
 
-fn reward_parts_to_beneficiaries(reward_parts: RewardParts) -> FetchedBeneficiaries {
-    let reward = reward_parts
-        .static_reward
-        .sum(reward_parts.txn_fees)
-        .sub(reward_parts.burned_fees)
-        .sum(reward_parts.uncle_reward);
-
-    let beneficiary = Beneficiary {
-        address_hash: reward_parts.miner_hash,
-        block_hash: reward_parts.block_hash,
-        block_number: reward_parts.block_number,
+fn reward_parts_to_beneficiaries(reward_parts: RewardParts) -> Vec<BlockRewardModel> {
+    let reward = (reward_parts.static_reward + reward_parts.txn_fees)
+        .saturating_sub(reward_parts.burned_fees)
+        + reward_parts.uncle_reward;
+
+    vec![beneficiary(
+        reward_parts.miner_hash,
+        VALIDATOR,
+        reward_parts.block_hash,
         reward,
-        address_type: AddressType::Validator,
-    };
+    )]
+}
 
-    FetchedBeneficiaries {
-        params_set: vec![beneficiary].into_iter().collect(),
-        ..FetchedBeneficiaries::default()
+fn beneficiary(
+    address: H160,
+    address_type: &str,
+    block_hash: H256,
+    reward: U256,
+) -> BlockRewardModel {
+    BlockRewardModel {
+        address_hash: address.as_bytes().to_vec(),
+        address_type: address_type.to_string(),
+        block_hash: block_hash.as_bytes().to_vec(),
+        reward: Some(Decimal::from_i128_with_scale(reward.as_u128() as i128, 0)),
+        inserted_at: Utc::now().naive_utc(),
+        updated_at: Utc::now().naive_utc(),
     }
 }
 
-fn fetch_beneficiaries_by_trace_block(
-    blocks: Vec<Block>,
-    json_rpc_named_arguments: JsonRpcNamedArguments,
-) -> FetchedBeneficiaries {
-    let hash_string_by_number: HashMap<u64, String> = blocks
-        .into_iter()
-        .filter_map(|block| {
-            if let (Some(number), Some(hash_string)) = (block.number, block.hash) {
-                Some((number, hash_string))
-            } else {
-                None
-            }
-        })
-        .collect();
+/// gas used times the price paid by every transaction of the block
+fn txn_fees(block: &Block<Transaction>, receipts: &[TransactionReceipt]) -> U256 {
+    let gas_prices = block
+        .transactions
+        .iter()
+        .map(|tx| (tx.hash, tx.gas_price))
+        .collect::<HashMap<_, _>>();
 
-    let block_numbers: Vec<u64> = hash_string_by_number.keys().cloned().collect();
+    receipts.iter().fold(U256::zero(), |acc, receipt| {
+        let gas_price = receipt
+            .effective_gas_price
+            .or_else(|| gas_prices.get(&receipt.transaction_hash).copied().flatten())
+            .unwrap_or_default();
+        acc + receipt.gas_used.unwrap_or_default() * gas_price
+    })
+}
 
-    match EthereumJSONRPC::fetch_beneficiaries(json_rpc_named_arguments, block_numbers) {
-        Ok(fetched_beneficiaries) => {
-            let consensus_params_set =
-                consensus_params_set(fetched_beneficiaries.params_set, &hash_string_by_number);
+/// the base fee of EIP-1559 blocks is burned instead of paid to the miner
+fn burned_fees(block: &Block<Transaction>, receipts: &[TransactionReceipt]) -> U256 {
+    let Some(base_fee_per_gas) = block.base_fee_per_gas else {
+        return U256::zero();
+    };
 
-            FetchedBeneficiaries {
-                params_set: consensus_params_set,
-                ..fetched_beneficiaries
-            }
-        }
-        Err(reason) => {
-            log_error(format!("Could not fetch beneficiaries: {:?}", reason));
-
-            let error = match reason {
-                Some(Error { code, message }) => Error { code, message },
-                _ => Error {
-                    code: -1,
-                    message: format!("{:?}", reason),
-                },
-            };
-
-            let errors: Vec<Error> = hash_string_by_number
-                .into_iter()
-                .filter_map(|(number, _)| {
-                    if let Some(block_number) = number {
-                        Some(Error {
-                            data: Some(Data { block_number }),
-                            ..error.clone()
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-
-            FetchedBeneficiaries {
-                errors,
-                ..FetchedBeneficiaries::default()
-            }
-        }
-    }
+    let gas_used = receipts.iter().fold(U256::zero(), |acc, receipt| {
+        acc + receipt.gas_used.unwrap_or_default()
+    });
+    gas_used * base_fee_per_gas
 }
 
-fn consensus_params_set(
-    params_set: HashSet<Beneficiary>,
-    hash_string_by_number: &HashMap<u64, String>,
-) -> HashSet<Beneficiary> {
-    params_set
-        .into_iter()
-        .filter(|beneficiary| {
-            if let Some(block_hash_string) = hash_string_by_number.get(&beneficiary.block_number) {
-                if block_hash_string == &beneficiary.block_hash {
-                    return true;
-                } else {
-                    log_debug(format!("fetch beneficiaries reported block number ({}) maps to different ({}) block hash than the one from getBlock ({}). A reorg has occurred.", beneficiary.block_number, block_hash_string, beneficiary.block_hash));
-                }
-            }
-            false
-        })
-        .collect()
+fn decimal_to_u256(value: Decimal) -> U256 {
+    U256::from_dec_str(&value.trunc().to_string()).unwrap_or_default()
 }
 
-fn block_reward_by_parts(block: Block, transactions: Vec<Transaction>) -> BlockReward {
-    let Block {
-        hash: block_hash,
-        number: block_number,
-        base_fee_per_gas,
-        uncles,
-    } = block;
-
-    let txn_fees = txn_fees(&transactions);
-
-    let static_reward = match EmissionReward::find_by_block_range(block_number) {
-        Some(emission_reward) => emission_reward.reward,
-        None => Wei {
-            value: Decimal::new(0),
-        },
+#[cfg(test)]
+mod tests {
+    use ethers::types::{
+        Action, ActionType, Block, Reward, RewardType, Trace, Transaction, TransactionReceipt,
+        H160, H256, U256, U64,
     };
+    use sea_orm::prelude::Decimal;
 
-    let has_uncles = uncles.is_some() && !uncles.unwrap().is_empty();
+    use super::{
+        block_reward_by_parts, fetch_beneficiaries_by_trace_block, reward_parts_to_beneficiaries,
+    };
 
-    let burned_fees = burned_fees(&transactions, base_fee_per_gas);
-    let uncle_reward = if has_uncles {
-        Wei::mult(static_reward, Decimal::from_float(1.0 / 32.0))
-    } else {
-        Wei {
-            value: Decimal::new(0),
+    fn block() -> Block<Transaction> {
+        Block {
+            hash: Some(H256::from_low_u64_be(1)),
+            number: Some(U64::from(100)),
+            author: Some(H160::from_low_u64_be(2)),
+            base_fee_per_gas: Some(U256::from(10)),
+            uncles: vec![H256::from_low_u64_be(3)],
+            ..Default::default()
         }
-    };
+    }
 
-    BlockReward {
-        block_number,
-        block_hash,
-        miner_hash: block.miner_hash,
-        static_reward,
-        txn_fees: Wei { value: txn_fees },
-        burned_fees: burned_fees.unwrap_or(Wei {
-            value: Decimal::new(0),
-        }),
-        uncle_reward: uncle_reward.unwrap_or(Wei {
-            value: Decimal::new(0),
-        }),
+    fn receipts() -> Vec<TransactionReceipt> {
+        vec![TransactionReceipt {
+            gas_used: Some(U256::from(21000)),
+            effective_gas_price: Some(U256::from(12)),
+            ..Default::default()
+        }]
     }
-}
 
-fn txn_fees(transactions: Vec<Transaction>) -> Decimal {
-    transactions
-        .iter()
-        .fold(Decimal::new(0), |acc, transaction| {
-            let gas_used = Decimal::new(transaction.gas_used);
-            let gas_price = Decimal::new(transaction.gas_price);
-            let fee = gas_used * gas_price;
-            acc + fee
-        })
-}
+    #[test]
+    fn test_block_reward_by_parts() {
+        let parts = block_reward_by_parts(&block(), &receipts(), U256::from(3200));
+        assert_eq!(parts.txn_fees, U256::from(21000 * 12));
+        assert_eq!(parts.burned_fees, U256::from(21000 * 10));
+        assert_eq!(parts.uncle_reward, U256::from(100));
 
-fn burned_fees(transactions: Vec<Transaction>, base_fee_per_gas: Option<Decimal>) -> Option<Wei> {
-    let burned_fee_counter = transactions
-        .iter()
-        .fold(Decimal::new(0), |acc, transaction| {
-            let gas_used = Decimal::new(transaction.gas_used);
-            Decimal::add(acc, gas_used)
-        });
-
-    match base_fee_per_gas {
-        Some(base_fee) => Some(Wei::mult(
-            base_fee_per_gas_to_wei(base_fee),
-            burned_fee_counter,
-        )),
-        None => None,
+        let rewards = reward_parts_to_beneficiaries(parts);
+        assert_eq!(rewards.len(), 1);
+        assert_eq!(rewards[0].address_type, "validator");
+        assert_eq!(rewards[0].reward, Some(Decimal::from(3200 + 42000 + 100)));
+    }
+
+    #[test]
+    fn test_beneficiaries_by_trace_block() {
+        let block = block();
+        let reward = |author: u64, value: u64, reward_type: RewardType| Trace {
+            action: Action::Reward(Reward {
+                author: H160::from_low_u64_be(author),
+                value: U256::from(value),
+                reward_type,
+            }),
+            result: None,
+            trace_address: vec![],
+            subtraces: 0,
+            transaction_position: None,
+            transaction_hash: None,
+            block_number: 100,
+            block_hash: block.hash.unwrap(),
+            action_type: ActionType::Reward,
+            error: None,
+        };
+        let traces = vec![
+            reward(2, 2000, RewardType::Block),
+            reward(4, 1500, RewardType::Uncle),
+        ];
+
+        let mut rewards = fetch_beneficiaries_by_trace_block(&block, &traces, &receipts());
+        rewards.sort_by(|a, b| a.address_type.cmp(&b.address_type));
+        assert_eq!(rewards.len(), 2);
+        assert_eq!(rewards[0].address_type, "uncle");
+        assert_eq!(rewards[0].reward, Some(Decimal::from(1500)));
+        assert_eq!(rewards[1].address_type, "validator");
+        assert_eq!(rewards[1].reward, Some(Decimal::from(2000 + 42000)));
+
+        // without reward traces the rewards are computed from parts
+        assert!(fetch_beneficiaries_by_trace_block(&block, &[], &receipts()).is_empty());
     }
 }
@@ -1,70 +1,114 @@
 use std::{collections::HashMap, sync::Arc};
 
 use anyhow::bail;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{DateTime, Utc};
 use entities::{
+    address_coin_balances::Model as CoinBalanceModel,
     address_current_token_balances::Model as CurrentTokenBalanceModel,
     address_token_balances::Model as AddressTokenBalanceModel, addresses::Model as AddressModel,
-    blocks::Model as BlockModel, internal_transactions::Model as InnerTransactionModel,
-    logs::Model as LogModel, token_transfers::Model as TokenTransferModel,
+    block_rewards::Model as BlockRewardModel,
+    block_second_degree_relations::Model as SecondDegreeRelationModel, blocks::Model as BlockModel,
+    internal_transactions::Model as InnerTransactionModel, logs::Model as LogModel,
+    token_instances::Model as TokenInstanceModel, token_transfers::Model as TokenTransferModel,
     tokens::Model as TokenModel, transactions::Model as TransactionModel,
     withdrawals::Model as WithdrawModel,
 };
 use ethers::types::{Block, Trace, Transaction, TransactionReceipt, TxHash};
 use repo::dal::{
     address::Mutation as AddressMutation,
+    address_coin_balance::Mutation as CoinBalanceMutation,
     block::{Mutation as BlockMutation, Query as BlockQuery},
+    block_event::Mutation as BlockEventMutation,
+    block_reward::Mutation as BlockRewardMutation,
+    block_second_degree_relation::Mutation as SecondDegreeRelationMutation,
     current_token_balance::Mutation as CurrentTokenMutation,
     event::Mutation as EventMutation,
     internal_transaction::Mutation as InnerTransactionMutation,
     token::Mutation as TokenMutation,
     token_balance::Mutation as TokenBalanceMutation,
+    token_instance::Mutation as TokenInstanceMutation,
     token_transfer::Mutation as TokenTransferMutation,
     transaction::Mutation as TransactionMutation,
     withdrawal::Mutation as WithdrawalMutation,
 };
 use sea_orm::{prelude::Decimal, DatabaseConnection, DbConn, TransactionTrait};
 
+use super::address::{
+    count_block_addresses, process_block_addresses, process_token_transfer_addresses,
+    AddressCounter,
+};
+use super::beneficiary::fetch_beneficiaries;
+use super::block_event::block_added_event;
+use super::coin_balance::handle_coin_balances;
 use super::internal_transaction::{classify_txs, handler_inner_transaction};
-use super::token::handle_token_from_receipts;
-use super::{address::process_block_addresses, withdrawal::withdrawals_process};
+use super::token::{handle_token_from_receipts, total_supply_changed_tokens};
+use super::token_instance::handle_token_instances;
+use super::uncle::handle_second_degree_relations;
+use super::watchlist::handle_watchlist_notifications;
+use super::withdrawal::withdrawals_process;
 use super::{event::handle_block_event, transaction::handle_transactions};
-use crate::common::err::ScannerError;
-use crate::evms::eth::EthCli;
+use crate::common::err::{RpcError, ScannerError};
+use crate::evms::{batch::BlockData, eth::EthCli};
 
 pub struct HandlerModels {
     block: BlockModel,
     datas: DataModels,
 }
 
+impl HandlerModels {
+    pub fn block_number(&self) -> i64 {
+        self.block.number
+    }
+
+    /// tokens minted or burned in the block
+    pub fn total_supply_changed_tokens(&self) -> Vec<Vec<u8>> {
+        total_supply_changed_tokens(&self.datas.token_transfers)
+    }
+}
+
 #[derive(Default)]
 pub struct DataModels {
     transactions: Vec<TransactionModel>,
     events: Vec<LogModel>,
     inner_tx: Vec<InnerTransactionModel>,
     addresses: Vec<AddressModel>,
+    address_counters: HashMap<Vec<u8>, AddressCounter>,
+    coin_balances: Vec<CoinBalanceModel>,
     tokens: Vec<TokenModel>,
     token_transfers: Vec<TokenTransferModel>,
+    token_instances: Vec<TokenInstanceModel>,
     withdraws: Vec<WithdrawModel>,
     address_token_balance: Vec<AddressTokenBalanceModel>,
     current_token_balance: Vec<CurrentTokenBalanceModel>,
+    block_rewards: Vec<BlockRewardModel>,
+    second_degree_relations: Vec<SecondDegreeRelationModel>,
 }
 
-pub async fn init_block(cli: Arc<EthCli>, conn: Arc<DatabaseConnection>) {
-    if let Some(block) = BlockQuery::select_latest(conn.as_ref()).await.unwrap() {
+pub async fn init_block(cli: Arc<EthCli>, conn: Arc<DatabaseConnection>) -> anyhow::Result<()> {
+    if let Some(block) = BlockQuery::select_latest(conn.as_ref())
+        .await
+        .map_err(ScannerError::Query)?
+    {
         if block.number != 0 {
-            return;
+            return Ok(());
         }
     }
 
-    let latest_block_number = cli.get_block_number().await;
-    let latest_block = cli.get_block(latest_block_number).await;
+    let latest_block_number = cli.get_block_number().await?;
+    let latest_block = cli.get_block(latest_block_number).await?;
     let block = convert_block_to_model(&latest_block);
 
-    BlockMutation::create(conn.as_ref(), &block).await.unwrap();
+    if let Err(e) = BlockMutation::create(conn.as_ref(), &block).await {
+        bail!(ScannerError::Create {
+            src: "create init block".to_string(),
+            err: e
+        });
+    }
+
+    Ok(())
 }
 
-fn convert_block_to_model(block: &Block<TxHash>) -> BlockModel {
+pub fn convert_block_to_model(block: &Block<TxHash>) -> BlockModel {
     let block = BlockModel {
         difficulty: Some(Decimal::from_i128_with_scale(
             block.difficulty.as_u128() as i128,
@@ -90,7 +134,9 @@ fn convert_block_to_model(block: &Block<TxHash>) -> BlockModel {
         },
         parent_hash: block.parent_hash.as_bytes().to_vec(),
         size: block.size.map(|size| size.as_u32() as i32),
-        timestamp: NaiveDateTime::from_timestamp_opt(block.timestamp.as_u64() as i64, 0).unwrap(),
+        timestamp: DateTime::from_timestamp(block.timestamp.as_u64() as i64, 0)
+            .unwrap()
+            .naive_utc(),
         base_fee_per_gas: block.base_fee_per_gas.map(|base_fee_per_gas| {
             Decimal::from_i128_with_scale(base_fee_per_gas.as_u128() as i128, 0)
         }),
@@ -107,12 +153,46 @@ fn convert_block_to_model(block: &Block<TxHash>) -> BlockModel {
     block
 }
 
+/// fetch the block together with its traces and receipts, returns none if the node does not
+/// have the block yet.
+pub async fn fetch_block(cli: &EthCli, number: u64) -> Result<Option<BlockData>, RpcError> {
+    Ok(fetch_blocks(cli, &[number]).await?.pop().flatten())
+}
+
+/// fetch several blocks with their traces and receipts in a single batch request, in the
+/// order of `numbers`.
+pub async fn fetch_blocks(
+    cli: &EthCli,
+    numbers: &[u64],
+) -> Result<Vec<Option<BlockData>>, RpcError> {
+    cli.get_blocks(numbers).await
+}
+
+/// fetch the block and index it, returns false if the node does not have the block yet.
+pub async fn sync_block(
+    cli: &EthCli,
+    conn: &DbConn,
+    number: u64,
+    deliver_watchlist: bool,
+) -> anyhow::Result<bool> {
+    let Some((block, block_traces, recipts)) = fetch_block(cli, number).await? else {
+        return Ok(false);
+    };
+
+    let handle_models = handle_block(conn, &block, &block_traces, &recipts).await?;
+    sync_to_db(conn, handle_models, deliver_watchlist).await?;
+
+    Ok(true)
+}
+
 pub async fn handle_block(
+    conn: &DbConn,
     block: &Block<Transaction>,
     traces: &[Trace],
     recipts: &[TransactionReceipt],
 ) -> anyhow::Result<HandlerModels> {
-    let data_model = parse_block(block, traces, recipts).await?;
+    let mut data_model = parse_block(block, traces, recipts).await?;
+    data_model.block_rewards = fetch_beneficiaries(conn, block, traces, recipts).await?;
     let block_header = handle_block_header(block)?;
     Ok(HandlerModels {
         block: block_header,
@@ -146,7 +226,9 @@ pub fn handle_block_header(block: &Block<Transaction>) -> anyhow::Result<BlockMo
         },
         parent_hash: block.parent_hash.as_bytes().to_vec(),
         size: block.size.map(|size| size.as_u32() as i32),
-        timestamp: NaiveDateTime::from_timestamp_opt(block.timestamp.as_u64() as i64, 0).unwrap(),
+        timestamp: DateTime::from_timestamp(block.timestamp.as_u64() as i64, 0)
+            .unwrap()
+            .naive_utc(),
         base_fee_per_gas: block.base_fee_per_gas.map(|base_fee_per_gas| {
             Decimal::from_i128_with_scale(base_fee_per_gas.as_u128() as i128, 0)
         }),
@@ -182,18 +264,36 @@ pub async fn parse_block(
     data_models.transactions = handle_transactions(block, &recipet_map, &trace_map)?;
     data_models.events = handle_block_event(recipts);
     data_models.inner_tx = handler_inner_transaction(traces);
-    data_models.addresses = process_block_addresses(block, &recipet_map, &trace_map);
     (
         data_models.tokens,
         data_models.token_transfers,
         data_models.address_token_balance,
         data_models.current_token_balance,
     ) = handle_token_from_receipts(recipts);
+    data_models.token_instances =
+        handle_token_instances(&data_models.tokens, &data_models.token_transfers);
+    data_models.addresses = process_block_addresses(block, &recipet_map, &trace_map);
+    let transfer_addresses =
+        process_token_transfer_addresses(&data_models.addresses, &data_models.token_transfers);
+    data_models.addresses.extend(transfer_addresses);
+    data_models.address_counters =
+        count_block_addresses(&data_models.transactions, &data_models.token_transfers);
+    data_models.coin_balances = handle_coin_balances(
+        block.number.unwrap_or_default().as_u64() as i64,
+        &data_models.addresses,
+    );
+    data_models.second_degree_relations = handle_second_degree_relations(block);
 
     Ok(data_models)
 }
 
-pub async fn sync_to_db(conn: &DbConn, handle_models: HandlerModels) -> anyhow::Result<()> {
+/// store the models of the block in one transaction, the watchlist notifications are scheduled
+/// for the webhooks only if `deliver_watchlist`.
+pub async fn sync_to_db(
+    conn: &DbConn,
+    handle_models: HandlerModels,
+    deliver_watchlist: bool,
+) -> anyhow::Result<()> {
     let txn = conn.begin().await?;
 
     match BlockMutation::create(&txn, &handle_models.block).await {
@@ -258,6 +358,40 @@ pub async fn sync_to_db(conn: &DbConn, handle_models: HandlerModels) -> anyhow::
         }
     }
 
+    for (hash, counter) in handle_models.datas.address_counters.into_iter() {
+        match AddressMutation::increment_counters(
+            &txn,
+            hash,
+            counter.transactions_count,
+            counter.token_transfers_count,
+            counter.gas_used,
+        )
+        .await
+        {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "increment address counters".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    if !handle_models.datas.coin_balances.is_empty() {
+        match CoinBalanceMutation::save(&txn, &handle_models.datas.coin_balances).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Upsert {
+                    src: "save coin balances".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
     if !handle_models.datas.tokens.is_empty() {
         match TokenMutation::save(&txn, &handle_models.datas.tokens).await {
             Ok(_) => {}
@@ -323,6 +457,84 @@ pub async fn sync_to_db(conn: &DbConn, handle_models: HandlerModels) -> anyhow::
         }
     }
 
+    if !handle_models.datas.block_rewards.is_empty() {
+        match BlockRewardMutation::save(&txn, &handle_models.datas.block_rewards).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Upsert {
+                    src: "save block rewards".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    if !handle_models.datas.token_instances.is_empty() {
+        match TokenInstanceMutation::save(&txn, &handle_models.datas.token_instances).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Create {
+                    src: "create token instances".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    if !handle_models.datas.second_degree_relations.is_empty() {
+        match SecondDegreeRelationMutation::save(&txn, &handle_models.datas.second_degree_relations)
+            .await
+        {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Upsert {
+                    src: "save block second degree relations".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    match handle_watchlist_notifications(
+        &txn,
+        handle_models.block.number,
+        &handle_models.block.hash,
+        &handle_models.datas.transactions,
+        &handle_models.datas.inner_tx,
+        &handle_models.datas.token_transfers,
+        deliver_watchlist,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Create {
+                src: "create watchlist notifications".to_string(),
+                err: e
+            });
+        }
+    }
+
+    let block_added = block_added_event(
+        &handle_models.block,
+        &handle_models.datas.transactions,
+        &handle_models.datas.token_transfers,
+    );
+    match BlockEventMutation::append(&txn, &[block_added]).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Create {
+                src: "create block added event".to_string(),
+                err: e
+            });
+        }
+    }
+
     txn.commit().await?;
 
     Ok(())
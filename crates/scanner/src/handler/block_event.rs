@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use common::chain_ident;
+use entities::block_events::Model as BlockEventModel;
+use entities::blocks::Model as BlockModel;
+use entities::token_transfers::Model as TokenTransferModel;
+use entities::transactions::Model as TransactionModel;
+use serde_json::{json, Value};
+
+pub const BLOCK_ADDED: &str = "block_added";
+pub const BLOCK_REMOVED: &str = "block_removed";
+
+/// "block_added" of an indexed block, it carries the transactions and token transfers so
+/// consumers need no further lookups.
+pub fn block_added_event(
+    block: &BlockModel,
+    transactions: &[TransactionModel],
+    token_transfers: &[TokenTransferModel],
+) -> BlockEventModel {
+    let transactions = transactions
+        .iter()
+        .map(|tx| {
+            json!({
+                "hash": chain_ident!(&tx.hash),
+                "index": tx.index,
+                "from": chain_ident!(&tx.from_address_hash),
+                "to": tx.to_address_hash.as_ref().map(|to| chain_ident!(to)),
+                "value": tx.value.to_string(),
+                "status": tx.status,
+            })
+        })
+        .collect::<Vec<_>>();
+    let token_transfers = token_transfers
+        .iter()
+        .map(|transfer| {
+            json!({
+                "transaction_hash": chain_ident!(&transfer.transaction_hash),
+                "log_index": transfer.log_index,
+                "token": chain_ident!(&transfer.token_contract_address_hash),
+                "from": chain_ident!(&transfer.from_address_hash),
+                "to": chain_ident!(&transfer.to_address_hash),
+                "amount": transfer.amount.as_ref().map(|amount| amount.to_string()),
+                "token_id": transfer.token_id.as_ref().map(|id| id.to_string()),
+                "amounts": transfer.amounts.as_ref().map(|amounts| {
+                    amounts.iter().map(|amount| amount.to_string()).collect::<Vec<_>>()
+                }),
+                "token_ids": transfer.token_ids.as_ref().map(|ids| {
+                    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()
+                }),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    new_event(
+        BLOCK_ADDED,
+        block,
+        json!({
+            "hash": chain_ident!(&block.hash),
+            "number": block.number,
+            "parent_hash": chain_ident!(&block.parent_hash),
+            "timestamp": block.timestamp.and_utc().timestamp(),
+            "transactions": transactions,
+            "token_transfers": token_transfers,
+        }),
+    )
+}
+
+/// "block_removed" of the orphaned blocks from the highest down, with the hashes of the
+/// transactions they retract.
+pub fn block_removed_events(
+    blocks: &[BlockModel],
+    transactions: &[TransactionModel],
+) -> Vec<BlockEventModel> {
+    let mut by_block: HashMap<&[u8], Vec<String>> = HashMap::new();
+    for tx in transactions.iter() {
+        if let Some(block_hash) = &tx.block_hash {
+            by_block
+                .entry(block_hash.as_slice())
+                .or_default()
+                .push(chain_ident!(&tx.hash));
+        }
+    }
+
+    let mut blocks = blocks.iter().collect::<Vec<_>>();
+    blocks.sort_by_key(|block| std::cmp::Reverse(block.number));
+    blocks
+        .into_iter()
+        .map(|block| {
+            new_event(
+                BLOCK_REMOVED,
+                block,
+                json!({
+                    "hash": chain_ident!(&block.hash),
+                    "number": block.number,
+                    "parent_hash": chain_ident!(&block.parent_hash),
+                    "transactions": by_block.remove(block.hash.as_slice()).unwrap_or_default(),
+                }),
+            )
+        })
+        .collect()
+}
+
+fn new_event(event_type: &str, block: &BlockModel, payload: Value) -> BlockEventModel {
+    BlockEventModel {
+        // taken by the insert
+        sequence: 0,
+        event_type: event_type.to_string(),
+        block_number: block.number,
+        block_hash: block.hash.clone(),
+        payload,
+        inserted_at: Utc::now().naive_utc(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use entities::blocks::Model as BlockModel;
+    use entities::transactions::Model as TransactionModel;
+    use sea_orm::prelude::Decimal;
+
+    use super::{block_added_event, block_removed_events, BLOCK_ADDED, BLOCK_REMOVED};
+    use crate::handler::token;
+
+    fn block(number: i64, hash: u8) -> BlockModel {
+        BlockModel {
+            consensus: true,
+            difficulty: None,
+            gas_limit: Decimal::ZERO,
+            gas_used: Decimal::ZERO,
+            hash: vec![hash; 32],
+            miner_hash: vec![0; 20],
+            nonce: vec![0; 8],
+            number,
+            parent_hash: vec![hash - 1; 32],
+            size: None,
+            timestamp: Utc::now().naive_utc(),
+            total_difficulty: None,
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            refetch_needed: None,
+            base_fee_per_gas: None,
+            is_empty: None,
+        }
+    }
+
+    fn transaction(hash: u8, block_hash: u8) -> TransactionModel {
+        TransactionModel {
+            index: Some(0),
+            status: Some(1),
+            value: BigDecimal::from(7),
+            block_hash: Some(vec![block_hash; 32]),
+            ..token::transaction(&[hash; 32], &[1; 20])
+        }
+    }
+
+    #[test]
+    fn test_block_added_event() {
+        let event = block_added_event(&block(10, 2), &[transaction(3, 2)], &[]);
+        assert_eq!(event.event_type, BLOCK_ADDED);
+        assert_eq!(event.block_number, 10);
+        assert_eq!(event.payload["number"], 10);
+        assert_eq!(event.payload["transactions"][0]["value"], "7");
+        assert_eq!(
+            event.payload["token_transfers"].as_array().unwrap().len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_block_removed_events() {
+        let events = block_removed_events(
+            &[block(10, 2), block(11, 4)],
+            &[transaction(3, 2), transaction(5, 4), transaction(6, 4)],
+        );
+        assert!(events.iter().all(|event| event.event_type == BLOCK_REMOVED));
+        assert_eq!(
+            events.iter().map(|e| e.block_number).collect::<Vec<_>>(),
+            vec![11, 10]
+        );
+        assert_eq!(
+            events[0].payload["transactions"].as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            events[1].payload["transactions"].as_array().unwrap().len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::bail;
+use chrono::{Duration, NaiveDateTime, Utc};
+use entities::{
+    address_coin_balances::Model as CoinBalanceModel, addresses::Model as AddressModel,
+};
+use ethers::types::H160;
+use futures::StreamExt;
+use repo::dal::{
+    address::Mutation as AddressMutation,
+    address_coin_balance::{Mutation as CoinBalanceMutation, Query as CoinBalanceQuery},
+    address_coin_balance_daily::Mutation as CoinBalanceDailyMutation,
+};
+use sea_orm::{prelude::Decimal, DbConn, TransactionTrait};
+
+use crate::common::err::ScannerError;
+use crate::evms::eth::EthCli;
+
+const FETCH_CONCURRENCY: usize = 10;
+// a balance failing this often, like one pruned by a non-archive node, is given up
+const MAX_FETCH_ATTEMPTS: i32 = 5;
+// wait before the second attempt, doubled per further attempt
+const FETCH_RETRY_DELAY: i64 = 60;
+
+/// queue the coin balance of every address touched by the block at its height, the balances
+/// are fetched later by the coin balance task.
+pub fn handle_coin_balances(
+    block_number: i64,
+    addresses: &[AddressModel],
+) -> Vec<CoinBalanceModel> {
+    addresses
+        .iter()
+        .map(|address| CoinBalanceModel {
+            address_hash: address.hash.clone(),
+            block_number,
+            value: None,
+            value_fetched_at: None,
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            fetch_attempts: 0,
+            next_fetch_at: None,
+        })
+        .collect()
+}
+
+/// fetch at most `limit` queued coin balances, store them with the daily roll-up and the
+/// current balance of the address, returns the number of balances fetched.
+pub async fn fetch_coin_balances(cli: &EthCli, conn: &DbConn, limit: u64) -> anyhow::Result<usize> {
+    let now = Utc::now().naive_utc();
+    let models = CoinBalanceQuery::find_unfetched(conn, now, MAX_FETCH_ATTEMPTS, limit)
+        .await
+        .map_err(ScannerError::Query)?;
+
+    let balances = futures::stream::iter(models)
+        .map(|model| async move {
+            let address = H160::from_slice(&model.address_hash);
+            let res = cli
+                .get_balance(address, Some(model.block_number as u64))
+                .await;
+            (model, res)
+        })
+        .buffer_unordered(FETCH_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut fetched = 0;
+    for (model, res) in balances.into_iter() {
+        let balance = match res {
+            Ok(balance) => Decimal::from_i128_with_scale(balance.as_u128() as i128, 0),
+            Err(err) => {
+                let attempts = model.fetch_attempts + 1;
+                tracing::warn!(
+                    message = "get coin balance",
+                    block_number = model.block_number,
+                    attempts,
+                    err = ?err
+                );
+                CoinBalanceMutation::mark_fetch_failed(
+                    conn,
+                    model.address_hash,
+                    model.block_number,
+                    attempts,
+                    next_fetch_at(attempts, now),
+                )
+                .await
+                .map_err(|e| ScannerError::Update {
+                    src: "mark coin balance fetch failed".to_string(),
+                    err: e,
+                })?;
+                continue;
+            }
+        };
+        save_coin_balance(conn, &model, balance).await?;
+        fetched += 1;
+    }
+
+    Ok(fetched)
+}
+
+/// the next fetch of a balance failed `attempts` times, none once it is given up.
+fn next_fetch_at(attempts: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if attempts >= MAX_FETCH_ATTEMPTS {
+        return None;
+    }
+    Some(now + Duration::seconds(FETCH_RETRY_DELAY << (attempts - 1).clamp(0, 16)))
+}
+
+async fn save_coin_balance(
+    conn: &DbConn,
+    model: &CoinBalanceModel,
+    balance: Decimal,
+) -> anyhow::Result<()> {
+    let txn = conn.begin().await?;
+
+    match CoinBalanceMutation::update_value(
+        &txn,
+        model.address_hash.clone(),
+        model.block_number,
+        balance,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Upsert {
+                src: "update coin balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match CoinBalanceDailyMutation::refresh(&txn, model.address_hash.clone(), model.block_number)
+        .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Upsert {
+                src: "refresh daily coin balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match AddressMutation::update_coin_balance(
+        &txn,
+        model.address_hash.clone(),
+        balance,
+        model.block_number,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Upsert {
+                src: "update address coin balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{handle_coin_balances, next_fetch_at};
+    use crate::handler::address::address;
+
+    #[test]
+    fn test_handle_coin_balances() {
+        let address = address(&[1; 20]);
+
+        let balances = handle_coin_balances(100, &[address]);
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].block_number, 100);
+        assert_eq!(balances[0].address_hash, vec![1; 20]);
+        assert!(balances[0].value_fetched_at.is_none());
+    }
+
+    #[test]
+    fn test_next_fetch_at() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(
+            next_fetch_at(1, now),
+            Some(now + chrono::Duration::seconds(60))
+        );
+        assert_eq!(
+            next_fetch_at(3, now),
+            Some(now + chrono::Duration::seconds(240))
+        );
+        assert_eq!(next_fetch_at(5, now), None);
+    }
+}
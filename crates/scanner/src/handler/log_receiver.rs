@@ -1,32 +1,244 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
 use entities::log_receiver_chain::Model as ScannerBlockModel;
+use ethers::types::{Block, Filter, Log, TxHash, H160, U64};
 use repo::dal::log_receiver_chain::{Mutation, Query};
-use sea_orm::DbConn;
+use sea_orm::{DbConn, DbErr};
+
+use crate::{
+    cache::log_receiver::ContractAddrCache,
+    common::err::ScannerError,
+    evms::eth::{EthCli, LogDetail, MyLog},
+};
+
+// calls per batch request
+const BATCH_SIZE: usize = 100;
 
+/// the next block the task scans, a new task starts at `start_height`.
 pub async fn log_scanner_current_height(
     conn: &DbConn,
     task_name: &str,
     chain_name: &str,
-) -> Option<u64> {
-    let current_model = Query::select_one_by_task_name(conn, task_name)
-        .await
-        .unwrap();
+    start_height: i64,
+) -> Result<i64, DbErr> {
+    let current_model = Query::select_one_by_task_name(conn, task_name).await?;
     match current_model {
-        Some(current) => return Some(current.height),
+        Some(current) => Ok(current.height),
         None => {
             tracing::debug!("not found {}", task_name);
             let insert_data = ScannerBlockModel {
                 id: 0,
                 task_name: task_name.to_owned(),
                 chain_name: chain_name.to_owned(),
-                height: 1,
+                height: start_height,
                 created_at: None,
                 updated_at: None,
             };
-            let result = Mutation::create_scanner_height(conn, insert_data)
-                .await
-                .unwrap_or_else(|_| panic!("insert {} to scanner height table err", task_name));
+            let result = Mutation::create_scanner_height(conn, insert_data).await?;
             tracing::debug!("insert {} return :{:?}", task_name, result);
+            Ok(start_height)
+        }
+    }
+}
+
+/// logs of the registered contracts in `[from, to]` grouped per transaction in chain order.
+pub async fn fetch_contract_logs(
+    eth_cli: &EthCli,
+    contracts: &ContractAddrCache,
+    chain_name: &str,
+    chain_id: i64,
+    from: u64,
+    to: u64,
+) -> Result<Vec<MyLog>> {
+    let (addresses, topics) = contracts.filter_params();
+    if addresses.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let filter = Filter::new()
+        .from_block(from)
+        .to_block(to)
+        .address(addresses)
+        .topic0(topics);
+    let logs = eth_cli
+        .get_logs(&filter)
+        .await?
+        .into_iter()
+        .filter(|log| !log.removed.unwrap_or(false) && contracts.matches(chain_name, chain_id, log))
+        .collect::<Vec<_>>();
+
+    // only the timestamps of the blocks and the `to` of the transactions are needed, headers
+    // and transactions are fetched in batches instead of whole blocks one by one.
+    let mut numbers = logs
+        .iter()
+        .filter_map(|log| log.block_number)
+        .map(|number| number.as_u64())
+        .collect::<Vec<_>>();
+    numbers.sort_unstable();
+    numbers.dedup();
+    let mut headers = HashMap::new();
+    for chunk in numbers.chunks(BATCH_SIZE) {
+        let fetched = eth_cli.get_headers(chunk).await?;
+        for (number, header) in chunk.iter().zip(fetched) {
+            match header {
+                Some(header) => {
+                    headers.insert(U64::from(*number), header);
+                }
+                None => bail!("log receiver block {} not found", number),
+            }
+        }
+    }
+
+    let mut hashes = logs
+        .iter()
+        .filter_map(|log| log.transaction_hash)
+        .collect::<Vec<_>>();
+    hashes.sort_unstable();
+    hashes.dedup();
+    let mut tos = HashMap::new();
+    for chunk in hashes.chunks(BATCH_SIZE) {
+        let fetched = eth_cli.get_transactions(chunk).await?;
+        for (hash, transaction) in chunk.iter().zip(fetched) {
+            match transaction {
+                Some(transaction) => {
+                    tos.insert(*hash, transaction.to.unwrap_or_default());
+                }
+                None => bail!(ScannerError::Reorg(format!(
+                    "log receiver transaction {:#x} not found",
+                    hash
+                ))),
+            }
+        }
+    }
+
+    group_logs(logs, &headers, &tos)
+}
+
+/// group the logs per transaction, the headers of the blocks carry the timestamps and `tos` the
+/// `to` of the transactions. a block whose hash differs from the one of its logs was
+/// reorganized in between and the range has to be fetched again.
+pub fn group_logs(
+    logs: Vec<Log>,
+    headers: &HashMap<U64, Block<TxHash>>,
+    tos: &HashMap<TxHash, H160>,
+) -> Result<Vec<MyLog>> {
+    let mut grouped: BTreeMap<(U64, U64), MyLog> = BTreeMap::new();
+    for log in logs.iter() {
+        let (Some(block_number), Some(block_hash), Some(transaction_index)) =
+            (log.block_number, log.block_hash, log.transaction_index)
+        else {
+            // pending logs
+            continue;
+        };
+        let Some(header) = headers.get(&block_number) else {
+            bail!("log receiver block {} not fetched", block_number);
+        };
+        if header.hash != Some(block_hash) {
+            bail!(ScannerError::Reorg(format!(
+                "log receiver block {} changed to {:?}",
+                block_number, header.hash
+            )));
         }
+
+        grouped
+            .entry((block_number, transaction_index))
+            .or_insert_with(|| MyLog {
+                log_details: vec![],
+                block_hash,
+                block_number,
+                block_timestamp: header.timestamp,
+                transaction_index,
+                to: log
+                    .transaction_hash
+                    .and_then(|hash| tos.get(&hash).copied())
+                    .unwrap_or_default(),
+            })
+            .log_details
+            .push(LogDetail::from(log));
+    }
+
+    let mut logs = grouped.into_values().collect::<Vec<_>>();
+    for log in logs.iter_mut() {
+        log.log_details.sort_by_key(|detail| detail.log_index);
+    }
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ethers::types::{Address, Block, Log, TxHash, H256, U256, U64};
+
+    use super::group_logs;
+
+    fn log(block: u64, tx_index: u64, log_index: u64) -> Log {
+        Log {
+            address: Address::from_low_u64_be(1),
+            block_number: Some(U64::from(block)),
+            block_hash: Some(H256::from_low_u64_be(block)),
+            transaction_hash: Some(H256::from_low_u64_be(block * 100 + tx_index)),
+            transaction_index: Some(U64::from(tx_index)),
+            log_index: Some(U256::from(log_index)),
+            ..Default::default()
+        }
+    }
+
+    fn header(number: u64) -> Block<TxHash> {
+        Block {
+            hash: Some(H256::from_low_u64_be(number)),
+            number: Some(U64::from(number)),
+            timestamp: U256::from(number * 12),
+            ..Default::default()
+        }
+    }
+
+    fn tos(number: u64, tx_indexes: &[u64]) -> Vec<(TxHash, Address)> {
+        tx_indexes
+            .iter()
+            .map(|index| {
+                (
+                    H256::from_low_u64_be(number * 100 + index),
+                    Address::from_low_u64_be(*index + 10),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_group_logs() {
+        let headers = HashMap::from([(U64::from(10), header(10)), (U64::from(11), header(11))]);
+        let tos = tos(10, &[0, 1])
+            .into_iter()
+            .chain(tos(11, &[0]))
+            .collect::<HashMap<_, _>>();
+        let logs = group_logs(
+            vec![log(11, 0, 0), log(10, 1, 3), log(10, 0, 1), log(10, 1, 2)],
+            &headers,
+            &tos,
+        )
+        .unwrap();
+
+        assert_eq!(
+            logs.iter()
+                .map(|l| (l.block_number.as_u64(), l.transaction_index.as_u64()))
+                .collect::<Vec<_>>(),
+            vec![(10, 0), (10, 1), (11, 0)]
+        );
+        assert_eq!(logs[1].to, Address::from_low_u64_be(11));
+        assert_eq!(logs[1].block_timestamp, U256::from(120));
+        assert_eq!(
+            logs[1]
+                .log_details
+                .iter()
+                .map(|d| d.log_index.unwrap().as_u64())
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        let mut reorged = log(10, 0, 1);
+        reorged.block_hash = Some(H256::zero());
+        assert!(group_logs(vec![reorged], &headers, &tos).is_err());
     }
-    None
 }
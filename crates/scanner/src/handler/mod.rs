@@ -1,10 +1,18 @@
 pub mod address;
 pub mod address_token_balance;
+pub mod backfill;
+pub mod beneficiary;
 pub mod block;
+pub mod block_event;
+pub mod coin_balance;
 pub mod event;
 pub mod internal_transaction;
 pub mod log_receiver;
 pub mod mint_transfer;
+pub mod reorg;
 pub mod token;
+pub mod token_instance;
 pub mod transaction;
+pub mod uncle;
+pub mod watchlist;
 pub mod withdrawal;
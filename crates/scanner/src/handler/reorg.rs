@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use chrono::Utc;
+use entities::{
+    address_coin_balances::Model as CoinBalanceModel,
+    address_current_token_balances::Model as CurrentTokenBalanceModel,
+    transaction_forks::Model as TransactionForkModel, transactions::Model as TransactionModel,
+};
+use ethers::types::{Block, Transaction};
+use repo::dal::{
+    account_watchlist_delivery::Mutation as DeliveryMutation,
+    account_watchlist_notification::Mutation as NotificationMutation,
+    address::{Mutation as AddressMutation, Query as AddressQuery},
+    address_coin_balance::{Mutation as CoinBalanceMutation, Query as CoinBalanceQuery},
+    block::{Mutation as BlockMutation, Query as BlockQuery},
+    block_event::Mutation as BlockEventMutation,
+    block_reward::Mutation as BlockRewardMutation,
+    current_token_balance::{Mutation as CurrentTokenMutation, Query as CurrentTokenQuery},
+    event::Mutation as EventMutation,
+    internal_transaction::Mutation as InnerTransactionMutation,
+    token::Mutation as TokenMutation,
+    token_balance::{Mutation as TokenBalanceMutation, Query as TokenBalanceQuery},
+    token_instance::Mutation as TokenInstanceMutation,
+    token_transfer::Mutation as TokenTransferMutation,
+    transaction::{Mutation as TransactionMutation, Query as TransactionQuery},
+    transaction_fork::Mutation as TransactionForkMutation,
+    withdrawal::Mutation as WithdrawalMutation,
+};
+use sea_orm::{prelude::Decimal, DbConn, TransactionTrait};
+
+use crate::common::err::ScannerError;
+use crate::evms::eth::EthCli;
+use crate::handler::address_token_balance::holder_delta;
+use crate::handler::block_event::block_removed_events;
+
+/// how deep we walk back looking for the common ancestor before giving up.
+pub const MAX_REORG_DEPTH: i64 = 128;
+
+/// `head` is the new block whose parent hash does not match our latest consensus block.
+/// roll back every block above the common ancestor, the canonical branch is re-indexed
+/// by the block handler starting from `ancestor + 1`.
+pub async fn handle_reorg(
+    cli: &EthCli,
+    conn: &DbConn,
+    head: &Block<Transaction>,
+) -> anyhow::Result<i64> {
+    let head_number = match head.number {
+        Some(number) => number.as_u64() as i64,
+        None => bail!(ScannerError::Reorg("head block without number".to_string())),
+    };
+
+    let ancestor = find_common_ancestor(cli, conn, head_number - 1).await?;
+    tracing::warn!(
+        "reorg detected at height {}, common ancestor {}, depth {}",
+        head_number,
+        ancestor,
+        head_number - 1 - ancestor
+    );
+
+    rollback_to(conn, ancestor).await?;
+
+    Ok(ancestor)
+}
+
+/// walk down from `from` until the local consensus block matches the one on chain.
+pub async fn find_common_ancestor(cli: &EthCli, conn: &DbConn, from: i64) -> anyhow::Result<i64> {
+    let lowest = (from - MAX_REORG_DEPTH).max(0);
+    for number in (lowest..=from).rev() {
+        let local = match BlockQuery::find_by_height(conn, number).await {
+            Ok(Some(local)) => local,
+            Ok(None) => bail!(ScannerError::Reorg(format!(
+                "no indexed block at height {}, can not find common ancestor",
+                number
+            ))),
+            Err(e) => bail!(ScannerError::Query(e)),
+        };
+
+        let remote = cli.get_block(number as u64).await?;
+        if let Some(hash) = remote.hash {
+            if hash.as_bytes() == local.hash.as_slice() {
+                return Ok(number);
+            }
+        }
+    }
+
+    bail!(ScannerError::Reorg(format!(
+        "no common ancestor found within {} blocks below {}",
+        MAX_REORG_DEPTH, from
+    )))
+}
+
+/// mark every block above `ancestor` as non-consensus and undo the rows derived from them.
+pub async fn rollback_to(conn: &DbConn, ancestor: i64) -> anyhow::Result<()> {
+    let orphaned = BlockQuery::find_above(conn, ancestor)
+        .await
+        .map_err(ScannerError::Query)?;
+    if orphaned.is_empty() {
+        return Ok(());
+    }
+
+    let hashes = orphaned
+        .iter()
+        .map(|b| b.hash.clone())
+        .collect::<Vec<Vec<u8>>>();
+    let transactions = TransactionQuery::find_by_block_hashes(conn, hashes.clone())
+        .await
+        .map_err(ScannerError::Query)?;
+    let forks = build_transaction_forks(&transactions);
+    let removed = block_removed_events(&orphaned, &transactions);
+
+    // current balances touched by orphaned blocks are rebuilt from the latest history before the ancestor.
+    let mut restored = vec![];
+    let currents = CurrentTokenQuery::find_above(conn, ancestor)
+        .await
+        .map_err(ScannerError::Query)?;
+    for current in currents.iter() {
+        let latest = TokenBalanceQuery::find_latest_not_above(
+            conn,
+            current.address_hash.clone(),
+            current.token_contract_address_hash.clone(),
+            current.token_id.clone(),
+            ancestor,
+        )
+        .await
+        .map_err(ScannerError::Query)?;
+        if let Some(balance) = latest {
+            restored.push(CurrentTokenBalanceModel {
+                id: 0,
+                address_hash: balance.address_hash,
+                block_number: balance.block_number,
+                token_contract_address_hash: balance.token_contract_address_hash,
+                value: balance.value,
+                value_fetched_at: balance.value_fetched_at,
+                inserted_at: Utc::now().naive_utc(),
+                updated_at: Utc::now().naive_utc(),
+                old_value: None,
+                token_id: balance.token_id,
+                token_type: balance.token_type,
+            });
+        }
+    }
+
+    let holder_deltas = rollback_holder_deltas(&currents, &restored);
+
+    // coin balances fetched at the orphaned heights go back to the latest one before the ancestor
+    let coin_balance_addresses = AddressQuery::find_coin_balance_above(conn, ancestor)
+        .await
+        .map_err(ScannerError::Query)?
+        .into_iter()
+        .map(|address| address.hash)
+        .collect::<Vec<_>>();
+    let mut latest_coin_balances = HashMap::new();
+    for address in coin_balance_addresses.iter() {
+        let latest =
+            CoinBalanceQuery::find_latest_fetched_not_above(conn, address.clone(), ancestor)
+                .await
+                .map_err(ScannerError::Query)?;
+        if let Some(balance) = latest {
+            latest_coin_balances.insert(address.clone(), balance);
+        }
+    }
+    let coin_balances = restore_coin_balances(&coin_balance_addresses, &latest_coin_balances);
+
+    // counters of addresses touched by orphaned blocks are recounted once they are forked
+    let touched = AddressQuery::find_touched(conn, ancestor + 1, i64::MAX)
+        .await
+        .map_err(ScannerError::Query)?
+        .into_iter()
+        .map(|address| address.hash)
+        .collect::<Vec<_>>();
+
+    let txn = conn.begin().await?;
+
+    match BlockMutation::lose_consensus(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Update {
+                src: "lose consensus blocks".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match BlockEventMutation::append(&txn, &removed).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Create {
+                src: "create block removed events".to_string(),
+                err: e
+            });
+        }
+    }
+
+    if !forks.is_empty() {
+        match TransactionForkMutation::save(&txn, &forks).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Create {
+                    src: "create transaction forks".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    match TransactionMutation::fork_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Update {
+                src: "fork transactions".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match EventMutation::delete_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete events".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match TokenTransferMutation::delete_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete token transfers".to_string(),
+                err: e
+            });
+        }
+    }
+
+    // owners set by the orphaned blocks go back to the receiver of the latest remaining transfer
+    match TokenInstanceMutation::restore_owners_above(&txn, ancestor).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Update {
+                src: "restore token instance owners".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match InnerTransactionMutation::delete_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete internal transactions".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match BlockRewardMutation::delete_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete block rewards".to_string(),
+                err: e
+            });
+        }
+    }
+
+    // the canonical blocks notify their own activities, the ones of the orphaned blocks are
+    // no longer delivered
+    match DeliveryMutation::mark_dead_by_block_hashes(
+        &txn,
+        hashes.clone(),
+        "block removed by reorg".to_string(),
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Update {
+                src: "kill watchlist deliveries".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match NotificationMutation::delete_by_block_hashes(&txn, hashes.clone()).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete watchlist notifications".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match WithdrawalMutation::delete_by_block_hashes(&txn, hashes).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete withdraws".to_string(),
+                err: e
+            });
+        }
+    }
+
+    match TokenBalanceMutation::delete_above(&txn, ancestor).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete address token balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    // balances of the forked heights are queued again when the canonical blocks are indexed
+    match CoinBalanceMutation::delete_above(&txn, ancestor).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete address coin balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    for (hash, value, block_number) in coin_balances.into_iter() {
+        match AddressMutation::set_coin_balance(&txn, hash, value, block_number).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "restore address coin balance".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    match CurrentTokenMutation::delete_above(&txn, ancestor).await {
+        Ok(_) => {}
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Delete {
+                src: "delete address current token balance".to_string(),
+                err: e
+            });
+        }
+    }
+
+    if !restored.is_empty() {
+        match CurrentTokenMutation::save(&txn, &restored).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Upsert {
+                    src: "restore address current token balance".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    for (token, delta) in holder_deltas.into_iter().filter(|(_, delta)| *delta != 0) {
+        match TokenMutation::increment_holder_count(&txn, token, delta).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "update token holder count".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    if !touched.is_empty() {
+        match AddressMutation::recount(&txn, touched).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Update {
+                    src: "recount address counters".to_string(),
+                    err: e
+                });
+            }
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// the holder count change per token of the current balances going back from the orphaned
+/// values to the restored ones, the balances not restored are no longer held.
+pub fn rollback_holder_deltas(
+    currents: &[CurrentTokenBalanceModel],
+    restored: &[CurrentTokenBalanceModel],
+) -> HashMap<Vec<u8>, i32> {
+    let key = |balance: &CurrentTokenBalanceModel| {
+        (
+            balance.address_hash.clone(),
+            balance.token_contract_address_hash.clone(),
+            balance.token_id.clone(),
+        )
+    };
+    let restored = restored
+        .iter()
+        .map(|balance| (key(balance), balance.value.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut deltas: HashMap<Vec<u8>, i32> = HashMap::new();
+    for current in currents.iter() {
+        let value = restored.get(&key(current)).cloned().flatten();
+        *deltas
+            .entry(current.token_contract_address_hash.clone())
+            .or_default() += holder_delta(
+            &current.address_hash,
+            current.value.as_ref(),
+            value.as_ref(),
+        );
+    }
+    deltas
+}
+
+/// the coin balance of the addresses at the latest fetched balance left, none if there is none
+/// left and the canonical blocks queue it again.
+pub fn restore_coin_balances(
+    addresses: &[Vec<u8>],
+    latest: &HashMap<Vec<u8>, CoinBalanceModel>,
+) -> Vec<(Vec<u8>, Option<Decimal>, Option<i64>)> {
+    addresses
+        .iter()
+        .map(|address| match latest.get(address) {
+            Some(balance) => (address.clone(), balance.value, Some(balance.block_number)),
+            None => (address.clone(), None, None),
+        })
+        .collect()
+}
+
+pub fn build_transaction_forks(transactions: &[TransactionModel]) -> Vec<TransactionForkModel> {
+    transactions
+        .iter()
+        .filter_map(|tx| match (&tx.block_hash, tx.index) {
+            (Some(block_hash), Some(index)) => Some(TransactionForkModel {
+                hash: tx.hash.clone(),
+                index,
+                uncle_hash: block_hash.clone(),
+                inserted_at: Utc::now().naive_utc(),
+                updated_at: Utc::now().naive_utc(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use entities::transactions::Model as TransactionModel;
+    use sea_orm::prelude::Decimal;
+
+    use std::collections::HashMap;
+
+    use entities::{
+        address_coin_balances::Model as CoinBalanceModel,
+        address_current_token_balances::Model as CurrentTokenBalanceModel,
+    };
+
+    use super::{build_transaction_forks, restore_coin_balances, rollback_holder_deltas};
+    use crate::handler::token::transaction;
+
+    #[test]
+    fn test_build_transaction_forks() {
+        let collated = TransactionModel {
+            block_hash: Some(vec![2; 32]),
+            index: Some(3),
+            ..transaction(&[1; 32], &[])
+        };
+        let pending = transaction(&[4; 32], &[]);
+
+        let forks = build_transaction_forks(&[collated, pending]);
+        assert_eq!(forks.len(), 1);
+        assert_eq!(forks[0].hash, vec![1; 32]);
+        assert_eq!(forks[0].uncle_hash, vec![2; 32]);
+        assert_eq!(forks[0].index, 3);
+    }
+
+    #[test]
+    fn test_restore_coin_balances() {
+        let balance = CoinBalanceModel {
+            address_hash: vec![1],
+            block_number: 90,
+            value: Some(Decimal::from(5)),
+            value_fetched_at: Some(Utc::now().naive_utc()),
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            fetch_attempts: 0,
+            next_fetch_at: None,
+        };
+        let latest = HashMap::from([(vec![1], balance)]);
+
+        // the address only touched by the orphaned blocks has no balance left
+        assert_eq!(
+            restore_coin_balances(&[vec![1], vec![2]], &latest),
+            vec![
+                (vec![1], Some(Decimal::from(5)), Some(90)),
+                (vec![2], None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rollback_holder_deltas() {
+        let current = |address: u8, value: i32| CurrentTokenBalanceModel {
+            id: 0,
+            address_hash: vec![address],
+            block_number: 100,
+            token_contract_address_hash: vec![7],
+            value: Some(BigDecimal::from(value)),
+            value_fetched_at: None,
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            old_value: None,
+            token_id: None,
+            token_type: None,
+        };
+
+        // 1 received its first tokens on the orphaned branch, 2 sent all of its tokens there
+        // and 3 only changed its balance
+        let currents = vec![current(1, 5), current(2, 0), current(3, 4)];
+        let restored = vec![current(2, 3), current(3, 8)];
+        let deltas = rollback_holder_deltas(&currents, &restored);
+        assert_eq!(deltas, HashMap::from([(vec![7], 0)]));
+
+        let deltas = rollback_holder_deltas(&[current(1, 5), current(3, 4)], &restored);
+        assert_eq!(deltas, HashMap::from([(vec![7], -1)]));
+    }
+}
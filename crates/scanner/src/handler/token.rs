@@ -6,8 +6,12 @@ use entities::address_current_token_balances::Model as CurrentTokenBalanceModel;
 use entities::address_token_balances::Model as AddressTokenBalanceModel;
 use entities::token_transfers::Model as TokenTransferModel;
 use entities::tokens::Model as TokenModel;
+#[cfg(test)]
+use entities::transactions::Model as TransactionModel;
 use ethers::types::{Log, TransactionReceipt, H160, H256};
 use sea_orm::prelude::BigDecimal;
+#[cfg(test)]
+use sea_orm::prelude::Decimal;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
@@ -107,28 +111,19 @@ pub fn token_process(logs: &[Log]) -> (Vec<TokenModel>, Vec<TokenTransferModel>)
     (tokens_uniq, acc.1)
 }
 
-#[allow(dead_code)]
-fn call_update_total_supply(transfers: &[TokenTransferModel]) {
-    let burn_transfers = transfers
+/// contracts of the mint and burn transfers, the total supply of those tokens has changed.
+pub fn total_supply_changed_tokens(transfers: &[TokenTransferModel]) -> Vec<Vec<u8>> {
+    let burn_address: H160 = consts::BURN_ADDRESS.parse().unwrap();
+    let burn_address = burn_address.as_bytes();
+    transfers
         .iter()
         .filter(|transfer| {
-            transfer.to_address_hash == consts::BURN_ADDRESS.as_bytes().to_vec()
-                || transfer.from_address_hash == consts::BURN_ADDRESS.as_bytes().to_vec()
+            transfer.to_address_hash == burn_address || transfer.from_address_hash == burn_address
         })
-        .cloned()
-        .collect::<Vec<TokenTransferModel>>();
-
-    let burn_contract_addresses = burn_transfers
-        .iter()
         .map(|transfer| transfer.token_contract_address_hash.clone())
-        .collect::<Vec<Vec<u8>>>();
-
-    let _unique_burn_contract_addresses = burn_contract_addresses
-        .iter()
-        .cloned()
-        .collect::<HashSet<Vec<u8>>>();
-
-    // TokenTotalSupplyUpdater::add_tokens(unique_token_contract_addresses);
+        .collect::<HashSet<Vec<u8>>>()
+        .into_iter()
+        .collect()
 }
 
 fn confirm_token_type(new_type: String, old_type: String) -> String {
@@ -415,3 +410,116 @@ fn defualt_model(log: &Log) -> (TokenModel, TokenTransferModel) {
 
     (token, transfer_model)
 }
+
+#[cfg(test)]
+pub(crate) fn token(address: &[u8], r#type: &str) -> TokenModel {
+    TokenModel {
+        contract_address_hash: address.to_vec(),
+        name: None,
+        symbol: None,
+        total_supply: None,
+        decimals: None,
+        r#type: r#type.to_string(),
+        cataloged: None,
+        inserted_at: Utc::now().naive_utc(),
+        updated_at: Utc::now().naive_utc(),
+        holder_count: None,
+        skip_metadata: None,
+        fiat_value: None,
+        circulating_market_cap: None,
+        total_supply_updated_at_block: None,
+        icon_url: None,
+        is_verified_via_admin_panel: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn token_transfer(contract: &[u8], from: &[u8], to: &[u8]) -> TokenTransferModel {
+    TokenTransferModel {
+        transaction_hash: vec![],
+        log_index: 0,
+        from_address_hash: from.to_vec(),
+        to_address_hash: to.to_vec(),
+        amount: None,
+        token_id: None,
+        token_contract_address_hash: contract.to_vec(),
+        inserted_at: Utc::now().naive_utc(),
+        updated_at: Utc::now().naive_utc(),
+        block_number: None,
+        block_hash: vec![],
+        amounts: None,
+        token_ids: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn transaction(hash: &[u8], from: &[u8]) -> TransactionModel {
+    TransactionModel {
+        cumulative_gas_used: None,
+        error: None,
+        gas: Decimal::ZERO,
+        gas_price: None,
+        gas_used: None,
+        hash: hash.to_vec(),
+        index: None,
+        input: vec![],
+        nonce: 0,
+        r: vec![],
+        s: vec![],
+        status: None,
+        v: Decimal::ZERO,
+        value: BigDecimal::from(0),
+        inserted_at: Utc::now().naive_utc(),
+        updated_at: Utc::now().naive_utc(),
+        block_hash: None,
+        block_number: None,
+        from_address_hash: from.to_vec(),
+        to_address_hash: None,
+        created_contract_address_hash: None,
+        created_contract_code_indexed_at: None,
+        earliest_processing_start: None,
+        old_block_hash: None,
+        revert_reason: None,
+        max_priority_fee_per_gas: None,
+        max_fee_per_gas: None,
+        r#type: None,
+        has_error_in_internal_txs: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Log, H160};
+
+    use super::{defualt_model, total_supply_changed_tokens};
+
+    #[test]
+    fn test_total_supply_changed_tokens() {
+        let transfer = |contract: u64, from: u64, to: u64| {
+            let log = Log {
+                address: H160::from_low_u64_be(contract),
+                ..Default::default()
+            };
+            let (_, mut transfer) = defualt_model(&log);
+            transfer.from_address_hash = H160::from_low_u64_be(from).as_bytes().to_vec();
+            transfer.to_address_hash = H160::from_low_u64_be(to).as_bytes().to_vec();
+            transfer
+        };
+        let transfers = vec![
+            transfer(1, 0, 10),
+            transfer(1, 10, 0),
+            transfer(2, 10, 11),
+            transfer(3, 11, 0),
+        ];
+
+        let mut contracts = total_supply_changed_tokens(&transfers);
+        contracts.sort();
+        assert_eq!(
+            contracts,
+            vec![
+                H160::from_low_u64_be(1).as_bytes().to_vec(),
+                H160::from_low_u64_be(3).as_bytes().to_vec(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use bigdecimal::BigDecimal;
+use chrono::{Duration, NaiveDateTime, Utc};
+use common::{chain_ident, consts};
+use entities::token_instances::Model as TokenInstanceModel;
+use entities::token_transfers::Model as TokenTransferModel;
+use entities::tokens::Model as TokenModel;
+use ethers::types::U256;
+use repo::dal::token_instance::Mutation as TokenInstanceMutation;
+use sea_orm::DbConn;
+use serde_json::Value;
+
+use crate::common::err::ScannerError;
+use crate::contracts::nft::INFTCall;
+use crate::indexer::token_instance::{fetch_metadata, MetadataFetcher};
+
+// fetch attempts of a token instance before its error is kept for good
+pub const MAX_RETRIES: i32 = 5;
+// seconds before the second attempt
+const RETRY_BASE_DELAY: i64 = 30;
+const MAX_ERROR_LEN: usize = 255;
+
+/// one instance for every token id transferred in the block, the metadata is fetched later.
+/// ERC-721 instances carry the receiver of their last transfer in the block as owner.
+pub fn handle_token_instances(
+    tokens: &[TokenModel],
+    token_transfers: &[TokenTransferModel],
+) -> Vec<TokenInstanceModel> {
+    let erc721_contracts = tokens
+        .iter()
+        .filter(|token| token.r#type == consts::ERC721)
+        .map(|token| token.contract_address_hash.clone())
+        .collect::<HashSet<_>>();
+
+    let mut index: HashMap<(Vec<u8>, BigDecimal), usize> = HashMap::new();
+    let mut resp: Vec<TokenInstanceModel> = vec![];
+    for transfer in token_transfers.iter() {
+        let token_ids = match (&transfer.token_ids, &transfer.token_id) {
+            (Some(token_ids), _) => token_ids.clone(),
+            (None, Some(token_id)) => vec![token_id.clone()],
+            (None, None) => continue,
+        };
+
+        let owned = erc721_contracts.contains(&transfer.token_contract_address_hash);
+        for token_id in token_ids.into_iter() {
+            let mut instance = TokenInstanceModel {
+                token_id: token_id.clone(),
+                token_contract_address_hash: transfer.token_contract_address_hash.clone(),
+                metadata: None,
+                inserted_at: Utc::now().naive_utc(),
+                updated_at: Utc::now().naive_utc(),
+                error: None,
+                owner_address_hash: None,
+                owner_updated_at_block: None,
+                owner_updated_at_log_index: None,
+                retries_count: 0,
+                refetch_after: None,
+            };
+            if owned {
+                instance.owner_address_hash = Some(transfer.to_address_hash.clone());
+                instance.owner_updated_at_block = transfer.block_number;
+                instance.owner_updated_at_log_index = Some(transfer.log_index);
+            }
+
+            let key = (transfer.token_contract_address_hash.clone(), token_id);
+            match index.get(&key) {
+                Some(idx) => {
+                    let current = &mut resp[*idx];
+                    if owned
+                        && (
+                            current.owner_updated_at_block,
+                            current.owner_updated_at_log_index,
+                        ) < (
+                            instance.owner_updated_at_block,
+                            instance.owner_updated_at_log_index,
+                        )
+                    {
+                        *current = instance;
+                    }
+                }
+                None => {
+                    index.insert(key, resp.len());
+                    resp.push(instance);
+                }
+            }
+        }
+    }
+
+    resp
+}
+
+/// read the token uri from the contract and fetch the metadata behind it.
+pub async fn fetch_instance_metadata(
+    nft_call: &INFTCall,
+    fetcher: &dyn MetadataFetcher,
+    ipfs_gateway: Option<&str>,
+    instance: &TokenInstanceModel,
+    token_type: &str,
+) -> anyhow::Result<Value> {
+    let token_kind = match token_type {
+        consts::ERC721 => consts::TokenKind::ERC721,
+        consts::ERC1155 => consts::TokenKind::ERC1155,
+        _ => bail!("token type {} has no instances", token_type),
+    };
+    let token_id = U256::from_dec_str(&instance.token_id.to_string())?;
+    let uri = nft_call
+        .token_uri(
+            chain_ident!(&instance.token_contract_address_hash).as_str(),
+            token_id,
+            token_kind,
+        )
+        .await?;
+
+    fetch_metadata(fetcher, &uri, ipfs_gateway).await
+}
+
+/// store the metadata, or the error of the fetch together with its next attempt. false once
+/// the instance ran out of retries.
+pub async fn save_instance_metadata(
+    conn: &DbConn,
+    instance: &TokenInstanceModel,
+    result: &anyhow::Result<Value>,
+    now: NaiveDateTime,
+) -> anyhow::Result<bool> {
+    let (res, retried) = match result {
+        Ok(metadata) => (
+            TokenInstanceMutation::update_metadata(
+                conn,
+                instance.token_contract_address_hash.clone(),
+                instance.token_id.clone(),
+                metadata.clone(),
+            )
+            .await,
+            true,
+        ),
+        Err(err) => {
+            let retries_count = instance.retries_count + 1;
+            let refetch_after = refetch_after(retries_count, now);
+            (
+                TokenInstanceMutation::mark_fetch_failed(
+                    conn,
+                    instance.token_contract_address_hash.clone(),
+                    instance.token_id.clone(),
+                    truncate_error(&err.to_string()),
+                    retries_count,
+                    refetch_after,
+                )
+                .await,
+                refetch_after.is_some(),
+            )
+        }
+    };
+
+    match res {
+        Ok(_) => Ok(retried),
+        Err(e) => bail!(ScannerError::Update {
+            src: "update token instance metadata".to_string(),
+            err: e
+        }),
+    }
+}
+
+/// the next fetch of an instance failed `retries_count` times, the delay doubles with every
+/// failure. none once it ran out of retries.
+pub fn refetch_after(retries_count: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if retries_count >= MAX_RETRIES {
+        return None;
+    }
+    Some(now + Duration::seconds(RETRY_BASE_DELAY << (retries_count - 1).clamp(0, 16)))
+}
+
+// `token_instances.error` is a varchar(255)
+fn truncate_error(err: &str) -> String {
+    err.chars().take(MAX_ERROR_LEN).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::{Duration, Utc};
+    use common::consts;
+    use entities::token_transfers::Model as TokenTransferModel;
+    use ethers::types::H160;
+
+    use super::{handle_token_instances, refetch_after, truncate_error, MAX_RETRIES};
+    use crate::handler::token::{token, token_transfer};
+
+    fn transfer(token_id: Option<u64>, token_ids: Option<Vec<u64>>) -> TokenTransferModel {
+        TokenTransferModel {
+            token_id: token_id.map(BigDecimal::from),
+            block_number: Some(100),
+            token_ids: token_ids.map(|ids| ids.into_iter().map(BigDecimal::from).collect()),
+            ..token_transfer(
+                H160::from_low_u64_be(1).as_bytes(),
+                &[],
+                H160::from_low_u64_be(10).as_bytes(),
+            )
+        }
+    }
+
+    #[test]
+    fn test_handle_token_instances() {
+        let transfers = vec![
+            transfer(None, None),
+            transfer(Some(1), None),
+            transfer(None, Some(vec![1, 2, 3])),
+        ];
+
+        let instances = handle_token_instances(&[], &transfers);
+        let ids = instances
+            .iter()
+            .map(|instance| instance.token_id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                BigDecimal::from(1),
+                BigDecimal::from(2),
+                BigDecimal::from(3)
+            ]
+        );
+        assert!(instances
+            .iter()
+            .all(|instance| instance.owner_address_hash.is_none()));
+    }
+
+    #[test]
+    fn test_handle_token_instance_owners() {
+        let token = token(H160::from_low_u64_be(1).as_bytes(), consts::ERC721);
+        let mut resold = transfer(Some(1), None);
+        resold.log_index = 5;
+        resold.to_address_hash = H160::from_low_u64_be(11).as_bytes().to_vec();
+
+        // the later transfer wins whatever order the transfers come in
+        let instances = handle_token_instances(&[token], &[resold, transfer(Some(1), None)]);
+        assert_eq!(instances.len(), 1);
+        assert_eq!(
+            instances[0].owner_address_hash,
+            Some(H160::from_low_u64_be(11).as_bytes().to_vec())
+        );
+        assert_eq!(instances[0].owner_updated_at_block, Some(100));
+        assert_eq!(instances[0].owner_updated_at_log_index, Some(5));
+    }
+
+    #[test]
+    fn test_refetch_after() {
+        let now = Utc::now().naive_utc();
+        assert_eq!(refetch_after(1, now), Some(now + Duration::seconds(30)));
+        // the delay doubles with every failure
+        assert_eq!(refetch_after(2, now), Some(now + Duration::seconds(60)));
+        assert_eq!(refetch_after(MAX_RETRIES, now), None);
+
+        assert_eq!(truncate_error(&"e".repeat(300)).len(), 255);
+        assert_eq!(truncate_error(&"é".repeat(300)).chars().count(), 255);
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::bail;
+use entities::{
+    block_second_degree_relations::Model as SecondDegreeRelationModel, blocks::Model as BlockModel,
+};
+use ethers::types::{Block, Transaction, H256};
+use repo::dal::{
+    block::{Mutation as BlockMutation, Query as BlockQuery},
+    block_second_degree_relation::{
+        Mutation as SecondDegreeRelationMutation, Query as SecondDegreeRelationQuery,
+    },
+};
+use sea_orm::{DbConn, TransactionTrait};
+
+use super::block::convert_block_to_model;
+use crate::common::err::ScannerError;
+use crate::evms::eth::EthCli;
+
+/// nephew/uncle relations of the uncles referenced by the block, the uncle headers are
+/// fetched later by the uncle task.
+pub fn handle_second_degree_relations(
+    block: &Block<Transaction>,
+) -> Vec<SecondDegreeRelationModel> {
+    let Some(nephew_hash) = block.hash else {
+        return vec![];
+    };
+
+    block
+        .uncles
+        .iter()
+        .enumerate()
+        .map(|(idx, uncle)| SecondDegreeRelationModel {
+            nephew_hash: nephew_hash.as_bytes().to_vec(),
+            uncle_hash: uncle.as_bytes().to_vec(),
+            uncle_fetched_at: None,
+            index: Some(idx as i32),
+        })
+        .collect()
+}
+
+/// fetch the headers of at most `limit` unfetched uncles and store them as non-consensus
+/// blocks, returns the number of uncles stored.
+pub async fn fetch_uncles(cli: &EthCli, conn: &DbConn, limit: u64) -> anyhow::Result<usize> {
+    let relations = SecondDegreeRelationQuery::find_unfetched(conn, limit)
+        .await
+        .map_err(ScannerError::Query)?;
+
+    let mut fetched = 0;
+    for relation in relations.into_iter() {
+        let nephew_hash = H256::from_slice(&relation.nephew_hash);
+        let index = relation.index.unwrap_or_default() as u64;
+        let Some(uncle) = cli.get_uncle(nephew_hash, index).await? else {
+            tracing::warn!("uncle {} of block {:#x} not found", index, nephew_hash);
+            settle_not_found(conn, relation).await?;
+            continue;
+        };
+
+        let uncle = BlockModel {
+            consensus: false,
+            ..convert_block_to_model(&uncle)
+        };
+        let txn = conn.begin().await?;
+        match BlockMutation::create_uncle(&txn, &uncle).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Create {
+                    src: "create uncle block".to_string(),
+                    err: e
+                });
+            }
+        }
+        match SecondDegreeRelationMutation::mark_fetched(&txn, relation.uncle_hash).await {
+            Ok(_) => {}
+            Err(e) => {
+                txn.rollback().await?;
+                bail!(ScannerError::Upsert {
+                    src: "mark uncle fetched".to_string(),
+                    err: e
+                });
+            }
+        }
+        txn.commit().await?;
+        fetched += 1;
+    }
+
+    Ok(fetched)
+}
+
+/// a relation whose uncle the node does not return is dropped if its nephew lost consensus,
+/// otherwise it is marked fetched so it is not queried again.
+async fn settle_not_found(
+    conn: &DbConn,
+    relation: SecondDegreeRelationModel,
+) -> anyhow::Result<()> {
+    let nephew = BlockQuery::find_by_hash(conn, relation.nephew_hash.clone())
+        .await
+        .map_err(ScannerError::Query)?;
+    if nephew.is_some_and(|nephew| nephew.consensus) {
+        SecondDegreeRelationMutation::mark_fetched(conn, relation.uncle_hash)
+            .await
+            .map_err(|e| ScannerError::Update {
+                src: "mark uncle not found".to_string(),
+                err: e,
+            })?;
+    } else {
+        SecondDegreeRelationMutation::delete(conn, relation.nephew_hash, relation.uncle_hash)
+            .await
+            .map_err(|e| ScannerError::Delete {
+                src: "delete orphaned uncle relation".to_string(),
+                err: e,
+            })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Block, Transaction, H256};
+
+    use super::handle_second_degree_relations;
+
+    #[test]
+    fn test_handle_second_degree_relations() {
+        let block: Block<Transaction> = Block {
+            hash: Some(H256::from_low_u64_be(1)),
+            uncles: vec![H256::from_low_u64_be(2), H256::from_low_u64_be(3)],
+            ..Default::default()
+        };
+
+        let relations = handle_second_degree_relations(&block);
+        assert_eq!(relations.len(), 2);
+        assert_eq!(relations[1].uncle_hash, H256::from_low_u64_be(3).as_bytes());
+        assert_eq!(relations[1].index, Some(1));
+        assert!(relations[0].uncle_fetched_at.is_none());
+    }
+}
@@ -0,0 +1,476 @@
+use std::{collections::HashMap, str::FromStr};
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use common::consts;
+use entities::{
+    account_watchlist_addresses::Model as WatchlistAddressModel,
+    account_watchlist_deliveries::Model as DeliveryModel,
+    account_watchlist_notifications::Model as NotificationModel,
+    internal_transactions::Model as InnerTransactionModel,
+    token_transfers::Model as TokenTransferModel, transactions::Model as TransactionModel,
+};
+use repo::dal::{
+    account_watchlist_address::Query as WatchlistAddressQuery,
+    account_watchlist_delivery::{Mutation as DeliveryMutation, DELIVERY_PENDING},
+    account_watchlist_notification::Mutation as NotificationMutation,
+    account_watchlist_webhook::Query as WebhookQuery,
+    token::Query as TokenQuery,
+};
+use sea_orm::{prelude::Decimal, ConnectionTrait, DbErr};
+
+pub const COIN: &str = "COIN";
+pub const INCOMING: &str = "incoming";
+pub const OUTGOING: &str = "outgoing";
+
+/// a value moved from one address to another by the block
+#[derive(Clone, Debug, PartialEq)]
+pub struct Activity {
+    /// `COIN` or the type of the token
+    pub r#type: String,
+    pub method: String,
+    pub transaction_hash: Vec<u8>,
+    /// log index of token transfers, trace index of internal transfers and -1 for the value
+    /// of the transaction
+    pub log_index: i32,
+    pub from: Vec<u8>,
+    pub to: Vec<u8>,
+    pub amount: Option<Decimal>,
+    pub tx_fee: Option<Decimal>,
+    /// id of the transferred token, if any
+    pub subject: Option<String>,
+}
+
+/// write the notifications of the activities of the watched addresses in the block and
+/// schedule them for the webhooks of their owners if `deliver`, within the transaction of the
+/// block.
+pub async fn handle_watchlist_notifications<C>(
+    db: &C,
+    block_number: i64,
+    block_hash: &[u8],
+    transactions: &[TransactionModel],
+    inner_txs: &[InnerTransactionModel],
+    token_transfers: &[TokenTransferModel],
+    deliver: bool,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut token_contracts = vec![];
+    for transfer in token_transfers.iter() {
+        if !token_contracts.contains(&transfer.token_contract_address_hash) {
+            token_contracts.push(transfer.token_contract_address_hash.clone());
+        }
+    }
+    let token_types = if token_contracts.is_empty() {
+        HashMap::new()
+    } else {
+        TokenQuery::find_by_contract_addresses(db, token_contracts)
+            .await?
+            .into_iter()
+            .map(|token| (token.contract_address_hash, token.r#type))
+            .collect()
+    };
+
+    let activities = block_activities(transactions, inner_txs, token_transfers, &token_types);
+    let mut addresses = vec![];
+    for activity in activities.iter() {
+        for address in [&activity.from, &activity.to] {
+            if !addresses.contains(address) {
+                addresses.push(address.clone());
+            }
+        }
+    }
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    let mut watched = vec![];
+    let mut identities = HashMap::new();
+    for (address, watchlist) in WatchlistAddressQuery::find_by_address_hashes(db, addresses)
+        .await?
+        .into_iter()
+    {
+        if let Some(identity_id) = watchlist.and_then(|watchlist| watchlist.identity_id) {
+            identities.insert(address.id, identity_id);
+        }
+        watched.push(address);
+    }
+
+    let notifications = match_activities(block_number, block_hash, &activities, &watched);
+    if notifications.is_empty() {
+        return Ok(());
+    }
+    // the notifications of a block synced again are already scheduled
+    let notifications = NotificationMutation::create(db, &notifications).await?;
+    if !deliver || notifications.is_empty() {
+        return Ok(());
+    }
+
+    let mut identity_ids = identities.values().cloned().collect::<Vec<_>>();
+    identity_ids.sort();
+    identity_ids.dedup();
+    let webhooks = WebhookQuery::find_enabled_by_identity_ids(db, identity_ids)
+        .await?
+        .into_iter()
+        .map(|webhook| (webhook.identity_id, webhook.id))
+        .collect::<HashMap<_, _>>();
+
+    let now = Utc::now().naive_utc();
+    let mut deliveries = vec![];
+    for notification in notifications.iter() {
+        let webhook_id = notification
+            .watchlist_address_id
+            .and_then(|id| identities.get(&id))
+            .and_then(|identity_id| webhooks.get(identity_id));
+        if let Some(webhook_id) = webhook_id {
+            deliveries.push(DeliveryModel {
+                id: 0,
+                notification_id: notification.id,
+                webhook_id: *webhook_id,
+                status: DELIVERY_PENDING.to_string(),
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                delivered_at: None,
+                inserted_at: now,
+                updated_at: now,
+            });
+        }
+    }
+    if !deliveries.is_empty() {
+        DeliveryMutation::create(db, &deliveries).await?;
+    }
+
+    Ok(())
+}
+
+/// the coin transfers of the succeeded transactions and internal transactions and the token
+/// transfers of the block, `token_types` maps the token contracts to their types.
+pub fn block_activities(
+    transactions: &[TransactionModel],
+    inner_txs: &[InnerTransactionModel],
+    token_transfers: &[TokenTransferModel],
+    token_types: &HashMap<Vec<u8>, String>,
+) -> Vec<Activity> {
+    let tx_fees = transactions
+        .iter()
+        .map(|tx| (tx.hash.clone(), tx_fee(tx)))
+        .collect::<HashMap<_, _>>();
+    let failed = transactions
+        .iter()
+        .filter(|tx| tx.status == Some(0) || tx.error.is_some())
+        .map(|tx| tx.hash.clone())
+        .collect::<Vec<_>>();
+
+    let mut activities = vec![];
+    for tx in transactions.iter() {
+        let Some(to) = tx.to_address_hash.as_ref() else {
+            continue;
+        };
+        if failed.contains(&tx.hash) || tx.value <= BigDecimal::from(0) {
+            continue;
+        }
+        activities.push(Activity {
+            r#type: COIN.to_string(),
+            method: "transfer".to_string(),
+            transaction_hash: tx.hash.clone(),
+            log_index: -1,
+            from: tx.from_address_hash.clone(),
+            to: to.clone(),
+            amount: to_decimal(&tx.value),
+            tx_fee: tx_fees.get(&tx.hash).cloned().flatten(),
+            subject: None,
+        });
+    }
+
+    for inner_tx in inner_txs.iter() {
+        // the first trace is the transaction itself
+        if inner_tx.index == 0
+            || inner_tx.error.is_some()
+            || inner_tx.value <= Decimal::ZERO
+            || failed.contains(&inner_tx.transaction_hash)
+            || !moves_value(inner_tx.call_type.as_deref())
+        {
+            continue;
+        }
+        let (Some(from), Some(to)) = (
+            inner_tx.from_address_hash.as_ref(),
+            inner_tx.to_address_hash.as_ref(),
+        ) else {
+            continue;
+        };
+        activities.push(Activity {
+            r#type: COIN.to_string(),
+            method: inner_tx.r#type.clone(),
+            transaction_hash: inner_tx.transaction_hash.clone(),
+            log_index: inner_tx.index,
+            from: from.clone(),
+            to: to.clone(),
+            amount: Some(inner_tx.value),
+            tx_fee: tx_fees.get(&inner_tx.transaction_hash).cloned().flatten(),
+            subject: None,
+        });
+    }
+
+    for transfer in token_transfers.iter() {
+        let Some(token_type) = token_types.get(&transfer.token_contract_address_hash) else {
+            continue;
+        };
+        activities.push(Activity {
+            r#type: token_type.clone(),
+            method: "transfer".to_string(),
+            transaction_hash: transfer.transaction_hash.clone(),
+            log_index: transfer.log_index,
+            from: transfer.from_address_hash.clone(),
+            to: transfer.to_address_hash.clone(),
+            amount: transfer.amount.as_ref().and_then(to_decimal),
+            tx_fee: tx_fees.get(&transfer.transaction_hash).cloned().flatten(),
+            subject: transfer.token_id.as_ref().map(|id| id.to_string()),
+        });
+    }
+
+    activities
+}
+
+/// a notification per watched address and direction of the activities it watches
+pub fn match_activities(
+    block_number: i64,
+    block_hash: &[u8],
+    activities: &[Activity],
+    watched: &[WatchlistAddressModel],
+) -> Vec<NotificationModel> {
+    let now = Utc::now().naive_utc();
+    let mut notifications = vec![];
+    for activity in activities.iter() {
+        for address in watched.iter() {
+            let Some(address_hash) = address.address_hash.as_ref() else {
+                continue;
+            };
+            for (direction, from) in [(OUTGOING, true), (INCOMING, false)] {
+                let target = if from { &activity.from } else { &activity.to };
+                if target != address_hash || !watches(address, &activity.r#type, from) {
+                    continue;
+                }
+                notifications.push(NotificationModel {
+                    id: 0,
+                    watchlist_address_id: Some(address.id),
+                    direction: Some(direction.to_string()),
+                    r#type: Some(activity.r#type.clone()),
+                    method: Some(activity.method.clone()),
+                    block_number: Some(block_number as i32),
+                    amount: activity.amount,
+                    tx_fee: activity.tx_fee,
+                    viewed_at: None,
+                    inserted_at: now,
+                    updated_at: now,
+                    name: address.name.clone(),
+                    subject: activity.subject.as_ref().map(|s| s.as_bytes().to_vec()),
+                    from_address_hash: Some(activity.from.clone()),
+                    to_address_hash: Some(activity.to.clone()),
+                    transaction_hash: Some(activity.transaction_hash.clone()),
+                    subject_hash: None,
+                    from_address_hash_hash: None,
+                    to_address_hash_hash: None,
+                    transaction_hash_hash: None,
+                    block_hash: Some(block_hash.to_vec()),
+                    log_index: Some(activity.log_index),
+                });
+            }
+        }
+    }
+
+    notifications
+}
+
+/// `output` flags watch the values sent by the address, `input` flags the received ones
+fn watches(address: &WatchlistAddressModel, r#type: &str, output: bool) -> bool {
+    let flag = match (r#type, output) {
+        (COIN, false) => address.watch_coin_input,
+        (COIN, true) => address.watch_coin_output,
+        (consts::ERC20, false) => address.watch_erc_20_input,
+        (consts::ERC20, true) => address.watch_erc_20_output,
+        (consts::ERC721, false) => address.watch_erc_721_input,
+        (consts::ERC721, true) => address.watch_erc_721_output,
+        (consts::ERC1155, false) => address.watch_erc_1155_input,
+        (consts::ERC1155, true) => address.watch_erc_1155_output,
+        _ => None,
+    };
+    flag.unwrap_or(false)
+}
+
+// delegate and static calls run in the context of the caller without moving its value
+fn moves_value(call_type: Option<&str>) -> bool {
+    !matches!(
+        call_type.map(|call_type| call_type.trim_matches('"')),
+        Some("delegatecall") | Some("staticcall") | Some("callcode")
+    )
+}
+
+fn tx_fee(tx: &TransactionModel) -> Option<Decimal> {
+    match (tx.gas_used, tx.gas_price) {
+        (Some(gas_used), Some(gas_price)) => gas_used.checked_mul(gas_price),
+        _ => None,
+    }
+}
+
+// values beyond the range of the notification amounts are left out
+fn to_decimal(value: &BigDecimal) -> Option<Decimal> {
+    Decimal::from_str(&value.to_string()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bigdecimal::BigDecimal;
+    use chrono::Utc;
+    use entities::{
+        account_watchlist_addresses::Model as WatchlistAddressModel,
+        internal_transactions::Model as InnerTransactionModel,
+        token_transfers::Model as TokenTransferModel, transactions::Model as TransactionModel,
+    };
+    use sea_orm::prelude::Decimal;
+
+    use super::{block_activities, match_activities, COIN, INCOMING, OUTGOING};
+    use crate::handler::token;
+
+    fn transaction(hash: u8, from: u8, to: u8, value: i64) -> TransactionModel {
+        TransactionModel {
+            gas_price: Some(Decimal::from(2)),
+            gas_used: Some(Decimal::from(21000)),
+            index: Some(0),
+            status: Some(1),
+            value: BigDecimal::from(value),
+            block_hash: Some(vec![9]),
+            block_number: Some(1),
+            to_address_hash: Some(vec![to]),
+            ..token::transaction(&[hash], &[from])
+        }
+    }
+
+    fn inner_tx(hash: u8, index: i32, from: u8, to: u8, value: i64) -> InnerTransactionModel {
+        InnerTransactionModel {
+            call_type: Some("\"call\"".to_string()),
+            created_contract_code: None,
+            error: None,
+            gas: None,
+            gas_used: None,
+            index,
+            init: None,
+            input: None,
+            output: None,
+            trace_address: vec![],
+            r#type: "call".to_string(),
+            value: Decimal::from(value),
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            created_contract_address_hash: None,
+            from_address_hash: Some(vec![from]),
+            to_address_hash: Some(vec![to]),
+            transaction_hash: vec![hash],
+            block_number: Some(1),
+            transaction_index: Some(0),
+            block_hash: vec![9],
+            block_index: index,
+        }
+    }
+
+    fn token_transfer(hash: u8, token: u8, from: u8, to: u8) -> TokenTransferModel {
+        TokenTransferModel {
+            transaction_hash: vec![hash],
+            amount: Some(BigDecimal::from(5)),
+            block_number: Some(1),
+            block_hash: vec![9],
+            ..token::token_transfer(&[token], &[from], &[to])
+        }
+    }
+
+    fn watched(id: i64, address: u8) -> WatchlistAddressModel {
+        WatchlistAddressModel {
+            id,
+            watchlist_id: Some(1),
+            watch_coin_input: Some(true),
+            watch_coin_output: Some(false),
+            watch_erc_20_input: Some(false),
+            watch_erc_20_output: Some(true),
+            watch_erc_721_input: None,
+            watch_erc_721_output: None,
+            watch_erc_1155_input: None,
+            watch_erc_1155_output: None,
+            notify_email: None,
+            notify_epns: None,
+            notify_feed: None,
+            notify_inapp: None,
+            inserted_at: Utc::now().naive_utc(),
+            updated_at: Utc::now().naive_utc(),
+            address_hash_hash: None,
+            name: Some(b"alice".to_vec()),
+            address_hash: Some(vec![address]),
+        }
+    }
+
+    #[test]
+    fn test_block_activities() {
+        let mut failed = transaction(3, 1, 2, 10);
+        failed.status = Some(0);
+        let transactions = vec![transaction(1, 1, 2, 10), transaction(2, 1, 2, 0), failed];
+        let mut delegated = inner_tx(1, 2, 2, 4, 7);
+        delegated.call_type = Some("\"delegatecall\"".to_string());
+        let inner_txs = vec![
+            inner_tx(1, 0, 1, 2, 10),
+            inner_tx(1, 1, 2, 3, 7),
+            delegated,
+            inner_tx(3, 1, 2, 3, 7),
+        ];
+        let transfers = vec![token_transfer(2, 8, 2, 1), token_transfer(2, 7, 2, 1)];
+        let token_types = HashMap::from([(vec![8], "ERC-20".to_string())]);
+
+        let activities = block_activities(&transactions, &inner_txs, &transfers, &token_types);
+        assert_eq!(
+            activities
+                .iter()
+                .map(|a| (a.r#type.as_str(), a.transaction_hash[0], a.from[0], a.to[0]))
+                .collect::<Vec<_>>(),
+            vec![(COIN, 1, 1, 2), (COIN, 1, 2, 3), ("ERC-20", 2, 2, 1)]
+        );
+        assert_eq!(activities[0].amount, Some(Decimal::from(10)));
+        assert_eq!(activities[0].tx_fee, Some(Decimal::from(42000)));
+        assert_eq!(activities[2].amount, Some(Decimal::from(5)));
+        assert_eq!(
+            activities.iter().map(|a| a.log_index).collect::<Vec<_>>(),
+            vec![-1, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_match_activities() {
+        let transactions = vec![transaction(1, 1, 2, 10)];
+        let transfers = vec![token_transfer(1, 8, 2, 1)];
+        let token_types = HashMap::from([(vec![8], "ERC-20".to_string())]);
+        let activities = block_activities(&transactions, &[], &transfers, &token_types);
+
+        // 2 watches the coins it receives and the erc-20 tokens it sends, 1 watches the same
+        let notifications =
+            match_activities(1, &[9], &activities, &[watched(10, 2), watched(11, 1)]);
+        assert_eq!(
+            notifications
+                .iter()
+                .map(|n| (
+                    n.watchlist_address_id.unwrap(),
+                    n.direction.clone().unwrap(),
+                    n.r#type.clone().unwrap()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (10, INCOMING.to_string(), COIN.to_string()),
+                (10, OUTGOING.to_string(), "ERC-20".to_string()),
+            ]
+        );
+        assert_eq!(notifications[0].name, Some(b"alice".to_vec()));
+        assert_eq!(notifications[0].transaction_hash, Some(vec![1]));
+        assert_eq!(notifications[0].block_number, Some(1));
+        assert_eq!(notifications[0].block_hash, Some(vec![9]));
+        assert_eq!(notifications[0].log_index, Some(-1));
+    }
+}
@@ -1 +1,2 @@
 pub mod token_balances;
+pub mod token_instance;
@@ -19,12 +19,11 @@ pub async fn fetch_token_balances_from_blockchain(
 ) -> Result<Vec<TokenBalanceModel>> {
     let mut fetched_token_balances = vec![];
     // let mut failed_token_balances = vec![];
-    for token_balance in token_balances.iter() {
-        let token_hash = chain_ident!(token_balance.token_contract_address_hash.clone());
-        let address_hash = chain_ident!(token_balance.address_hash.clone());
-        let req = TokenBalanceRequest {
-            token_hash,
-            address_hash,
+    let reqs = token_balances
+        .iter()
+        .map(|token_balance| TokenBalanceRequest {
+            token_hash: chain_ident!(token_balance.token_contract_address_hash.clone()),
+            address_hash: chain_ident!(token_balance.address_hash.clone()),
             block_number: Some(token_balance.block_number as u64),
             token_id: token_balance
                 .token_id
@@ -39,8 +38,12 @@ pub async fn fetch_token_balances_from_blockchain(
                     consts::ERC1155 => consts::TokenKind::ERC1155,
                     _ => consts::TokenKind::None,
                 }),
-        };
-        let result = balance_reader.token_balance_call_contract(req).await;
+        })
+        .collect::<Vec<_>>();
+
+    // ERC-1155 balances of the same token and block are read with one batch call
+    let results = balance_reader.get_balances_of(reqs, vec![]).await;
+    for (token_balance, result) in token_balances.iter().zip(results) {
         let mut item = token_balance.clone();
         item.value_fetched_at = Some(Utc::now().naive_utc());
         match result {
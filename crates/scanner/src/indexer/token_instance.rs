@@ -0,0 +1,326 @@
+use std::{net::IpAddr, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{header::LOCATION, redirect::Policy, Response, Url};
+use serde_json::Value;
+
+const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io/ipfs";
+// metadata documents larger than this are rejected
+const MAX_METADATA_SIZE: usize = 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+
+/// Fetches the document behind an http(s) url, tests plug in a local stub.
+#[async_trait]
+pub trait MetadataFetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// the urls come from the contracts, so every hop of a fetch must resolve to public addresses
+/// unless its host is trusted.
+pub struct HttpFetcher {
+    timeout: Duration,
+    trusted_hosts: Vec<String>,
+}
+
+impl HttpFetcher {
+    pub fn new(timeout: Duration) -> Self {
+        HttpFetcher {
+            timeout,
+            trusted_hosts: vec![],
+        }
+    }
+
+    /// a host reachable on any address, like the configured ipfs gateway
+    pub fn with_trusted_host(mut self, host: &str) -> Self {
+        self.trusted_hosts.push(host.to_lowercase());
+        self
+    }
+
+    // the client of a hop is pinned to the checked addresses of its host
+    async fn client_for(&self, url: &Url) -> Result<reqwest::Client> {
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("unsupported metadata url: {}", url);
+        }
+        let Some(host) = url.host_str() else {
+            bail!("metadata url without host: {}", url);
+        };
+        let builder = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .redirect(Policy::none());
+        if self.trusted_hosts.iter().any(|trusted| trusted == host) {
+            return Ok(builder.build()?);
+        }
+
+        if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse() {
+            if !is_public_ip(ip) {
+                bail!("metadata url on the non public address {}", ip);
+            }
+            return Ok(builder.build()?);
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs = tokio::net::lookup_host((host, port))
+            .await?
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            bail!("metadata host {} does not resolve", host);
+        }
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+            bail!(
+                "metadata host {} resolves to the non public address {}",
+                host,
+                addr.ip()
+            );
+        }
+        Ok(builder.resolve_to_addrs(host, &addrs).build()?)
+    }
+}
+
+#[async_trait]
+impl MetadataFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let mut url = Url::parse(url)?;
+        for _ in 0..=MAX_REDIRECTS {
+            let resp = self.client_for(&url).await?.get(url.clone()).send().await?;
+            if !resp.status().is_redirection() {
+                return read_body(resp.error_for_status()?).await;
+            }
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| anyhow!("redirect of {} without location", url))?;
+            url = url.join(location)?;
+        }
+        bail!("too many redirects fetching metadata")
+    }
+}
+
+async fn read_body(mut resp: Response) -> Result<Vec<u8>> {
+    if let Some(len) = resp.content_length() {
+        if len > MAX_METADATA_SIZE as u64 {
+            bail!("metadata of {} bytes is too large", len);
+        }
+    }
+    let mut body = vec![];
+    while let Some(chunk) = resp.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_METADATA_SIZE {
+            bail!(
+                "metadata of more than {} bytes is too large",
+                MAX_METADATA_SIZE
+            );
+        }
+    }
+    Ok(body)
+}
+
+// loopback, private, link-local, shared, unspecified and multicast addresses are refused
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segment = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segment & 0xfe00) == 0xfc00
+                || (segment & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// where the metadata of a token uri is read from
+#[derive(Debug, PartialEq)]
+pub enum MetadataSource {
+    Inline(Vec<u8>),
+    Url(String),
+}
+
+/// resolve `ipfs://` uris through the gateway and decode `data:` uris in place.
+pub fn resolve_uri(uri: &str, ipfs_gateway: Option<&str>) -> Result<MetadataSource> {
+    let uri = uri.trim();
+    if let Some(path) = uri.strip_prefix("ipfs://") {
+        let path = path.strip_prefix("ipfs/").unwrap_or(path);
+        let gateway = ipfs_gateway.unwrap_or(DEFAULT_IPFS_GATEWAY);
+        return Ok(MetadataSource::Url(format!(
+            "{}/{}",
+            gateway.trim_end_matches('/'),
+            path
+        )));
+    }
+
+    if let Some(data) = uri.strip_prefix("data:") {
+        let Some((media_type, payload)) = data.split_once(',') else {
+            bail!("malformed data uri");
+        };
+        if media_type.ends_with(";base64") {
+            return Ok(MetadataSource::Inline(STANDARD.decode(payload)?));
+        }
+        return Ok(MetadataSource::Inline(percent_decode(payload)));
+    }
+
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        return Ok(MetadataSource::Url(uri.to_string()));
+    }
+
+    Err(anyhow!("unsupported token uri: {}", uri))
+}
+
+/// read and parse the json metadata behind the token uri.
+pub async fn fetch_metadata(
+    fetcher: &dyn MetadataFetcher,
+    uri: &str,
+    ipfs_gateway: Option<&str>,
+) -> Result<Value> {
+    let body = match resolve_uri(uri, ipfs_gateway)? {
+        MetadataSource::Inline(body) => body,
+        MetadataSource::Url(url) => fetcher.fetch(&url).await?,
+    };
+
+    let metadata: Value = serde_json::from_slice(&body)?;
+    if !metadata.is_object() {
+        bail!("metadata is not a json object");
+    }
+    Ok(metadata)
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = input
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use super::{
+        fetch_metadata, is_public_ip, resolve_uri, HttpFetcher, MetadataFetcher, MetadataSource,
+    };
+
+    struct StubFetcher(HashMap<String, Vec<u8>>);
+
+    #[async_trait]
+    impl MetadataFetcher for StubFetcher {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+            self.0
+                .get(url)
+                .cloned()
+                .ok_or_else(|| anyhow!("404 not found: {}", url))
+        }
+    }
+
+    #[test]
+    fn test_resolve_uri() {
+        assert_eq!(
+            resolve_uri("ipfs://ipfs/QmCid/1.json", None).unwrap(),
+            MetadataSource::Url("https://ipfs.io/ipfs/QmCid/1.json".to_string())
+        );
+        assert_eq!(
+            resolve_uri("ipfs://QmCid", Some("http://localhost:8080/ipfs/")).unwrap(),
+            MetadataSource::Url("http://localhost:8080/ipfs/QmCid".to_string())
+        );
+        assert_eq!(
+            resolve_uri("data:application/json;base64,eyJhIjoxfQ==", None).unwrap(),
+            MetadataSource::Inline(br#"{"a":1}"#.to_vec())
+        );
+        assert_eq!(
+            resolve_uri("data:application/json;utf8,{%22a%22:1}", None).unwrap(),
+            MetadataSource::Inline(br#"{"a":1}"#.to_vec())
+        );
+        assert!(resolve_uri("ar://tx", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metadata() {
+        let fetcher = StubFetcher(HashMap::from([
+            (
+                "https://ipfs.io/ipfs/QmCid/1".to_string(),
+                br#"{"name":"One","image":"ipfs://QmImage"}"#.to_vec(),
+            ),
+            ("https://nft.test/2".to_string(), b"[1, 2]".to_vec()),
+        ]));
+
+        let metadata = fetch_metadata(&fetcher, "ipfs://QmCid/1", None)
+            .await
+            .unwrap();
+        assert_eq!(metadata, json!({"name": "One", "image": "ipfs://QmImage"}));
+        assert!(fetch_metadata(&fetcher, "https://nft.test/2", None)
+            .await
+            .is_err());
+        assert!(fetch_metadata(&fetcher, "https://nft.test/3", None)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_fetcher_refuses_private_hosts() {
+        let fetcher = HttpFetcher::new(Duration::from_secs(1));
+        for url in [
+            "http://169.254.169.254/latest/meta-data",
+            "http://localhost:8080/1.json",
+            "http://[::1]/1.json",
+            "file:///etc/passwd",
+        ] {
+            assert!(fetcher.fetch(url).await.is_err(), "{}", url);
+        }
+    }
+}
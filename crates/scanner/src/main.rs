@@ -1,20 +1,40 @@
 use clap::Parser;
+use config::chain::LogSink as LogSinkCfg;
 use config::{base::BaseConfig, Args, Config};
 use repo::orm::conn::connect_db;
 use scanner::{
-    contracts::{balance_reader::BalanceReader, erc20::IERC20Call},
-    evms::eth::EthCli,
+    contracts::{
+        balance_reader::BalanceReader, erc20::IERC20Call, multicall::Multicall, nft::INFTCall,
+    },
+    evms::{eth::EthCli, pool::RpcPool},
     handler::block::init_block,
+    indexer::token_instance::HttpFetcher,
     tasks::{
-        address::address_token_balance_task,
+        address::{address_recount_task, address_token_balance_task},
+        backfill::{backfill_task, gap_finder_task},
         block::handle_block_task,
-        token::{token_metadata_task, token_total_updater_task},
+        block_event::block_event_task,
+        coin_balance::coin_balance_task,
+        log_receiver::{
+            log_receiver_task, DbSink, KafkaSink, LogReceiverTask, LogSink, WebhookSink,
+        },
+        publisher::{KafkaBackend, LogBackend, Publisher, PublisherBackend},
+        rpc::rpc_health_task,
+        token::{token_holder_count_task, token_metadata_task},
+        token_instance::token_instance_task,
+        total_supply::{token_total_supply_task, TokenTotalSupplyOnDemand},
+        uncle::uncle_task,
+        watchlist::{watchlist_delivery_task, WebhookDeliverer},
     },
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+use tokio_util::sync::CancellationToken;
 use tracing::instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+// time the tasks get to finish their current work after a termination signal
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 // RUST_LOG=debug cargo run --package scanner
 #[instrument]
 fn main() {
@@ -28,9 +48,33 @@ fn main() {
     let args = Args::parse();
     let config = BaseConfig::load(args.config_path).unwrap();
     let chain = config.chain.unwrap();
-    let chain_rpc_url = &chain.url.as_str();
+    let kafka = config.kafka;
+    let chain_id = chain.chain_id.unwrap_or_default();
+    let backfill = chain.backfill.clone().unwrap_or_default();
+    let recount = chain.recount.clone();
+    let total_supply_ttl = chain.total_supply_ttl() as i64;
+    let ipfs_gateway = chain.ipfs_gateway.clone();
+    let metadata_timeout = Duration::from_secs(chain.rpc_timeout());
+    let multicall_cfg = chain.multicall.clone().unwrap_or_default();
+    let log_receiver = chain
+        .log_receiver
+        .as_ref()
+        .map(|cfg| (LogReceiverTask::new(&chain, cfg), cfg.clone()));
+    let watchlist = chain.watchlist.clone().unwrap_or_default();
+    let multicall_address = multicall_cfg
+        .address
+        .as_ref()
+        .map(|address| address.parse().expect("invalid multicall address"));
+    let rpc_pool =
+        RpcPool::new(&chain.endpoints(), chain.rpc_max_lag()).expect("invalid chain rpc url");
+    let eth_cli = EthCli::from_pool(&rpc_pool)
+        .with_retry(
+            chain.rpc_retries(),
+            Duration::from_secs(chain.rpc_timeout()),
+        )
+        .with_tracer(chain.tracer())
+        .with_new_heads(chain.ws_url.clone());
 
-    let rpc_url = Arc::new(chain_rpc_url.to_string());
     let db_cfg = config.database.unwrap();
     let scanner = tokio::runtime::Builder::new_multi_thread()
         .thread_name("scanner-runtime")
@@ -38,21 +82,143 @@ fn main() {
         .build()
         .unwrap();
 
-    scanner.spawn(async move {
+    let shutdown = CancellationToken::new();
+    let token = shutdown.clone();
+    let tasks = scanner.spawn(async move {
         let conn = connect_db(db_cfg.clone()).await.unwrap();
         let conn = Arc::new(conn);
-        let eth_cli = EthCli::new(rpc_url.clone().as_str());
+        let mut handles = vec![rpc_health_task(
+            rpc_pool.clone(),
+            chain.interval.max(1) * 5,
+            token.clone(),
+        )];
         let eth_cli = Arc::new(eth_cli);
-        init_block(eth_cli.clone(), conn.clone()).await;
+        if let Err(err) = init_block(eth_cli.clone(), conn.clone()).await {
+            tracing::error!(message = "init block", err = ?err);
+        }
+
+        // chain events are only logged if no kafka brokers are configured
+        let backend: Arc<dyn PublisherBackend> = match &kafka {
+            Some(kafka) => Arc::new(KafkaBackend::new(kafka).expect("invalid kafka config")),
+            None => Arc::new(LogBackend),
+        };
+        let publisher = Arc::new(
+            Publisher::new(backend, chain_id).with_topic_prefix(
+                kafka
+                    .and_then(|kafka| kafka.topic_prefix)
+                    .unwrap_or_default(),
+            ),
+        );
+
+        let multicall = Arc::new(Multicall::from_pool(
+            &rpc_pool,
+            multicall_address,
+            multicall_cfg.chunk_size(),
+        ));
+        handles.push(block_event_task(
+            publisher.clone(),
+            conn.clone(),
+            token.clone(),
+        ));
+
+        if let Some((task, cfg)) = log_receiver {
+            let sink: Arc<dyn LogSink> = match cfg.sink() {
+                LogSinkCfg::Db => Arc::new(DbSink),
+                LogSinkCfg::Kafka => Arc::new(KafkaSink::new(publisher.clone())),
+                LogSinkCfg::Webhook { url } => Arc::new(WebhookSink::new(url, metadata_timeout)),
+            };
+            handles.push(log_receiver_task(
+                eth_cli.clone(),
+                conn.clone(),
+                task,
+                sink,
+                cfg.interval(),
+                token.clone(),
+            ));
+        }
 
-        handle_block_task(eth_cli.clone(), conn.clone());
+        handles.push(watchlist_delivery_task(
+            conn.clone(),
+            Arc::new(WebhookDeliverer::new(&watchlist)),
+            watchlist,
+            token.clone(),
+        ));
 
-        let erc20_call = Arc::new(IERC20Call::new(rpc_url.as_str()));
-        token_metadata_task(erc20_call.clone(), conn.clone());
-        token_total_updater_task(eth_cli.clone(), erc20_call.clone(), conn.clone());
+        let erc20_call =
+            Arc::new(IERC20Call::from_pool(&rpc_pool).with_multicall(multicall.clone()));
+        let (total_supply_sender, total_supply_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let total_supply = Arc::new(TokenTotalSupplyOnDemand::new(
+            total_supply_ttl,
+            erc20_call.clone(),
+            publisher.clone(),
+            conn.clone(),
+        ));
+        handles.push(token_total_supply_task(
+            total_supply,
+            total_supply_receiver,
+            token.clone(),
+        ));
 
-        let reader = Arc::new(BalanceReader::new(rpc_url.as_str()));
-        address_token_balance_task(reader.clone(), conn.clone());
+        handles.push(handle_block_task(
+            eth_cli.clone(),
+            conn.clone(),
+            chain,
+            total_supply_sender,
+            token.clone(),
+        ));
+        handles.push(coin_balance_task(
+            eth_cli.clone(),
+            conn.clone(),
+            token.clone(),
+        ));
+        handles.push(uncle_task(eth_cli.clone(), conn.clone(), token.clone()));
+        if let Some(recount) = recount {
+            handles.push(address_recount_task(conn.clone(), recount, token.clone()));
+        }
+        handles.push(gap_finder_task(
+            conn.clone(),
+            backfill.chunk_size(),
+            token.clone(),
+        ));
+        handles.push(backfill_task(
+            eth_cli.clone(),
+            conn.clone(),
+            backfill,
+            token.clone(),
+        ));
+
+        handles.push(token_metadata_task(
+            erc20_call.clone(),
+            conn.clone(),
+            token.clone(),
+        ));
+
+        handles.push(token_holder_count_task(conn.clone(), token.clone()));
+        // a private ipfs gateway is the only private host the metadata is read from
+        let mut metadata_fetcher = HttpFetcher::new(metadata_timeout);
+        if let Some(host) = ipfs_gateway
+            .as_deref()
+            .and_then(|gateway| reqwest::Url::parse(gateway).ok())
+            .and_then(|gateway| gateway.host_str().map(str::to_string))
+        {
+            metadata_fetcher = metadata_fetcher.with_trusted_host(&host);
+        }
+        handles.push(token_instance_task(
+            Arc::new(INFTCall::from_pool(&rpc_pool)),
+            Arc::new(metadata_fetcher),
+            conn.clone(),
+            ipfs_gateway,
+            token.clone(),
+        ));
+
+        let reader = Arc::new(BalanceReader::from_pool(&rpc_pool).with_multicall(multicall));
+        handles.push(address_token_balance_task(
+            reader.clone(),
+            conn.clone(),
+            token.clone(),
+        ));
+
+        handles
     });
 
     // wait for SIGINT on the main thread
@@ -62,7 +228,19 @@ fn main() {
         .unwrap()
         .block_on(wait_termination());
 
-    drop(scanner);
+    // let every task finish its current unit of work, the rest is aborted with the runtime
+    tracing::info!("shutting down, waiting at most {:?}", SHUTDOWN_TIMEOUT);
+    shutdown.cancel();
+    let stopped = scanner.block_on(tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        if let Ok(handles) = tasks.await {
+            futures::future::join_all(handles).await;
+        }
+    }));
+    if stopped.is_err() {
+        tracing::warn!("shutdown timed out, aborting remaining tasks");
+    }
+
+    scanner.shutdown_background();
 }
 
 #[cfg(not(unix))]
@@ -1,13 +1,18 @@
 use crate::contracts::balance_reader::BalanceReader;
+use crate::handler::address::recount_addresses;
+use crate::handler::address_token_balance::save_fetched_token_balances;
 use crate::indexer::token_balances::fetch_token_balances_from_blockchain;
 use std::{sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Error};
-use repo::dal::token_balance::{Mutation, Query};
+use config::chain::Recount;
+use repo::dal::block::Query as BlockQuery;
+use repo::dal::token_balance::Query;
 use sea_orm::DatabaseConnection;
 use sea_orm::DbConn;
 
-use tokio::time::interval;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
 
 pub async fn handle_address_token_balance(
     reader: &BalanceReader,
@@ -22,27 +27,71 @@ pub async fn handle_address_token_balance(
     let res = fetch_token_balances_from_blockchain(reader, models)
         .await
         .unwrap();
-    for model in res.into_iter() {
-        if let Err(e) = Mutation::update_balance(conn, &model).await {
-            return Err(anyhow!(
-                "Handler address_token_balance: {:?}",
-                e.to_string()
-            ));
-        }
-        tracing::info!("update address token => model: {:?}", &model);
+    if let Err(e) = save_fetched_token_balances(conn, &res).await {
+        return Err(anyhow!(
+            "Handler address_token_balance: {:?}",
+            e.to_string()
+        ));
     }
+    tracing::info!("update address token balances => count: {}", res.len());
     Ok(())
 }
 
-pub fn address_token_balance_task(reader: Arc<BalanceReader>, conn: Arc<DatabaseConnection>) {
+pub fn address_token_balance_task(
+    reader: Arc<BalanceReader>,
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
     tokio::task::spawn(async move {
         let mut interval = interval(Duration::from_secs(300));
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
             match handle_address_token_balance(reader.as_ref(), conn.as_ref()).await {
                 Ok(_) => (),
                 Err(err) => tracing::error!(message = "token metadata task", err = ?err),
             };
         }
-    });
+        tracing::info!("address token balance task stopped");
+    })
+}
+
+/// rebuild the address counters of the configured height range once.
+pub fn address_recount_task(
+    conn: Arc<DatabaseConnection>,
+    cfg: Recount,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let to = match cfg.to {
+            Some(to) => to as i64,
+            None => match BlockQuery::find_max_number(conn.as_ref()).await {
+                Ok(max) => max,
+                Err(err) => {
+                    tracing::error!(message = "address recount task", err = ?err);
+                    return;
+                }
+            },
+        };
+
+        match recount_addresses(
+            conn.as_ref(),
+            cfg.from as i64,
+            to,
+            cfg.chunk_size(),
+            &shutdown,
+        )
+        .await
+        {
+            Ok(count) => tracing::info!(
+                "address recount from {} to {} finished, {} addresses",
+                cfg.from,
+                to,
+                count
+            ),
+            Err(err) => tracing::error!(message = "address recount task", err = ?err),
+        }
+    })
 }
@@ -0,0 +1,111 @@
+use std::{sync::Arc, time::Duration};
+
+use config::chain::Backfill;
+use futures::StreamExt;
+use repo::dal::{block::Query as BlockQuery, missing_block_range::Query as MissingRangeQuery};
+use sea_orm::DatabaseConnection;
+
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::evms::eth::EthCli;
+use crate::handler::backfill::{backfill_range, enqueue_missing};
+
+/// enqueue the configured historical range once, then keep working off `missing_block_ranges`
+/// with at most `concurrency` ranges in flight.
+pub fn backfill_task(
+    cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    cfg: Backfill,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        if let Some(from) = cfg.from {
+            let to = match cfg.to {
+                Some(to) => Some(to as i64),
+                None => BlockQuery::find_min_number(conn.as_ref())
+                    .await
+                    .ok()
+                    .map(|min| min - 1),
+            };
+            if let Some(to) = to {
+                match enqueue_missing(conn.as_ref(), from as i64, to, cfg.chunk_size()).await {
+                    Ok(count) => {
+                        tracing::info!("backfill from {} to {} enqueued {} ranges", from, to, count)
+                    }
+                    Err(err) => tracing::error!(message = "enqueue backfill range", err = ?err),
+                }
+            }
+        }
+
+        let deliver_watchlist = cfg.deliver_watchlist();
+        let mut interval = interval(Duration::from_secs(3));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            let ranges = match MissingRangeQuery::find_pending(
+                conn.as_ref(),
+                cfg.concurrency() as u64,
+            )
+            .await
+            {
+                Ok(ranges) => ranges,
+                Err(err) => {
+                    tracing::error!(message = "query missing block ranges", err = ?err);
+                    continue;
+                }
+            };
+
+            futures::stream::iter(ranges)
+                .for_each_concurrent(cfg.concurrency(), |range| {
+                    let (cli, conn, shutdown) = (cli.clone(), conn.clone(), shutdown.clone());
+                    async move {
+                        if let Err(err) = backfill_range(
+                            cli.as_ref(),
+                            conn.as_ref(),
+                            &range,
+                            deliver_watchlist,
+                            &shutdown,
+                        )
+                        .await
+                        {
+                            tracing::error!(message = "backfill range", id = range.id, err = ?err);
+                        }
+                    }
+                })
+                .await;
+        }
+        tracing::info!("backfill task stopped");
+    })
+}
+
+/// scan indexed blocks for holes left by crashes and enqueue them for the backfill task.
+pub fn gap_finder_task(
+    conn: Arc<DatabaseConnection>,
+    chunk_size: u64,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(60));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            let (Ok(min), Ok(max)) = (
+                BlockQuery::find_min_number(conn.as_ref()).await,
+                BlockQuery::find_max_number(conn.as_ref()).await,
+            ) else {
+                continue;
+            };
+
+            match enqueue_missing(conn.as_ref(), min, max, chunk_size).await {
+                Ok(0) => (),
+                Ok(count) => tracing::warn!("gap finder enqueued {} missing ranges", count),
+                Err(err) => tracing::error!(message = "gap finder task", err = ?err),
+            };
+        }
+    })
+}
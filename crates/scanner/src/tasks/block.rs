@@ -1,55 +1,203 @@
 use std::{sync::Arc, time::Duration};
 
-use repo::dal::block::Query;
-use sea_orm::DatabaseConnection;
+use common::consts;
+use config::chain::{Chain, FollowTag};
+use ethers::types::{BlockNumber, H256};
+use futures::StreamExt;
+use repo::dal::{block::Query, last_fetched_counter::Mutation as CounterMutation};
+use sea_orm::{prelude::Decimal, DatabaseConnection};
 
-use tokio::time::interval;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
 
+use crate::common::err::{RpcError, ScannerError};
 use crate::evms::eth::EthCli;
-use crate::handler::block::{handle_block, sync_to_db};
+use crate::handler::block::{fetch_blocks, handle_block, sync_to_db};
+use crate::handler::reorg::handle_reorg;
+use crate::tasks::total_supply::TotalSupplyChanged;
 
-pub fn handle_block_task(cli: Arc<EthCli>, conn: Arc<DatabaseConnection>) {
+const HEADS_FALLBACK_FACTOR: u32 = 10;
+// blocks fetched by one batch request
+const FETCH_CHUNK_SIZE: u64 = 5;
+
+/// poll the chain every `interval` seconds or wake up on `newHeads` if subscribed, while behind
+/// the followed head batches of `batch_size` blocks are indexed back to back. on shutdown the
+/// block being written is committed before the task exits. tokens minted or burned by the
+/// indexed blocks are sent to `total_supply`.
+pub fn handle_block_task(
+    cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    chain: Chain,
+    total_supply: UnboundedSender<TotalSupplyChanged>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
     tokio::task::spawn(async move {
-        let mut interval = interval(Duration::from_secs(3));
+        let poll = Duration::from_secs(chain.interval.max(1));
+        let mut interval = interval(poll);
+        let heads = cli.new_heads();
 
         loop {
-            interval.tick().await;
-            block_handler(cli.clone(), conn.clone()).await;
+            // driven by head notifications while subscribed, polling is only a safety net
+            let wait = async {
+                match &heads {
+                    Some(heads) if heads.is_live() => {
+                        heads.wait(poll * HEADS_FALLBACK_FACTOR).await
+                    }
+                    Some(heads) => heads.wait(poll).await,
+                    None => {
+                        interval.tick().await;
+                    }
+                }
+            };
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = wait => {}
+            }
+            finality_handler(cli.as_ref(), conn.as_ref(), &chain).await;
+            while !shutdown.is_cancelled() {
+                match block_handler(cli.clone(), conn.clone(), &chain, &total_supply, &shutdown)
+                    .await
+                {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(err) => {
+                        tracing::error!(message = "block handler", err = ?err);
+                        break;
+                    }
+                }
+            }
         }
-    });
+        tracing::info!("block task stopped");
+    })
 }
 
-pub async fn block_handler(cli: Arc<EthCli>, conn: Arc<DatabaseConnection>) {
-    let latest_block_number = cli.get_block_number().await;
-    if let Some(latest_block) = Query::select_latest(&conn).await.unwrap() {
-        if latest_block.number > latest_block_number as i64 {
-            tracing::info!(
-                "latestBlock.LatestBlockHeight: {} greater than latestBlockNumber: {}",
-                latest_block.number,
-                latest_block_number
-            );
-            return;
-        }
-        let current_number = latest_block.number as u64 + 1;
-        let current_block = cli.get_block_with_tx(current_number).await;
-        if current_block.is_none() {
-            return;
-        }
+/// the highest block the scanner may index, `confirmations` blocks below the followed tag.
+async fn followed_head(cli: &EthCli, chain: &Chain) -> Result<Option<u64>, RpcError> {
+    let followed = match chain.follow() {
+        FollowTag::Latest => Some(cli.get_block_number().await?),
+        FollowTag::Safe => cli.get_tag_block_number(BlockNumber::Safe).await,
+        FollowTag::Finalized => cli.get_tag_block_number(BlockNumber::Finalized).await,
+    };
+
+    Ok(followed.map(|number| number.saturating_sub(chain.confirmations())))
+}
+
+/// record the finalized height so the api can tell whether a block is final, chains without
+/// the `finalized` tag take blocks deeper than `confirmations` as final.
+pub async fn finality_handler(cli: &EthCli, conn: &DatabaseConnection, chain: &Chain) {
+    let finalized = match cli.get_tag_block_number(BlockNumber::Finalized).await {
+        Some(number) => number,
+        None if chain.confirmations() > 0 => match cli.get_block_number().await {
+            Ok(number) => number.saturating_sub(chain.confirmations()),
+            Err(err) => {
+                tracing::error!(message = "get block number", err = ?err);
+                return;
+            }
+        },
+        None => return,
+    };
+
+    if let Err(err) = CounterMutation::upsert(
+        conn,
+        consts::LAST_FINALIZED_BLOCK_NUMBER,
+        Decimal::from(finalized),
+    )
+    .await
+    {
+        tracing::error!(message = "update finalized block number", err = ?err);
+    }
+}
+
+/// index the next batch of blocks after the local head, returns true if the scanner is still
+/// behind the followed head. stops after the current block once `shutdown` is cancelled.
+pub async fn block_handler(
+    cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    chain: &Chain,
+    total_supply: &UnboundedSender<TotalSupplyChanged>,
+    shutdown: &CancellationToken,
+) -> anyhow::Result<bool> {
+    let Some(latest_block_number) = followed_head(cli.as_ref(), chain).await? else {
+        tracing::warn!("followed block tag {:?} not available", chain.follow());
+        return Ok(false);
+    };
+    let Some(latest_block) = Query::select_latest(&conn)
+        .await
+        .map_err(ScannerError::Query)?
+    else {
+        return Ok(false);
+    };
+    if latest_block.number > latest_block_number as i64 {
+        tracing::info!(
+            "latestBlock.LatestBlockHeight: {} greater than latestBlockNumber: {}",
+            latest_block.number,
+            latest_block_number
+        );
+        return Ok(false);
+    }
+
+    let from = latest_block.number as u64 + 1;
+    if from > latest_block_number {
+        return Ok(false);
+    }
+    let batch_size = chain.batch_size().max(1);
+    let to = latest_block_number.min(from + batch_size - 1);
+
+    // upcoming chunks are fetched in batch requests while the current blocks are written to db
+    let chunks = (from..=to)
+        .step_by(FETCH_CHUNK_SIZE as usize)
+        .map(|start| (start..=to.min(start + FETCH_CHUNK_SIZE - 1)).collect::<Vec<_>>());
+    let mut blocks = futures::stream::iter(chunks)
+        .map(|numbers| {
+            let cli = cli.clone();
+            async move { fetch_blocks(&cli, &numbers).await }
+        })
+        .buffered(2)
+        .map(|fetched| match fetched {
+            Ok(blocks) => futures::stream::iter(blocks.into_iter().map(Ok).collect::<Vec<_>>()),
+            Err(err) => futures::stream::iter(vec![Err(err)]),
+        })
+        .flatten();
 
-        let current_block = current_block.unwrap();
+    let mut parent_hash = latest_block.hash;
+    while let Some(fetched) = blocks.next().await {
+        if shutdown.is_cancelled() {
+            return Ok(false);
+        }
+        let Some((current_block, block_traces, recipts)) = fetched? else {
+            return Ok(false);
+        };
+        let current_number = current_block.number.unwrap().as_u64();
         tracing::info!(
             "get currentBlock blockNumber: {}, blockHash: {:#032x}, hash size: {}",
-            current_block.number.unwrap(),
+            current_number,
             current_block.hash.unwrap(),
             current_block.hash.unwrap().as_bytes().to_vec().len(),
         );
 
-        let block_traces = cli.trace_block(current_number).await;
-        let recipts = cli.get_block_receipt(current_number).await;
-        let handle_models = handle_block(&current_block, &block_traces, &recipts)
-            .await
-            .unwrap();
+        // the chain has switched branch under us, roll back to the common ancestor and
+        // re-index the canonical branch on the following iterations.
+        if current_block.parent_hash.as_bytes() != parent_hash.as_slice() {
+            tracing::warn!(
+                "parent hash of block {} mismatch local head {:#032x}",
+                current_number,
+                H256::from_slice(&parent_hash),
+            );
+            handle_reorg(&cli, &conn, &current_block).await?;
+            return Ok(true);
+        }
 
-        sync_to_db(&conn, handle_models).await.unwrap();
+        parent_hash = current_block.hash.unwrap().as_bytes().to_vec();
+        let handle_models = handle_block(&conn, &current_block, &block_traces, &recipts).await?;
+        let changed = TotalSupplyChanged {
+            block_number: handle_models.block_number(),
+            contracts: handle_models.total_supply_changed_tokens(),
+        };
+        sync_to_db(&conn, handle_models, true).await?;
+        if !changed.contracts.is_empty() && total_supply.send(changed).is_err() {
+            tracing::warn!("total supply receiver dropped");
+        }
     }
+
+    Ok(to < latest_block_number)
 }
@@ -0,0 +1,81 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Error};
+use bigdecimal::ToPrimitive;
+use common::chain_ident;
+use entities::block_events::Model as BlockEventModel;
+use repo::dal::{
+    block_event::Query as BlockEventQuery,
+    last_fetched_counter::{Mutation as CounterMutation, Query as CounterQuery},
+};
+use sea_orm::{prelude::Decimal, DatabaseConnection, DbConn};
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use super::publisher::{BroadcastType, Event, Publisher};
+
+/// the sequence of the last published block event
+pub const BLOCK_EVENTS_CURSOR: &str = "block_events_published_sequence";
+// all block events share the partition key to stay in order within their topics
+const BLOCK_EVENTS_KEY: &str = "blocks";
+const BLOCK_EVENTS_BATCH: u64 = 100;
+
+/// publish the block event log from the persisted cursor on. the cursor moves after every
+/// acknowledged event, events are published again if the task stops in between. consumers
+/// detect gaps by the sequence and replay by moving the cursor back.
+pub fn block_event_task(
+    publisher: Arc<Publisher>,
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            loop {
+                match handle_block_events(publisher.as_ref(), conn.as_ref()).await {
+                    Ok(published) if published as u64 == BLOCK_EVENTS_BATCH => continue,
+                    Ok(_) => break,
+                    Err(err) => {
+                        tracing::error!(message = "block event task", err = ?err);
+                        break;
+                    }
+                }
+            }
+        }
+        tracing::info!("block event task stopped");
+    })
+}
+
+pub async fn handle_block_events(publisher: &Publisher, conn: &DbConn) -> Result<usize, Error> {
+    let cursor = CounterQuery::find_by_type(conn, BLOCK_EVENTS_CURSOR)
+        .await
+        .map_err(|e| anyhow!("Handler block events: {:?}", e.to_string()))?
+        .and_then(|counter| counter.value)
+        .and_then(|value| value.to_i64())
+        .unwrap_or(0);
+
+    let events = BlockEventQuery::find_after(conn, cursor, BLOCK_EVENTS_BATCH)
+        .await
+        .map_err(|e| anyhow!("Handler block events: {:?}", e.to_string()))?;
+    for event in events.iter() {
+        publisher
+            .broadcast(vec![to_event(event)], BroadcastType::None)
+            .await?;
+        CounterMutation::upsert(conn, BLOCK_EVENTS_CURSOR, Decimal::from(event.sequence))
+            .await
+            .map_err(|e| anyhow!("Handler block events cursor: {:?}", e.to_string()))?;
+    }
+
+    Ok(events.len())
+}
+
+fn to_event(model: &BlockEventModel) -> Event {
+    Event::new(&model.event_type, model.payload.clone())
+        .with_key(BLOCK_EVENTS_KEY.to_string())
+        .with_sequence(model.sequence)
+        .with_block(model.block_number, Some(chain_ident!(&model.block_hash)))
+}
@@ -0,0 +1,39 @@
+use std::{sync::Arc, time::Duration};
+
+use sea_orm::DatabaseConnection;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::evms::eth::EthCli;
+use crate::handler::coin_balance::fetch_coin_balances;
+
+const COIN_BALANCE_BATCH_SIZE: u64 = 100;
+
+/// fetch the coin balances queued by the block task, back to back while the queue is full.
+pub fn coin_balance_task(
+    cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(5));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            while !shutdown.is_cancelled() {
+                match fetch_coin_balances(cli.as_ref(), conn.as_ref(), COIN_BALANCE_BATCH_SIZE)
+                    .await
+                {
+                    Ok(count) if count as u64 == COIN_BALANCE_BATCH_SIZE => continue,
+                    Ok(_) => break,
+                    Err(err) => {
+                        tracing::error!(message = "coin balance task", err = ?err);
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
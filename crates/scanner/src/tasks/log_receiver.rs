@@ -0,0 +1,409 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use config::chain::{Chain, LogReceiver};
+use entities::log_receiver_logs::Model as LogReceiverLogModel;
+use repo::dal::{
+    log_receiver_chain::Mutation as CursorMutation,
+    log_receiver_contract::Mutation as ContractMutation,
+    log_receiver_log::Mutation as LogReceiverLogMutation,
+};
+use sea_orm::{DatabaseConnection, DatabaseTransaction, DbConn, TransactionTrait};
+use serde_json::json;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use super::publisher::{BroadcastType, Event, Publisher};
+use crate::{
+    cache::log_receiver::{ContractAddrCache, LiveContractAddrCache},
+    common::err::ScannerError,
+    evms::eth::{EthCli, MyLog},
+    handler::log_receiver::{fetch_contract_logs, log_scanner_current_height},
+};
+
+pub const CONTRACT_LOGS: &str = "contract_logs";
+
+/// Delivers the logs of a scanned range. The cursor is moved in `txn` after the delivery, a
+/// sink writing into `txn` is delivered exactly once, the others at least once.
+#[async_trait]
+pub trait LogSink: Send + Sync {
+    async fn deliver(
+        &self,
+        txn: &DatabaseTransaction,
+        task_name: &str,
+        logs: &[MyLog],
+    ) -> Result<()>;
+}
+
+/// publishes a "contract_logs" event per transaction keyed by the task, so they stay in order.
+pub struct KafkaSink {
+    publisher: Arc<Publisher>,
+}
+
+impl KafkaSink {
+    pub fn new(publisher: Arc<Publisher>) -> Self {
+        KafkaSink { publisher }
+    }
+}
+
+#[async_trait]
+impl LogSink for KafkaSink {
+    async fn deliver(
+        &self,
+        _txn: &DatabaseTransaction,
+        task_name: &str,
+        logs: &[MyLog],
+    ) -> Result<()> {
+        let mut events = vec![];
+        for log in logs.iter() {
+            events.push(
+                Event::new(CONTRACT_LOGS, serde_json::to_value(log)?)
+                    .with_key(task_name.to_string())
+                    .with_block(
+                        log.block_number.as_u64() as i64,
+                        Some(format!("{:?}", log.block_hash)),
+                    ),
+            );
+        }
+        self.publisher.broadcast(events, BroadcastType::None).await
+    }
+}
+
+/// posts `{"task_name", "logs"}` of a range, any status but 2xx fails the delivery.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        WebhookSink {
+            client: reqwest::Client::builder().timeout(timeout).build().unwrap(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl LogSink for WebhookSink {
+    async fn deliver(
+        &self,
+        _txn: &DatabaseTransaction,
+        task_name: &str,
+        logs: &[MyLog],
+    ) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&json!({ "task_name": task_name, "logs": logs }))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map(|_| ())
+            .map_err(|err| anyhow!("deliver logs to {}: {}", self.url, err))
+    }
+}
+
+/// keeps the logs in `log_receiver_logs` together with the cursor.
+pub struct DbSink;
+
+#[async_trait]
+impl LogSink for DbSink {
+    async fn deliver(
+        &self,
+        txn: &DatabaseTransaction,
+        task_name: &str,
+        logs: &[MyLog],
+    ) -> Result<()> {
+        let models = logs
+            .iter()
+            .map(|log| to_log_model(task_name, log))
+            .collect::<Result<Vec<_>>>()?;
+        LogReceiverLogMutation::create(txn, &models)
+            .await
+            .map_err(|err| {
+                ScannerError::Create {
+                    src: "create log receiver logs".to_string(),
+                    err,
+                }
+                .into()
+            })
+    }
+}
+
+fn to_log_model(task_name: &str, log: &MyLog) -> Result<LogReceiverLogModel> {
+    Ok(LogReceiverLogModel {
+        id: 0,
+        task_name: task_name.to_string(),
+        block_number: log.block_number.as_u64() as i64,
+        block_hash: log.block_hash.as_bytes().to_vec(),
+        transaction_index: log.transaction_index.as_u64() as i64,
+        to_address_hash: log.to.as_bytes().to_vec(),
+        log_details: serde_json::to_value(&log.log_details)?,
+        inserted_at: Utc::now().naive_utc(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct LogReceiverTask {
+    pub task_name: String,
+    pub chain_name: String,
+    pub chain_id: i64,
+    pub from: u64,
+    pub range: u64,
+    pub confirmations: u64,
+}
+
+impl LogReceiverTask {
+    pub fn new(chain: &Chain, cfg: &LogReceiver) -> Self {
+        let chain_id = chain.chain_id.unwrap_or_default();
+        LogReceiverTask {
+            task_name: cfg
+                .task_name
+                .clone()
+                .unwrap_or_else(|| format!("{}:{}", chain.chain_name, chain_id)),
+            chain_name: chain.chain_name.clone(),
+            chain_id: chain_id as i64,
+            from: cfg.from.unwrap_or_default(),
+            range: cfg.range(),
+            confirmations: chain.confirmations(),
+        }
+    }
+}
+
+/// scan the logs of the registered contracts range by range until the confirmed head, the
+/// contracts are reloaded once the admin api changed them.
+pub fn log_receiver_task(
+    eth_cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    task: LogReceiverTask,
+    sink: Arc<dyn LogSink>,
+    interval_secs: u64,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut contracts = LiveContractAddrCache::new(&task.chain_name, task.chain_id);
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            while !shutdown.is_cancelled() {
+                match handle_log_receiver(
+                    eth_cli.as_ref(),
+                    conn.as_ref(),
+                    &task,
+                    &mut contracts,
+                    sink.as_ref(),
+                )
+                .await
+                {
+                    Ok(true) => break,
+                    Ok(false) => continue,
+                    Err(err) => {
+                        tracing::error!(message = "log receiver task", task = task.task_name, err = ?err);
+                        break;
+                    }
+                }
+            }
+        }
+        tracing::info!("log receiver task {} stopped", task.task_name);
+    })
+}
+
+/// deliver the next range and move the cursor past it, then backfill a range of every contract
+/// registered with a start height. true once the confirmed head is reached and nothing is left
+/// to backfill.
+pub async fn handle_log_receiver(
+    eth_cli: &EthCli,
+    conn: &DbConn,
+    task: &LogReceiverTask,
+    contracts: &mut LiveContractAddrCache,
+    sink: &dyn LogSink,
+) -> Result<bool> {
+    let head = eth_cli
+        .get_block_number()
+        .await?
+        .saturating_sub(task.confirmations);
+    let from = log_scanner_current_height(conn, &task.task_name, &task.chain_name, task.from as i64)
+        .await
+        .map_err(ScannerError::Query)? as u64;
+
+    contracts.refresh(conn).await.map_err(ScannerError::Query)?;
+    // the cursor takes new contracts over from its current height, the blocks before are
+    // left to their backfill
+    let taken_over = contracts
+        .cache()
+        .contracts()
+        .filter(|contract| contract.start_height.is_some() && contract.end_height.is_none())
+        .map(|contract| contract.id)
+        .collect::<Vec<_>>();
+    for id in taken_over {
+        ContractMutation::set_end_height(conn, id, from as i64)
+            .await
+            .map_err(|e| ScannerError::Update {
+                src: "set log receiver contract end height".to_string(),
+                err: e,
+            })?;
+        contracts.invalidate();
+    }
+    contracts.refresh(conn).await.map_err(ScannerError::Query)?;
+
+    let mut caught_up = from > head;
+    if !caught_up {
+        let to = head.min(from + task.range - 1);
+        deliver_range(
+            eth_cli,
+            conn,
+            task,
+            &task.task_name,
+            contracts.cache(),
+            sink,
+            from,
+            to,
+        )
+        .await?;
+        caught_up = to >= head;
+    }
+
+    let mut backfilled = true;
+    let backfills = contracts
+        .cache()
+        .contracts()
+        .filter_map(
+            |contract| match (contract.start_height, contract.end_height) {
+                (Some(start), Some(end)) if start < end => Some((contract.clone(), start, end)),
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+    for (contract, start, end) in backfills {
+        let task_name = format!("{}#{}", task.task_name, contract.id);
+        let from = log_scanner_current_height(conn, &task_name, &task.chain_name, start)
+            .await
+            .map_err(ScannerError::Query)?;
+        if from >= end {
+            continue;
+        }
+        let to = (end - 1).min(from + task.range as i64 - 1);
+
+        let mut cache = ContractAddrCache::new();
+        cache.insert(contract.cache_key(), contract);
+        deliver_range(
+            eth_cli,
+            conn,
+            task,
+            &task_name,
+            &cache,
+            sink,
+            from as u64,
+            to as u64,
+        )
+        .await?;
+        backfilled &= to + 1 >= end;
+    }
+
+    Ok(caught_up && backfilled)
+}
+
+/// deliver the logs of `[from, to]` and move the cursor of `cursor_name` past them
+#[allow(clippy::too_many_arguments)]
+async fn deliver_range(
+    eth_cli: &EthCli,
+    conn: &DbConn,
+    task: &LogReceiverTask,
+    cursor_name: &str,
+    contracts: &ContractAddrCache,
+    sink: &dyn LogSink,
+    from: u64,
+    to: u64,
+) -> Result<()> {
+    let logs = fetch_contract_logs(
+        eth_cli,
+        contracts,
+        &task.chain_name,
+        task.chain_id,
+        from,
+        to,
+    )
+    .await?;
+
+    let txn = conn.begin().await?;
+    if !logs.is_empty() {
+        if let Err(err) = sink.deliver(&txn, &task.task_name, &logs).await {
+            txn.rollback().await?;
+            return Err(err);
+        }
+    }
+    match CursorMutation::advance_height(&txn, cursor_name, from as i64, to as i64 + 1).await {
+        Ok(true) => {}
+        Ok(false) => {
+            txn.rollback().await?;
+            bail!(
+                "log receiver {} cursor moved by another runner",
+                cursor_name
+            );
+        }
+        Err(e) => {
+            txn.rollback().await?;
+            bail!(ScannerError::Update {
+                src: "advance log receiver height".to_string(),
+                err: e
+            });
+        }
+    }
+    txn.commit().await?;
+
+    tracing::debug!(
+        "log receiver {} delivered {} transactions of blocks {} to {}",
+        cursor_name,
+        logs.len(),
+        from,
+        to
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, Bytes, H256, U256, U64};
+
+    use super::to_log_model;
+    use crate::evms::eth::{LogDetail, MyLog};
+
+    #[test]
+    fn test_to_log_model() {
+        let log = MyLog {
+            log_details: vec![LogDetail {
+                address: Address::from_low_u64_be(1),
+                topics: vec![H256::from_low_u64_be(2)],
+                data: Bytes::default(),
+                transaction_log_index: None,
+                log_index: Some(U256::from(3)),
+                log_type: None,
+                removed: Some(false),
+            }],
+            block_hash: H256::from_low_u64_be(4),
+            block_number: U64::from(5),
+            block_timestamp: U256::from(6),
+            transaction_index: U64::from(7),
+            to: Address::from_low_u64_be(8),
+        };
+
+        let model = to_log_model("eth:5", &log).unwrap();
+        assert_eq!(model.task_name, "eth:5");
+        assert_eq!(model.block_number, 5);
+        assert_eq!(
+            model.block_hash,
+            H256::from_low_u64_be(4).as_bytes().to_vec()
+        );
+        assert_eq!(model.transaction_index, 7);
+        assert_eq!(
+            model.to_address_hash,
+            Address::from_low_u64_be(8).as_bytes().to_vec()
+        );
+        assert_eq!(model.log_details[0]["logIndex"], "0x3");
+    }
+}
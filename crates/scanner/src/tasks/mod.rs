@@ -1,5 +1,13 @@
 pub mod address;
+pub mod backfill;
 pub mod block;
+pub mod block_event;
+pub mod coin_balance;
+pub mod log_receiver;
 pub mod publisher;
+pub mod rpc;
 pub mod token;
+pub mod token_instance;
 pub mod total_supply;
+pub mod uncle;
+pub mod watchlist;
@@ -1,10 +1,19 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::kafka::Kafka;
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
     fmt::{self, Display, Formatter},
-    sync::mpsc::Sender,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BroadcastType {
     OnDamend,
     None,
@@ -23,19 +32,175 @@ impl Display for BroadcastType {
     }
 }
 
+/// an event to publish, `key` picks the partition so the events of an address stay in order.
+/// block events carry the `sequence` of the block event log.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub event_type: String,
+    pub key: Option<String>,
+    pub sequence: Option<i64>,
+    pub block_number: Option<i64>,
+    pub block_hash: Option<String>,
+    pub payload: Value,
+}
+
+impl Event {
+    pub fn new(event_type: &str, payload: Value) -> Self {
+        Event {
+            event_type: event_type.to_string(),
+            key: None,
+            sequence: None,
+            block_number: None,
+            block_hash: None,
+            payload,
+        }
+    }
+
+    pub fn with_key(mut self, key: String) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_sequence(mut self, sequence: i64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    pub fn with_block(mut self, block_number: i64, block_hash: Option<String>) -> Self {
+        self.block_number = Some(block_number);
+        self.block_hash = block_hash;
+        self
+    }
+}
+
+/// the json document consumers receive for every event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub event_type: String,
+    pub broadcast_type: BroadcastType,
+    pub chain_id: u64,
+    pub sequence: Option<i64>,
+    pub block_number: Option<i64>,
+    pub block_hash: Option<String>,
+    pub payload: Value,
+}
+
+/// Delivers the encoded envelopes, `publish` returns once the message is acknowledged.
+#[async_trait]
+pub trait PublisherBackend: Send + Sync {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: &[u8]) -> Result<()>;
+}
+
+pub struct KafkaBackend {
+    producer: FutureProducer,
+    timeout: Duration,
+}
+
+impl KafkaBackend {
+    pub fn new(kafka: &Kafka) -> Result<Self> {
+        let mut client_config = ClientConfig::new();
+        client_config
+            .set("bootstrap.servers", kafka.brokers_to_str())
+            .set("acks", "all")
+            .set(
+                "message.timeout.ms",
+                (kafka.message_timeout() * 1000).to_string(),
+            );
+        if let Some(log_level) = &kafka.log_level {
+            client_config.set("log_level", log_level);
+        }
+
+        let producer = client_config
+            .create()
+            .map_err(|err| anyhow!("create kafka producer: {}", err))?;
+        Ok(KafkaBackend {
+            producer,
+            timeout: Duration::from_secs(kafka.message_timeout()),
+        })
+    }
+}
+
+#[async_trait]
+impl PublisherBackend for KafkaBackend {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: &[u8]) -> Result<()> {
+        let mut record = FutureRecord::<str, [u8]>::to(topic).payload(payload);
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+
+        self.producer
+            .send(record, self.timeout)
+            .await
+            .map(|_| ())
+            .map_err(|(err, _)| anyhow!("publish to kafka topic {}: {}", topic, err))
+    }
+}
+
+/// logs the events, used when no kafka brokers are configured.
+pub struct LogBackend;
+
+#[async_trait]
+impl PublisherBackend for LogBackend {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: &[u8]) -> Result<()> {
+        tracing::debug!(
+            "chain event: topic {}, key {:?}, {}",
+            topic,
+            key,
+            String::from_utf8_lossy(payload)
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub key: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+/// keeps the published messages in memory for tests.
+#[derive(Default)]
+pub struct MemoryBackend {
+    messages: Mutex<Vec<Message>>,
+}
+
+impl MemoryBackend {
+    pub fn messages(&self) -> Vec<Message> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl PublisherBackend for MemoryBackend {
+    async fn publish(&self, topic: &str, key: Option<&str>, payload: &[u8]) -> Result<()> {
+        self.messages.lock().unwrap().push(Message {
+            topic: topic.to_string(),
+            key: key.map(|key| key.to_string()),
+            payload: payload.to_vec(),
+        });
+        Ok(())
+    }
+}
+
 pub struct Publisher {
     allowed_events: Vec<String>,
-    sender: Sender<String>,
+    chain_id: u64,
+    topic_prefix: String,
+    backend: Arc<dyn PublisherBackend>,
 }
 
 impl Publisher {
-    pub fn new(sender: Sender<String>) -> Self {
+    pub fn new(backend: Arc<dyn PublisherBackend>, chain_id: u64) -> Self {
         let allowed_events = vec![
             "addresses".to_string(),
             "address_coin_balances".to_string(),
             "address_token_balances".to_string(),
             "address_current_token_balances".to_string(),
             "blocks".to_string(),
+            "contract_logs".to_string(),
+            "block_added".to_string(),
+            "block_removed".to_string(),
             "block_rewards".to_string(),
             "internal_transactions".to_string(),
             "last_block_number".to_string(),
@@ -49,26 +214,96 @@ impl Publisher {
 
         Publisher {
             allowed_events,
-            sender,
+            chain_id,
+            topic_prefix: String::new(),
+            backend,
         }
     }
 
-    pub async fn broadcast(&self, data: Vec<(String, String)>, broadcast_type: BroadcastType) {
-        for (event_type, event_data) in data {
-            if self.allowed_events.contains(&event_type) {
-                self.send_data(&event_type, broadcast_type.clone(), &event_data);
+    /// topics are named `<prefix><event type>`
+    pub fn with_topic_prefix(mut self, topic_prefix: String) -> Self {
+        self.topic_prefix = topic_prefix;
+        self
+    }
+
+    /// publish the allowed events in order, stops at the first event which is not acknowledged.
+    pub async fn broadcast(&self, events: Vec<Event>, broadcast_type: BroadcastType) -> Result<()> {
+        for event in events {
+            if self.allowed_events.contains(&event.event_type) {
+                self.send_data(event, broadcast_type.clone()).await?;
             }
         }
+        Ok(())
     }
 
-    pub fn broadcast_single(&self, event_type: String) {
+    pub async fn broadcast_single(&self, event_type: String) -> Result<()> {
         if self.allowed_events.contains(&event_type) {
-            self.send_data(&event_type, BroadcastType::None, "");
+            self.send_data(Event::new(&event_type, Value::Null), BroadcastType::None)
+                .await?;
         }
+        Ok(())
     }
 
-    fn send_data(&self, event_type: &str, broadcast_type: BroadcastType, event_data: &str) {
-        let message = format!("{} {} {}", event_type, broadcast_type, event_data);
-        self.sender.send(message).unwrap();
+    async fn send_data(&self, event: Event, broadcast_type: BroadcastType) -> Result<()> {
+        let topic = format!("{}{}", self.topic_prefix, event.event_type);
+        let envelope = Envelope {
+            event_type: event.event_type,
+            broadcast_type,
+            chain_id: self.chain_id,
+            sequence: event.sequence,
+            block_number: event.block_number,
+            block_hash: event.block_hash,
+            payload: event.payload,
+        };
+        let payload = serde_json::to_vec(&envelope)?;
+        self.backend
+            .publish(&topic, event.key.as_deref(), &payload)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{BroadcastType, Envelope, Event, MemoryBackend, Publisher};
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let backend = Arc::new(MemoryBackend::default());
+        let publisher = Publisher::new(backend.clone(), 5).with_topic_prefix("soler.".to_string());
+
+        publisher
+            .broadcast(
+                vec![
+                    Event::new("token_total_supply", json!({"total_supply": "10"}))
+                        .with_key("0x01".to_string())
+                        .with_block(100, Some("0xaa".to_string())),
+                    Event::new("unknown", json!({})),
+                ],
+                BroadcastType::OnDamend,
+            )
+            .await
+            .unwrap();
+
+        let messages = backend.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].topic, "soler.token_total_supply");
+        assert_eq!(messages[0].key, Some("0x01".to_string()));
+        let envelope: Envelope = serde_json::from_slice(&messages[0].payload).unwrap();
+        assert_eq!(
+            envelope,
+            Envelope {
+                event_type: "token_total_supply".to_string(),
+                broadcast_type: BroadcastType::OnDamend,
+                chain_id: 5,
+                sequence: None,
+                block_number: Some(100),
+                block_hash: Some("0xaa".to_string()),
+                payload: json!({"total_supply": "10"}),
+            }
+        );
     }
 }
@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::evms::pool::RpcPool;
+
+/// probe every endpoint of the pool, lagging or failing endpoints are skipped until they recover.
+pub fn rpc_health_task(
+    pool: RpcPool,
+    interval_secs: u64,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            pool.health_check().await;
+        }
+    })
+}
@@ -1,5 +1,4 @@
 use crate::contracts::erc20::IERC20Call;
-use crate::evms::eth::EthCli;
 use std::{str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Error};
@@ -9,7 +8,10 @@ use repo::dal::token::{Mutation, Query};
 use sea_orm::DatabaseConnection;
 use sea_orm::{prelude::Decimal, DbConn};
 
-use tokio::time::interval;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+const HOLDER_COUNT_PAGE_SIZE: u64 = 500;
 
 // all metadata failed then update skip metadata
 // or catalog will be set ture
@@ -73,63 +75,77 @@ pub async fn handle_metadata(erc20_call: &IERC20Call, conn: &DbConn) -> Result<(
     Ok(())
 }
 
-pub fn token_metadata_task(erc20_call: Arc<IERC20Call>, conn: Arc<DatabaseConnection>) {
+pub fn token_metadata_task(
+    erc20_call: Arc<IERC20Call>,
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
     tokio::task::spawn(async move {
         let mut interval = interval(Duration::from_secs(300));
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
             match handle_metadata(erc20_call.as_ref(), conn.as_ref()).await {
                 Ok(_) => (),
                 Err(err) => tracing::error!(message = "token metadata task", err = ?err),
             };
         }
-    });
+        tracing::info!("token metadata task stopped");
+    })
 }
 
-// TODO use channel to receive contranct transfer action and then update contract's total supply
-pub fn token_total_updater_task(cli: Arc<EthCli>, erc20_call: Arc<IERC20Call>, conn: Arc<DbConn>) {
+/// recompute the holder counts page by page, the incremental counts drift on reorgs and for
+/// ERC-1155 holders of several ids.
+pub fn token_holder_count_task(
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
     tokio::task::spawn(async move {
-        let mut interval = interval(Duration::from_secs(300));
+        let mut interval = interval(Duration::from_secs(600));
         loop {
-            interval.tick().await;
-            match handle_token_total_supply(cli.clone(), erc20_call.clone(), conn.clone()).await {
-                Ok(_) => (),
-                Err(err) => tracing::error!(message = "token total supply task", err = ?err),
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            match handle_holder_counts(conn.as_ref(), &shutdown).await {
+                Ok(drifted) => tracing::info!("reconciled holder count of {} tokens", drifted),
+                Err(err) => tracing::error!(message = "token holder count task", err = ?err),
             };
         }
-    });
+        tracing::info!("token holder count task stopped");
+    })
 }
 
-pub async fn handle_token_total_supply(
-    cli: Arc<EthCli>,
-    erc20_call: Arc<IERC20Call>,
-    conn: Arc<DbConn>,
-) -> Result<(), Error> {
-    let block_number = cli.get_block_number().await;
-    match Query::filter_not_skip_metadata(conn.as_ref(), block_number as i64, None).await {
-        Ok(models) => {
-            for mut model in models.into_iter() {
-                let contract_addr = chain_ident!(&model.contract_address_hash);
-                if let Ok(total_supply) = erc20_call
-                    .total_supply(contract_addr.as_str(), Some(block_number))
-                    .await
-                {
-                    model.total_supply =
-                        Some(BigDecimal::from_str(total_supply.to_string().as_str()).unwrap());
-                    model.total_supply_updated_at_block = Some(block_number as i64);
-                }
-                tracing::info!(
-                    "update total_supply contract_address: {:?}, total_supply: {:?}, block_height: {}",
-                    contract_addr.clone(),
-                    &model.total_supply.clone(),
-                    block_number,
-                );
-                if let Err(e) = Mutation::update_total_supply(conn.as_ref(), &model).await {
-                    return Err(anyhow!("Handler total supply: {:?}", e.to_string()));
-                }
-            }
-            Ok(())
+pub async fn handle_holder_counts(
+    conn: &DbConn,
+    shutdown: &CancellationToken,
+) -> Result<u64, Error> {
+    let mut drifted = 0;
+    let mut page = 1;
+    loop {
+        if shutdown.is_cancelled() {
+            break;
         }
-        Err(e) => Err(anyhow!("Handler total supply: {:?}", e.to_string())),
+        let (models, num_pages) = Query::find_in_page(conn, page, HOLDER_COUNT_PAGE_SIZE)
+            .await
+            .map_err(|e| anyhow!("Handler holder count: {:?}", e.to_string()))?;
+        let hashes = models
+            .into_iter()
+            .map(|model| model.contract_address_hash)
+            .collect::<Vec<_>>();
+        if !hashes.is_empty() {
+            drifted += Mutation::reconcile_holder_counts(conn, hashes)
+                .await
+                .map_err(|e| anyhow!("Handler holder count: {:?}", e.to_string()))?
+                .rows_affected;
+        }
+        if page >= num_pages {
+            break;
+        }
+        page += 1;
     }
+
+    Ok(drifted)
 }
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use futures::StreamExt;
+use repo::dal::{token::Query as TokenQuery, token_instance::Query as TokenInstanceQuery};
+use sea_orm::{DatabaseConnection, DbConn};
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::common::err::ScannerError;
+use crate::contracts::nft::INFTCall;
+use crate::handler::token_instance::{fetch_instance_metadata, save_instance_metadata};
+use crate::indexer::token_instance::MetadataFetcher;
+
+const TOKEN_INSTANCE_BATCH_SIZE: u64 = 50;
+const TOKEN_INSTANCE_CONCURRENCY: usize = 10;
+
+/// fetch the metadata of new token instances, failed fetches are retried with backoff and keep
+/// their error once they ran out of retries.
+pub fn token_instance_task(
+    nft_call: Arc<INFTCall>,
+    fetcher: Arc<dyn MetadataFetcher>,
+    conn: Arc<DatabaseConnection>,
+    ipfs_gateway: Option<String>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(10));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            match handle_token_instances(
+                nft_call.as_ref(),
+                fetcher.as_ref(),
+                conn.as_ref(),
+                ipfs_gateway.as_deref(),
+            )
+            .await
+            {
+                Ok(_) => (),
+                Err(err) => tracing::error!(message = "token instance task", err = ?err),
+            };
+        }
+        tracing::info!("token instance task stopped");
+    })
+}
+
+pub async fn handle_token_instances(
+    nft_call: &INFTCall,
+    fetcher: &dyn MetadataFetcher,
+    conn: &DbConn,
+    ipfs_gateway: Option<&str>,
+) -> anyhow::Result<()> {
+    let now = Utc::now().naive_utc();
+    let instances = TokenInstanceQuery::find_unfetched(conn, now, TOKEN_INSTANCE_BATCH_SIZE)
+        .await
+        .map_err(ScannerError::Query)?;
+    if instances.is_empty() {
+        return Ok(());
+    }
+
+    let contracts = instances
+        .iter()
+        .map(|instance| instance.token_contract_address_hash.clone())
+        .collect::<Vec<_>>();
+    let token_types = TokenQuery::find_by_contract_addresses(conn, contracts)
+        .await
+        .map_err(ScannerError::Query)?
+        .into_iter()
+        .map(|token| (token.contract_address_hash, token.r#type))
+        .collect::<HashMap<_, _>>();
+
+    let results = futures::stream::iter(instances)
+        .map(|instance| {
+            let token_type = token_types
+                .get(&instance.token_contract_address_hash)
+                .cloned()
+                .unwrap_or_default();
+            async move {
+                let result = fetch_instance_metadata(
+                    nft_call,
+                    fetcher,
+                    ipfs_gateway,
+                    &instance,
+                    &token_type,
+                )
+                .await;
+                (instance, result)
+            }
+        })
+        .buffer_unordered(TOKEN_INSTANCE_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    for (instance, result) in results.iter() {
+        let token = ethers::types::H160::from_slice(&instance.token_contract_address_hash);
+        match save_instance_metadata(conn, instance, result, now).await {
+            Ok(true) => {}
+            Ok(false) => {
+                if let Err(err) = result {
+                    tracing::warn!(
+                        "give up fetching metadata of token {:#x} id {}: {}",
+                        token,
+                        instance.token_id,
+                        err
+                    );
+                }
+            }
+            // the other instances of the batch are stored anyway
+            Err(err) => tracing::error!(
+                message = "save token instance metadata",
+                token = ?token,
+                token_id = %instance.token_id,
+                err = ?err
+            ),
+        }
+    }
+
+    Ok(())
+}
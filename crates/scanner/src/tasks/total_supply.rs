@@ -1,19 +1,33 @@
-use super::publisher::{BroadcastType, Publisher};
-use crate::{cache::block_number::Cache, common::err::FetchError, contracts::erc20::IERC20Call};
+use super::publisher::{BroadcastType, Event, Publisher};
+use crate::{
+    cache::block_number::{Cache, CacheKey},
+    common::err::FetchError,
+    contracts::erc20::IERC20Call,
+};
 use bigdecimal::BigDecimal;
 use chrono::Utc;
 use common::chain_ident;
+use repo::dal::block::Query as BlockQuery;
 use repo::dal::token::{Mutation, Query};
 use sea_orm::DbConn;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+/// tokens minted or burned in an indexed block
+#[derive(Debug, Clone)]
+pub struct TotalSupplyChanged {
+    pub block_number: i64,
+    pub contracts: Vec<Vec<u8>>,
+}
 
 pub struct TokenTotalSupplyOnDemand {
     ttl_in_blocks: i64,
     erc20_call: Arc<IERC20Call>,
     chain_publisher: Arc<Publisher>,
     conn: Arc<DbConn>,
-    block_number_cache: Arc<Cache>,
+    block_number_cache: RwLock<Cache>,
 }
 
 impl TokenTotalSupplyOnDemand {
@@ -22,29 +36,50 @@ impl TokenTotalSupplyOnDemand {
         erc20_call: Arc<IERC20Call>,
         chain_publisher: Arc<Publisher>,
         conn: Arc<DbConn>,
-        block_number_cache: Arc<Cache>,
     ) -> Self {
         TokenTotalSupplyOnDemand {
             ttl_in_blocks,
             erc20_call,
             chain_publisher,
             conn,
-            block_number_cache,
+            block_number_cache: RwLock::new(Cache::new()),
         }
     }
 
+    pub fn update_max_block_number(&self, block_number: i64) {
+        let mut cache = self.block_number_cache.write().unwrap();
+        let old = cache.get_max();
+        let _ = cache.handle_update(CacheKey::Max, old, block_number);
+    }
+
     pub async fn trigger_fetch(&self, address: Vec<u8>) -> Result<(), FetchError> {
         self.fetch_and_update(address).await?;
         Ok(())
     }
 
+    async fn max_block_number(&self) -> Result<i64, FetchError> {
+        let max = self.block_number_cache.read().unwrap().get_max();
+        match max {
+            Some(max) => Ok(max),
+            None => {
+                let max = BlockQuery::find_max_number(self.conn.as_ref())
+                    .await
+                    .map_err(|e| FetchError::TokenTotalSupply(e.to_string()))?;
+                self.update_max_block_number(max);
+                Ok(max)
+            }
+        }
+    }
+
     async fn fetch_and_update(&self, address: Vec<u8>) -> Result<(), FetchError> {
         let token = Query::find_by_hash(self.conn.as_ref(), address)
             .await
             .map_err(|_| FetchError::TokenNotFound)?;
 
-        let max_block_number = self.block_number_cache.get_max().unwrap();
-        let mut token = token.unwrap();
+        let max_block_number = self.max_block_number().await?;
+        let Some(mut token) = token else {
+            return Err(FetchError::TokenNotFound);
+        };
         if token.total_supply_updated_at_block.is_none()
             || max_block_number - token.total_supply_updated_at_block.unwrap() > self.ttl_in_blocks
         {
@@ -52,9 +87,9 @@ impl TokenTotalSupplyOnDemand {
 
             let total_supply = self
                 .erc20_call
-                .total_supply(token_address_hash.as_str(), None)
+                .total_supply(token_address_hash.as_str(), Some(max_block_number as u64))
                 .await
-                .unwrap();
+                .map_err(|e| FetchError::TokenTotalSupply(e.to_string()))?;
             token.total_supply_updated_at_block = Some(max_block_number);
             token.updated_at = Utc::now().naive_utc();
             token.total_supply =
@@ -67,15 +102,20 @@ impl TokenTotalSupplyOnDemand {
                     err: e,
                 })?;
 
-            self.chain_publisher
-                .broadcast(
-                    vec![(
-                        "total_supply_update".to_string(),
-                        serde_json::to_string(&updated_token).unwrap(),
-                    )],
-                    BroadcastType::OnDamend,
-                )
-                .await;
+            let event = Event::new(
+                "token_total_supply",
+                serde_json::to_value(&updated_token)
+                    .map_err(|e| FetchError::TokenTotalSupply(e.to_string()))?,
+            )
+            .with_key(token_address_hash)
+            .with_block(max_block_number, None);
+            if let Err(err) = self
+                .chain_publisher
+                .broadcast(vec![event], BroadcastType::OnDamend)
+                .await
+            {
+                tracing::error!(message = "publish token total supply", err = ?err);
+            }
 
             Ok(())
         } else {
@@ -83,3 +123,34 @@ impl TokenTotalSupplyOnDemand {
         }
     }
 }
+
+/// refresh the total supply of the tokens minted or burned by the block task.
+pub fn token_total_supply_task(
+    on_demand: Arc<TokenTotalSupplyOnDemand>,
+    mut receiver: UnboundedReceiver<TotalSupplyChanged>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        loop {
+            let changed = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                changed = receiver.recv() => match changed {
+                    Some(changed) => changed,
+                    None => break,
+                },
+            };
+
+            on_demand.update_max_block_number(changed.block_number);
+            for contract in changed.contracts.into_iter() {
+                if let Err(err) = on_demand.trigger_fetch(contract.clone()).await {
+                    tracing::error!(
+                        message = "token total supply task",
+                        contract = chain_ident!(&contract),
+                        err = ?err
+                    );
+                }
+            }
+        }
+        tracing::info!("token total supply task stopped");
+    })
+}
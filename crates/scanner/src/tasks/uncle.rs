@@ -0,0 +1,32 @@
+use std::{sync::Arc, time::Duration};
+
+use sea_orm::DatabaseConnection;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::evms::eth::EthCli;
+use crate::handler::uncle::fetch_uncles;
+
+const UNCLE_BATCH_SIZE: u64 = 50;
+
+/// fetch the headers of uncles referenced by indexed blocks.
+pub fn uncle_task(
+    cli: Arc<EthCli>,
+    conn: Arc<DatabaseConnection>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(10));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            match fetch_uncles(cli.as_ref(), conn.as_ref(), UNCLE_BATCH_SIZE).await {
+                Ok(0) => (),
+                Ok(count) => tracing::info!("fetched {} uncle blocks", count),
+                Err(err) => tracing::error!(message = "uncle task", err = ?err),
+            };
+        }
+    })
+}
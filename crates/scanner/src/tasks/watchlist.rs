@@ -0,0 +1,320 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use common::chain_ident;
+use config::chain::Watchlist;
+use entities::{
+    account_watchlist_deliveries::Model as DeliveryModel,
+    account_watchlist_notifications::Model as NotificationModel,
+    account_watchlist_webhooks::Model as WebhookModel,
+};
+use futures::{stream, StreamExt};
+use hmac::{Hmac, Mac};
+use repo::dal::{
+    account_watchlist_delivery::{Mutation as DeliveryMutation, Query as DeliveryQuery},
+    account_watchlist_notification::Query as NotificationQuery,
+    account_watchlist_webhook::Query as WebhookQuery,
+};
+use sea_orm::{DatabaseConnection, DbConn};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::{task::JoinHandle, time::interval};
+use tokio_util::sync::CancellationToken;
+
+use crate::common::err::ScannerError;
+
+pub const DELIVERY_HEADER: &str = "X-Watchlist-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-Watchlist-Timestamp";
+/// `sha256=<hex>` of the HMAC-SHA256 of `<timestamp>.<body>` keyed by the webhook secret
+pub const SIGNATURE_HEADER: &str = "X-Watchlist-Signature";
+// longest wait between two attempts of a delivery
+const MAX_RETRY_DELAY: u64 = 24 * 60 * 60;
+// webhooks delivered to at the same time
+const DELIVERY_CONCURRENCY: usize = 10;
+
+/// posts the notifications to the webhooks, any status but 2xx fails the attempt.
+pub struct WebhookDeliverer {
+    client: reqwest::Client,
+    max_attempts: i32,
+    retry_delay: u64,
+}
+
+impl WebhookDeliverer {
+    pub fn new(cfg: &Watchlist) -> Self {
+        WebhookDeliverer {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(cfg.timeout()))
+                .build()
+                .unwrap(),
+            max_attempts: cfg.max_attempts(),
+            retry_delay: cfg.retry_delay(),
+        }
+    }
+
+    async fn deliver(
+        &self,
+        webhook: &WebhookModel,
+        delivery: &DeliveryModel,
+        notification: &NotificationModel,
+    ) -> Result<()> {
+        let body = serde_json::to_vec(&to_payload(delivery, notification))?;
+        let timestamp = Utc::now().timestamp();
+        self.client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(DELIVERY_HEADER, delivery.id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, sign(&webhook.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map(|_| ())
+            .map_err(|err| anyhow!("deliver notification to {}: {}", webhook.url, err))
+    }
+}
+
+/// deliver the due watchlist notifications, failed deliveries are retried with backoff until
+/// they run out of attempts and are left dead.
+pub fn watchlist_delivery_task(
+    conn: Arc<DatabaseConnection>,
+    deliverer: Arc<WebhookDeliverer>,
+    cfg: Watchlist,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::task::spawn(async move {
+        let mut interval = interval(Duration::from_secs(cfg.interval()));
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            while !shutdown.is_cancelled() {
+                match handle_watchlist_deliveries(
+                    conn.as_ref(),
+                    deliverer.as_ref(),
+                    cfg.batch_size(),
+                )
+                .await
+                {
+                    Ok(handled) if handled as u64 == cfg.batch_size() => continue,
+                    Ok(_) => break,
+                    Err(err) => {
+                        tracing::error!(message = "watchlist delivery task", err = ?err);
+                        break;
+                    }
+                }
+            }
+        }
+        tracing::info!("watchlist delivery task stopped");
+    })
+}
+
+/// deliver a batch of due deliveries, the webhooks in parallel and the deliveries of a webhook
+/// in order. returns the number of attempted deliveries, the rest of the due deliveries of a
+/// webhook failing an attempt are postponed to its next attempt.
+pub async fn handle_watchlist_deliveries(
+    conn: &DbConn,
+    deliverer: &WebhookDeliverer,
+    batch_size: u64,
+) -> Result<usize> {
+    let deliveries = DeliveryQuery::find_due(conn, Utc::now().naive_utc(), batch_size)
+        .await
+        .map_err(ScannerError::Query)?;
+    if deliveries.is_empty() {
+        return Ok(0);
+    }
+
+    let notifications = NotificationQuery::find_by_ids(
+        conn,
+        deliveries.iter().map(|d| d.notification_id).collect(),
+    )
+    .await
+    .map_err(ScannerError::Query)?
+    .into_iter()
+    .map(|notification| (notification.id, notification))
+    .collect::<HashMap<_, _>>();
+    let webhooks =
+        WebhookQuery::find_by_ids(conn, deliveries.iter().map(|d| d.webhook_id).collect())
+            .await
+            .map_err(ScannerError::Query)?
+            .into_iter()
+            .map(|webhook| (webhook.id, webhook))
+            .collect::<HashMap<_, _>>();
+
+    let mut by_webhook: HashMap<i64, Vec<&DeliveryModel>> = HashMap::new();
+    for delivery in deliveries.iter() {
+        by_webhook
+            .entry(delivery.webhook_id)
+            .or_default()
+            .push(delivery);
+    }
+
+    let sends = by_webhook
+        .into_iter()
+        .map(|(webhook_id, deliveries)| {
+            deliver_webhook(
+                conn,
+                deliverer,
+                webhooks.get(&webhook_id),
+                &notifications,
+                deliveries,
+            )
+        })
+        .collect::<Vec<_>>();
+    let results = stream::iter(sends)
+        .buffer_unordered(DELIVERY_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut attempted = 0;
+    for result in results.into_iter() {
+        attempted += result?;
+    }
+    Ok(attempted)
+}
+
+async fn deliver_webhook(
+    conn: &DbConn,
+    deliverer: &WebhookDeliverer,
+    webhook: Option<&WebhookModel>,
+    notifications: &HashMap<i64, NotificationModel>,
+    deliveries: Vec<&DeliveryModel>,
+) -> Result<usize> {
+    let mut attempted = 0;
+    for delivery in deliveries.into_iter() {
+        attempted += 1;
+        let attempts = delivery.attempts + 1;
+        let (result, webhook_failed) = match (notifications.get(&delivery.notification_id), webhook)
+        {
+            (Some(notification), Some(webhook)) if webhook.enabled => {
+                let result = deliverer.deliver(webhook, delivery, notification).await;
+                let failed = result.is_err();
+                (result, failed)
+            }
+            (None, _) => (
+                Err(anyhow!("notification {} removed", delivery.notification_id)),
+                false,
+            ),
+            _ => (
+                Err(anyhow!("webhook {} disabled", delivery.webhook_id)),
+                false,
+            ),
+        };
+
+        let next_attempt_at = retry_delay(attempts, deliverer.max_attempts, deliverer.retry_delay)
+            .map(|delay| Utc::now().naive_utc() + delay);
+        let res = match result {
+            Ok(_) => DeliveryMutation::mark_delivered(conn, delivery.id, attempts).await,
+            Err(err) => {
+                if next_attempt_at.is_none() {
+                    tracing::warn!(
+                        "watchlist delivery {} dead after {} attempts: {}",
+                        delivery.id,
+                        attempts,
+                        err
+                    );
+                }
+                DeliveryMutation::mark_failed(
+                    conn,
+                    delivery.id,
+                    attempts,
+                    next_attempt_at,
+                    err.to_string(),
+                )
+                .await
+            }
+        };
+        res.map_err(|e| ScannerError::Update {
+            src: "update watchlist delivery".to_string(),
+            err: e,
+        })?;
+
+        // the webhook is likely down, its other deliveries would fail the same way. they are
+        // pushed back together, otherwise they stay in front of the due deliveries of the
+        // other webhooks.
+        if webhook_failed {
+            let postpone_to = next_attempt_at.unwrap_or_else(|| {
+                Utc::now().naive_utc() + chrono::Duration::seconds(deliverer.retry_delay as i64)
+            });
+            DeliveryMutation::postpone_by_webhook(conn, delivery.webhook_id, postpone_to)
+                .await
+                .map_err(|e| ScannerError::Update {
+                    src: "postpone watchlist deliveries".to_string(),
+                    err: e,
+                })?;
+            break;
+        }
+    }
+
+    Ok(attempted)
+}
+
+/// wait before the attempt after `attempts`, none once they are used up.
+fn retry_delay(attempts: i32, max_attempts: i32, retry_delay: u64) -> Option<chrono::Duration> {
+    if attempts >= max_attempts {
+        return None;
+    }
+    let delay = retry_delay
+        .saturating_mul(1u64 << (attempts - 1).clamp(0, 32))
+        .min(MAX_RETRY_DELAY);
+    Some(chrono::Duration::seconds(delay as i64))
+}
+
+fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn to_payload(delivery: &DeliveryModel, notification: &NotificationModel) -> Value {
+    let hash = |hash: &Option<Vec<u8>>| hash.as_ref().map(|hash| chain_ident!(hash));
+    let text = |text: &Option<Vec<u8>>| {
+        text.as_ref()
+            .map(|text| String::from_utf8_lossy(text).to_string())
+    };
+    json!({
+        "delivery_id": delivery.id,
+        "notification_id": notification.id,
+        "watchlist_address_id": notification.watchlist_address_id,
+        "name": text(&notification.name),
+        "direction": notification.direction,
+        "type": notification.r#type,
+        "method": notification.method,
+        "block_number": notification.block_number,
+        "transaction_hash": hash(&notification.transaction_hash),
+        "from_address_hash": hash(&notification.from_address_hash),
+        "to_address_hash": hash(&notification.to_address_hash),
+        "amount": notification.amount.map(|amount| amount.to_string()),
+        "tx_fee": notification.tx_fee.map(|fee| fee.to_string()),
+        "subject": text(&notification.subject),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_delay, sign};
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1, 4, 30), Some(chrono::Duration::seconds(30)));
+        assert_eq!(retry_delay(3, 4, 30), Some(chrono::Duration::seconds(120)));
+        assert_eq!(retry_delay(4, 4, 30), None);
+        assert_eq!(
+            retry_delay(40, 64, 30),
+            Some(chrono::Duration::seconds(24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1700000000, br#"{"delivery_id":1}"#),
+            "sha256=667612fbaf0c1572efbb9d9ed86fefc169885a1346d5cf840fdeac88d676d10c"
+        );
+    }
+}
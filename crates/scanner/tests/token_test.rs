@@ -23,7 +23,7 @@ fn common_dir_path() -> PathBuf {
 #[test]
 #[ignore = "just for data source"]
 fn test_get_data_from_remote() {
-    let eth_cli = EthCli::new("https://rpc.ankr.com/eth_goerli");
+    let eth_cli = EthCli::new("https://rpc.ankr.com/eth_goerli").unwrap();
     let tx_hash: H256 = "0xb85ed2e0516654241786439ebbd75e9c116881086a0f3ddd9f816b526bf6600d"
         .parse()
         .unwrap();
@@ -31,6 +31,7 @@ fn test_get_data_from_remote() {
     let runtime = Runtime::new().unwrap();
     let recipet = runtime
         .block_on(eth_cli.get_transaction_receipt(tx_hash))
+        .unwrap()
         .unwrap();
     json!(recipet);
     let json_str = serde_json::to_string(&recipet).unwrap();